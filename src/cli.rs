@@ -1,12 +1,14 @@
 use anyhow::{bail, Context as _};
 use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
-use cargo_metadata::Metadata;
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand};
 use easy_ext::ext;
 use env_logger::fmt::WriteStyle;
+use itertools::Itertools as _;
 use serde::Deserialize;
 use std::{
     env,
-    io::Write as _,
+    ffi::OsString,
+    io::{self, Write as _},
     process::{self, Stdio},
     str,
 };
@@ -14,7 +16,13 @@ use structopt::{clap::AppSettings, StructOpt};
 use strum::{EnumString, IntoStaticStr, VariantNames};
 use termcolor::{BufferedStandardStream, ColorSpec, WriteColor};
 
-use crate::{Cp, Deactivate, Exclude, Focus, Include, Mv, New, Rm};
+use crate::{
+    config::{Config, FocusMode, Template},
+    Bump, Changed, CheckPublish, Cp, Deactivate, Eject, Exclude, Exec, Focus, Fork, Graph, Include,
+    Link, Merge, Mv, New, Patch, PatchList, PublishOrder, Rm, Selection, Sort, Split, StashApply,
+    StashList, StashPush, TrashEmpty, TrashList, TrashRestore, Undo, Unfork, Unlink, Unpatch,
+    Version, Which,
+};
 
 #[derive(StructOpt, Debug)]
 #[structopt(
@@ -42,6 +50,14 @@ pub enum CargoMember {
     #[structopt(author, visible_alias("d"))]
     Deactivate(CargoMemberDeactivate),
 
+    /// Sort and deduplicate `workspace.{members, exclude, default-members}`
+    #[structopt(author)]
+    Sort(CargoMemberSort),
+
+    /// Save and restore snapshots of `workspace.{members, exclude, default-members}`
+    #[structopt(author)]
+    Stash(CargoMemberStash),
+
     /// `include` a package and `deactivate`/`exclude` the others
     #[structopt(author, visible_alias("f"))]
     Focus(CargoMemberFocus),
@@ -61,6 +77,78 @@ pub enum CargoMember {
     /// Move a workspace member
     #[structopt(author, visible_alias("m"))]
     Mv(CargoMemberMv),
+
+    /// List, restore, or delete the packages removed by `rm`
+    #[structopt(author)]
+    Trash(CargoMemberTrash),
+
+    /// Revert the last operations that modified the workspace
+    #[structopt(author)]
+    Undo(CargoMemberUndo),
+
+    /// Copy a workspace member out of the workspace as a standalone package
+    #[structopt(author)]
+    Eject(CargoMemberEject),
+
+    /// Move workspace members into a new workspace
+    #[structopt(author)]
+    Split(CargoMemberSplit),
+
+    /// Copy or move the members of another workspace into this workspace
+    #[structopt(author)]
+    Merge(CargoMemberMerge),
+
+    /// Copy a registry dependency into the workspace and patch it with the copy
+    #[structopt(author)]
+    Fork(CargoMemberFork),
+
+    /// Undo `fork`
+    #[structopt(author)]
+    Unfork(CargoMemberUnfork),
+
+    /// Override an external package with a local one in `patch`
+    #[structopt(author)]
+    Patch(CargoMemberPatch),
+
+    /// Remove an entry from `patch`
+    #[structopt(author)]
+    Unpatch(CargoMemberUnpatch),
+
+    /// Add a workspace member to the dependencies of another member
+    #[structopt(author)]
+    Link(CargoMemberLink),
+
+    /// Remove a workspace member from the dependencies of another member
+    #[structopt(author)]
+    Unlink(CargoMemberUnlink),
+
+    /// Bump versions of members and update the requirements on them
+    #[structopt(author)]
+    Version(CargoMemberVersion),
+
+    /// Print the publishable members in an order they can be published in
+    #[structopt(author)]
+    PublishOrder(CargoMemberPublishOrder),
+
+    /// Check the publishable members for problems that would block or degrade publishing
+    #[structopt(author)]
+    CheckPublish(CargoMemberCheckPublish),
+
+    /// Print the dependency graph of the workspace members
+    #[structopt(author)]
+    Graph(CargoMemberGraph),
+
+    /// Print the packages that own the files
+    #[structopt(author)]
+    Which(CargoMemberWhich),
+
+    /// Print the members affected by the changes since a git revision
+    #[structopt(author)]
+    Changed(CargoMemberChanged),
+
+    /// Run a command in the directory of each selected member
+    #[structopt(author)]
+    Exec(CargoMemberExec),
 }
 
 impl CargoMember {
@@ -69,11 +157,31 @@ impl CargoMember {
             Self::Include(CargoMemberInclude { color, .. })
             | Self::Exclude(CargoMemberExclude { color, .. })
             | Self::Deactivate(CargoMemberDeactivate { color, .. })
+            | Self::Sort(CargoMemberSort { color, .. })
             | Self::Focus(CargoMemberFocus { color, .. })
             | Self::New(CargoMemberNew { color, .. })
             | Self::Cp(CargoMemberCp { color, .. })
             | Self::Rm(CargoMemberRm { color, .. })
-            | Self::Mv(CargoMemberMv { color, .. }) => color,
+            | Self::Mv(CargoMemberMv { color, .. })
+            | Self::Undo(CargoMemberUndo { color, .. })
+            | Self::Eject(CargoMemberEject { color, .. })
+            | Self::Split(CargoMemberSplit { color, .. })
+            | Self::Merge(CargoMemberMerge { color, .. })
+            | Self::Fork(CargoMemberFork { color, .. })
+            | Self::Unfork(CargoMemberUnfork { color, .. })
+            | Self::Patch(CargoMemberPatch { color, .. })
+            | Self::Unpatch(CargoMemberUnpatch { color, .. })
+            | Self::Link(CargoMemberLink { color, .. })
+            | Self::Unlink(CargoMemberUnlink { color, .. })
+            | Self::Version(CargoMemberVersion { color, .. })
+            | Self::PublishOrder(CargoMemberPublishOrder { color, .. })
+            | Self::CheckPublish(CargoMemberCheckPublish { color, .. })
+            | Self::Graph(CargoMemberGraph { color, .. })
+            | Self::Which(CargoMemberWhich { color, .. })
+            | Self::Changed(CargoMemberChanged { color, .. })
+            | Self::Exec(CargoMemberExec { color, .. }) => color,
+            Self::Stash(ref stash) => stash.color(),
+            Self::Trash(ref trash) => trash.color(),
         }
     }
 }
@@ -101,6 +209,24 @@ pub struct CargoMemberInclude {
     #[structopt(long)]
     pub force: bool,
 
+    /// Dissolve nested workspaces into this workspace
+    #[structopt(long)]
+    pub absorb: bool,
+
+    /// How to update `Cargo.lock`: edit the path packages, resolve again, or leave it
+    #[structopt(long, value_name("MODE"), possible_values(self::LockMode::VARIANTS))]
+    pub lock_mode: Option<self::LockMode>,
+
+    /// Sort `workspace.{members, exclude, default-members}` even if `sort-members` is not
+    /// configured
+    #[structopt(long)]
+    pub sort: bool,
+
+    /// Do not sort `workspace.{members, exclude, default-members}` even if `sort-members` is
+    /// configured
+    #[structopt(long, conflicts_with("sort"))]
+    pub no_sort: bool,
+
     /// Dry run. Also enables `--frozen` and `--locked`
     #[structopt(long)]
     pub dry_run: bool,
@@ -111,9 +237,8 @@ pub struct CargoMemberInclude {
 
 #[derive(StructOpt, Debug)]
 pub struct CargoMemberExclude {
-    /// [cargo] Package(s) to exclude
-    #[structopt(short, long, value_name("SPEC"), min_values(1), number_of_values(1))]
-    pub package: Vec<String>,
+    #[structopt(flatten)]
+    pub selection: SelectionOpt,
 
     /// [cargo] Path to Cargo.toml
     #[structopt(long, value_name("PATH"))]
@@ -132,6 +257,20 @@ pub struct CargoMemberExclude {
     #[structopt(long)]
     pub offline: bool,
 
+    /// How to update `Cargo.lock`: edit the path packages, resolve again, or leave it
+    #[structopt(long, value_name("MODE"), possible_values(self::LockMode::VARIANTS))]
+    pub lock_mode: Option<self::LockMode>,
+
+    /// Sort `workspace.{members, exclude, default-members}` even if `sort-members` is not
+    /// configured
+    #[structopt(long)]
+    pub sort: bool,
+
+    /// Do not sort `workspace.{members, exclude, default-members}` even if `sort-members` is
+    /// configured
+    #[structopt(long, conflicts_with("sort"))]
+    pub no_sort: bool,
+
     /// Dry run. Also enables `--frozen` and `--locked`
     #[structopt(long)]
     pub dry_run: bool,
@@ -142,9 +281,8 @@ pub struct CargoMemberExclude {
 
 #[derive(StructOpt, Debug)]
 pub struct CargoMemberDeactivate {
-    /// [cargo] Package(s) to exclude
-    #[structopt(short, long, value_name("SPEC"), min_values(1), number_of_values(1))]
-    pub package: Vec<String>,
+    #[structopt(flatten)]
+    pub selection: SelectionOpt,
 
     /// [cargo] Path to Cargo.toml
     #[structopt(long, value_name("PATH"))]
@@ -163,6 +301,20 @@ pub struct CargoMemberDeactivate {
     #[structopt(long)]
     pub offline: bool,
 
+    /// How to update `Cargo.lock`: edit the path packages, resolve again, or leave it
+    #[structopt(long, value_name("MODE"), possible_values(self::LockMode::VARIANTS))]
+    pub lock_mode: Option<self::LockMode>,
+
+    /// Sort `workspace.{members, exclude, default-members}` even if `sort-members` is not
+    /// configured
+    #[structopt(long)]
+    pub sort: bool,
+
+    /// Do not sort `workspace.{members, exclude, default-members}` even if `sort-members` is
+    /// configured
+    #[structopt(long, conflicts_with("sort"))]
+    pub no_sort: bool,
+
     /// Dry run. Also enables `--frozen` and `--locked`
     #[structopt(long)]
     pub dry_run: bool,
@@ -171,12 +323,167 @@ pub struct CargoMemberDeactivate {
     pub paths: Vec<PathBuf>,
 }
 
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberSort {
+    /// Put each entry on its own line when an array has more than N entries
+    #[structopt(long, value_name("N"))]
+    pub multiline_threshold: Option<usize>,
+
+    /// Fail instead of writing if the arrays are not sorted
+    #[structopt(long, conflicts_with("dry-run"))]
+    pub check: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum CargoMemberStash {
+    /// Save the current membership
+    #[structopt(author)]
+    Push(CargoMemberStashPush),
+
+    /// Print the saved snapshots, newest first
+    #[structopt(author)]
+    List(CargoMemberStashList),
+
+    /// Restore a snapshot and drop it
+    #[structopt(author)]
+    Pop(CargoMemberStashApply),
+
+    /// Restore a snapshot and keep it
+    #[structopt(author)]
+    Apply(CargoMemberStashApply),
+}
+
+impl CargoMemberStash {
+    fn color(&self) -> self::ColorChoice {
+        match *self {
+            Self::Push(CargoMemberStashPush { color, .. })
+            | Self::List(CargoMemberStashList { color, .. })
+            | Self::Pop(CargoMemberStashApply { color, .. })
+            | Self::Apply(CargoMemberStashApply { color, .. }) => color,
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberStashPush {
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Name of the snapshot
+    pub name: Option<String>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberStashList {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberStashApply {
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// How to update `Cargo.lock`: edit the path packages, resolve again, or leave it
+    #[structopt(long, value_name("MODE"), possible_values(self::LockMode::VARIANTS))]
+    pub lock_mode: Option<self::LockMode>,
+
+    /// Sort `workspace.{members, exclude, default-members}` even if `sort-members` is not
+    /// configured
+    #[structopt(long)]
+    pub sort: bool,
+
+    /// Do not sort `workspace.{members, exclude, default-members}` even if `sort-members` is
+    /// configured
+    #[structopt(long, conflicts_with("sort"))]
+    pub no_sort: bool,
+
+    /// Index or name of the snapshot. Defaults to the newest one
+    pub stash: Option<String>,
+}
+
 #[derive(StructOpt, Debug)]
 pub struct CargoMemberFocus {
     /// Add existing packages to `workspace.exclude`
     #[structopt(long)]
     pub exclude: bool,
 
+    /// Remove existing packages from `workspace.members` only. This is the default unless
+    /// configured otherwise
+    #[structopt(long, conflicts_with("exclude"))]
+    pub deactivate: bool,
+
     /// Dry run. Also enables `--frozen` and `--locked`
     #[structopt(long)]
     pub dry_run: bool,
@@ -220,6 +527,10 @@ pub struct CargoMemberNew {
     )]
     pub vcs: Option<String>,
 
+    /// [cargo-new] Use a binary (application) template
+    #[structopt(long, conflicts_with("lib"))]
+    pub bin: bool,
+
     /// [cargo-new] Use a library template
     #[structopt(long)]
     pub lib: bool,
@@ -285,9 +596,8 @@ pub struct CargoMemberCp {
 
 #[derive(StructOpt, Debug)]
 pub struct CargoMemberRm {
-    /// [cargo] Package(s) to exclude
-    #[structopt(short, long, value_name("SPEC"), min_values(1), number_of_values(1))]
-    pub package: Vec<String>,
+    #[structopt(flatten)]
+    pub selection: SelectionOpt,
 
     /// [cargo] Path to Cargo.toml
     #[structopt(long, value_name("PATH"))]
@@ -310,6 +620,24 @@ pub struct CargoMemberRm {
     #[structopt(long)]
     pub force: bool,
 
+    /// Delete the packages instead of moving them into `target/cargo-member/trash`
+    #[structopt(long)]
+    pub permanent: bool,
+
+    /// How to update `Cargo.lock`: edit the path packages, resolve again, or leave it
+    #[structopt(long, value_name("MODE"), possible_values(self::LockMode::VARIANTS))]
+    pub lock_mode: Option<self::LockMode>,
+
+    /// Sort `workspace.{members, exclude, default-members}` even if `sort-members` is not
+    /// configured
+    #[structopt(long)]
+    pub sort: bool,
+
+    /// Do not sort `workspace.{members, exclude, default-members}` even if `sort-members` is
+    /// configured
+    #[structopt(long, conflicts_with("sort"))]
+    pub no_sort: bool,
+
     /// Dry run. Also enables `--frozen` and `--locked`
     #[structopt(long)]
     pub dry_run: bool,
@@ -337,6 +665,10 @@ pub struct CargoMemberMv {
     #[structopt(long)]
     pub offline: bool,
 
+    /// How to update `Cargo.lock`: edit the path packages, resolve again, or leave it
+    #[structopt(long, value_name("MODE"), possible_values(self::LockMode::VARIANTS))]
+    pub lock_mode: Option<self::LockMode>,
+
     /// Dry run. Also enables `--frozen` and `--locked`
     #[structopt(long)]
     pub dry_run: bool,
@@ -345,6 +677,10 @@ pub struct CargoMemberMv {
     #[structopt(long)]
     pub no_rename: bool,
 
+    /// Move the member into another workspace
+    #[structopt(long, value_name("PATH"))]
+    pub to_workspace: Option<PathBuf>,
+
     /// Package ID specification
     pub src: String,
 
@@ -352,129 +688,1444 @@ pub struct CargoMemberMv {
     pub dst: PathBuf,
 }
 
-/// Coloring.
-#[derive(EnumString, VariantNames, IntoStaticStr, Clone, Copy, Debug)]
-#[strum(serialize_all = "kebab-case")]
-pub enum ColorChoice {
-    Auto,
-    Always,
-    Never,
+#[derive(StructOpt, Debug)]
+pub enum CargoMemberTrash {
+    /// Print the removed packages, newest first
+    #[structopt(author)]
+    List(CargoMemberTrashList),
+
+    /// Move a removed package back and add it to `workspace.members`
+    #[structopt(author)]
+    Restore(CargoMemberTrashRestore),
+
+    /// Delete the removed packages for good
+    #[structopt(author)]
+    Empty(CargoMemberTrashEmpty),
 }
 
-impl From<self::ColorChoice> for WriteStyle {
-    fn from(choice: self::ColorChoice) -> Self {
-        match choice {
-            self::ColorChoice::Auto => Self::Auto,
-            self::ColorChoice::Always => Self::Always,
-            self::ColorChoice::Never => Self::Never,
+impl CargoMemberTrash {
+    fn color(&self) -> self::ColorChoice {
+        match *self {
+            Self::List(CargoMemberTrashList { color, .. })
+            | Self::Restore(CargoMemberTrashRestore { color, .. })
+            | Self::Empty(CargoMemberTrashEmpty { color, .. }) => color,
         }
     }
 }
 
-#[derive(Debug)]
-pub struct Context<W> {
-    cwd: PathBuf,
-    stderr: W,
-    stderr_redirection: Stdio,
-}
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberTrashList {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
 
-impl<W> Context<W> {
-    pub fn new(stderr: W) -> anyhow::Result<Self> {
-        let cwd = env::current_dir()
-            .with_context(|| "failed to get CWD")?
-            .try_into()
-            .expect("CWD is not UTF-8");
-        let stderr_redirection = Stdio::inherit();
-        Ok(Self {
-            cwd,
-            stderr,
-            stderr_redirection,
-        })
-    }
-}
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
 
-pub fn init_logger(color: self::ColorChoice) {
-    env_logger::Builder::from_default_env()
-        .write_style(color.into())
-        .init();
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
 }
 
-pub fn stderr(color: self::ColorChoice) -> BufferedStandardStream {
-    BufferedStandardStream::stderr(match color {
-        self::ColorChoice::Auto if atty::is(atty::Stream::Stderr) => termcolor::ColorChoice::Auto,
-        self::ColorChoice::Always => termcolor::ColorChoice::Always,
-        self::ColorChoice::Auto | self::ColorChoice::Never => termcolor::ColorChoice::Never,
-    })
-}
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberTrashRestore {
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
 
-pub fn exit_with_error(error: anyhow::Error, color: self::ColorChoice) -> ! {
-    let mut stderr = BufferedStandardStream::stderr(match color {
-        self::ColorChoice::Auto if atty::is(atty::Stream::Stderr) => termcolor::ColorChoice::Auto,
-        self::ColorChoice::Always => termcolor::ColorChoice::Always,
-        self::ColorChoice::Auto | self::ColorChoice::Never => termcolor::ColorChoice::Never,
-    });
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
 
-    let _ = stderr.set_color(
-        ColorSpec::new()
-            .set_fg(Some(termcolor::Color::Red))
-            .set_bold(true)
-            .set_reset(false),
-    );
-    let _ = stderr.write_all(b"error: ");
-    let _ = stderr.reset();
-    let _ = writeln!(stderr, "{}", error);
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
 
-    for error in error.chain().skip(1) {
-        let _ = writeln!(stderr, "\nCaused by:\n  {}", error);
-    }
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
 
-    let _ = stderr.flush();
-    process::exit(101);
-}
+    /// How to update `Cargo.lock`: edit the path packages, resolve again, or leave it
+    #[structopt(long, value_name("MODE"), possible_values(self::LockMode::VARIANTS))]
+    pub lock_mode: Option<self::LockMode>,
 
-pub fn run(opt: CargoMember, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    /// Sort `workspace.{members, exclude, default-members}` even if `sort-members` is not
+    /// configured
+    #[structopt(long)]
+    pub sort: bool,
+
+    /// Do not sort `workspace.{members, exclude, default-members}` even if `sort-members` is
+    /// configured
+    #[structopt(long, conflicts_with("sort"))]
+    pub no_sort: bool,
+
+    /// Index or directory name of the package. Defaults to the newest one
+    pub item: Option<String>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberTrashEmpty {
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberUndo {
+    /// Number of operations to revert
+    #[structopt(long, value_name("N"), default_value("1"))]
+    pub steps: usize,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberEject {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Keep path dependencies on workspace members instead of replacing them with versions
+    #[structopt(long)]
+    pub keep_paths: bool,
+
+    /// Remove the source member after ejecting
+    #[structopt(long)]
+    pub remove_source: bool,
+
+    /// Package ID specification
+    pub src: String,
+
+    /// Directory
+    pub dst: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberSplit {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Directory for the new workspace
+    #[structopt(long, value_name("DIR"))]
+    pub into: PathBuf,
+
+    /// Package ID specifications
+    #[structopt(required(true))]
+    pub specs: Vec<String>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberMerge {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Directory to put the members in, relative to the workspace root
+    #[structopt(long, value_name("DIR"))]
+    pub prefix: Option<PathBuf>,
+
+    /// Remove the members from the other workspace
+    #[structopt(long)]
+    pub remove_source: bool,
+
+    /// Root directory of the other workspace
+    pub other: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberFork {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Directory to put the fork in, relative to the workspace root
+    #[structopt(long, value_name("DIR"), default_value("forks"))]
+    pub dir: PathBuf,
+
+    /// Add the fork to `workspace.exclude` instead of `workspace.members`
+    #[structopt(long)]
+    pub exclude: bool,
+
+    /// Package ID specification of a registry dependency
+    pub spec: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberUnfork {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Move the patching directory into the trash even if `fork` did not create it
+    #[structopt(long)]
+    pub remove: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Name of the forked package
+    pub name: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberPatch {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// List the entries in `patch` and whether each one is used
+    #[structopt(long, conflicts_with_all(&["registry", "git"]))]
+    pub list: bool,
+
+    /// Registry to patch [default: crates-io]
+    #[structopt(long, value_name("REGISTRY"), conflicts_with("git"))]
+    pub registry: Option<String>,
+
+    /// Git repository to patch
+    #[structopt(long, value_name("URL"))]
+    pub git: Option<String>,
+
+    /// Name of the package to patch
+    #[structopt(required_unless("list"))]
+    pub name: Option<String>,
+
+    /// Path to the local package
+    #[structopt(required_unless("list"))]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberUnpatch {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Registry of the entry
+    #[structopt(long, value_name("REGISTRY"), conflicts_with("git"))]
+    pub registry: Option<String>,
+
+    /// Git repository of the entry
+    #[structopt(long, value_name("URL"))]
+    pub git: Option<String>,
+
+    /// Name of the patched package
+    pub name: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberLink {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Add as a development dependency
+    #[structopt(long, conflicts_with("build"))]
+    pub dev: bool,
+
+    /// Add as a build dependency
+    #[structopt(long)]
+    pub build: bool,
+
+    /// Mark the dependency as optional
+    #[structopt(long)]
+    pub optional: bool,
+
+    /// Features of the dependency to enable
+    #[structopt(long, value_name("FEATURES"), use_delimiter(true))]
+    pub features: Vec<String>,
+
+    /// Version requirement to write. Defaults to the current version of the dependency
+    #[structopt(long, value_name("REQ"))]
+    pub version_req: Option<String>,
+
+    /// Package ID specification of the dependent
+    pub from: String,
+
+    /// Package ID specification of the dependency
+    pub to: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberUnlink {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Only remove from `dev-dependencies`
+    #[structopt(long, conflicts_with("build"))]
+    pub dev: bool,
+
+    /// Only remove from `build-dependencies`
+    #[structopt(long)]
+    pub build: bool,
+
+    /// Package ID specification of the dependent
+    pub from: String,
+
+    /// Package ID specification of the dependency
+    pub to: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberVersion {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    #[structopt(flatten)]
+    pub selection: SelectionOpt,
+
+    /// Also bump the members that depend on the bumped ones
+    #[structopt(long)]
+    pub cascade: bool,
+
+    /// Package ID specifications followed by `major`, `minor`, `patch`, or a version
+    #[structopt(value_name("SPEC|BUMP"), required(true))]
+    pub args: Vec<String>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberPublishOrder {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Output format
+    #[structopt(
+        long,
+        value_name("FORMAT"),
+        possible_values(self::OutputFormat::VARIANTS),
+        default_value("text")
+    )]
+    pub format: self::OutputFormat,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberCheckPublish {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Output format
+    #[structopt(
+        long,
+        value_name("FORMAT"),
+        possible_values(self::OutputFormat::VARIANTS),
+        default_value("text")
+    )]
+    pub format: self::OutputFormat,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberGraph {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Output format
+    #[structopt(
+        long,
+        value_name("FORMAT"),
+        possible_values(self::GraphFormat::VARIANTS),
+        default_value("dot")
+    )]
+    pub format: self::GraphFormat,
+
+    /// Show only the packages around this member
+    #[structopt(long, value_name("SPEC"))]
+    pub focus: Option<String>,
+
+    /// Number of edges to follow from `--focus`
+    #[structopt(long, value_name("N"), default_value("1"))]
+    pub depth: usize,
+
+    /// Also show the excluded and inactive packages under the workspace root
+    #[structopt(long)]
+    pub all: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberWhich {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Output format
+    #[structopt(
+        long,
+        value_name("FORMAT"),
+        possible_values(self::OutputFormat::VARIANTS),
+        default_value("text")
+    )]
+    pub format: self::OutputFormat,
+
+    /// Paths to files. Read from stdin, one per line, if none are given
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberChanged {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Git revision to compare the working tree with
+    #[structopt(long, value_name("REV"))]
+    pub since: String,
+
+    /// Also include the members that have the affected ones as dev-dependencies
+    #[structopt(long)]
+    pub dev: bool,
+
+    /// Output format
+    #[structopt(
+        long,
+        value_name("FORMAT"),
+        possible_values(self::ChangedFormat::VARIANTS),
+        default_value("names")
+    )]
+    pub format: self::ChangedFormat,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberExec {
+    #[structopt(flatten)]
+    pub selection: SelectionOpt,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: self::ColorChoice,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Also select the members the selected ones depend on
+    #[structopt(long)]
+    pub with_dependencies: bool,
+
+    /// Also select the members depending on the selected ones
+    #[structopt(long)]
+    pub with_dependents: bool,
+
+    /// Number of members to run the command in at once
+    #[structopt(short, long, value_name("N"), default_value("1"))]
+    pub jobs: usize,
+
+    /// Stop starting the command in other members after a failure
+    #[structopt(long)]
+    pub fail_fast: bool,
+
+    /// Command to run
+    #[structopt(last(true), required(true), parse(from_os_str))]
+    pub command: Vec<OsString>,
+}
+
+/// Options to select members.
+#[derive(StructOpt, Debug)]
+pub struct SelectionOpt {
+    /// Package(s) to select. Globs on the names are allowed
+    #[structopt(short, long, value_name("SPEC"), min_values(1), number_of_values(1))]
+    pub package: Vec<String>,
+
+    /// Select all the members
+    #[structopt(long)]
+    pub all: bool,
+
+    /// Package(s) to leave out of `--all`. Globs on the names are allowed
+    #[structopt(long, value_name("SPEC"), min_values(1), number_of_values(1))]
+    pub exclude: Vec<String>,
+
+    /// Select the members whose directories relative to the workspace root match the glob
+    #[structopt(long, value_name("GLOB"), min_values(1), number_of_values(1))]
+    pub path: Vec<String>,
+
+    /// Select the members depending on the package
+    #[structopt(long, value_name("SPEC"), min_values(1), number_of_values(1))]
+    pub dependents_of: Vec<String>,
+
+    /// Select the members the package depends on
+    #[structopt(long, value_name("SPEC"), min_values(1), number_of_values(1))]
+    pub dependencies_of: Vec<String>,
+
+    /// Select the members affected by the changes since a git revision
+    #[structopt(long, value_name("REV"))]
+    pub changed_since: Option<String>,
+
+    /// Keep only the selected members that have the feature
+    #[structopt(long, value_name("FEATURE"), min_values(1), number_of_values(1))]
+    pub has_feature: Vec<String>,
+}
+
+impl From<SelectionOpt> for Selection {
+    fn from(opt: SelectionOpt) -> Self {
+        let SelectionOpt {
+            package,
+            all,
+            exclude,
+            path,
+            dependents_of,
+            dependencies_of,
+            changed_since,
+            has_feature,
+        } = opt;
+
+        Selection::new()
+            .specs(package)
+            .all(all)
+            .exclude(exclude)
+            .paths(path)
+            .dependents_of(dependents_of)
+            .dependencies_of(dependencies_of)
+            .changed_since(changed_since.as_deref())
+            .has_feature(has_feature)
+    }
+}
+
+/// Coloring.
+#[derive(EnumString, VariantNames, IntoStaticStr, Clone, Copy, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Output format.
+#[derive(EnumString, VariantNames, IntoStaticStr, Clone, Copy, PartialEq, Eq, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// How `Cargo.lock` is updated.
+#[derive(EnumString, VariantNames, IntoStaticStr, Clone, Copy, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum LockMode {
+    Edit,
+    Resolve,
+    Skip,
+}
+
+impl From<self::LockMode> for crate::LockMode {
+    fn from(lock_mode: self::LockMode) -> Self {
+        match lock_mode {
+            self::LockMode::Edit => Self::Edit,
+            self::LockMode::Resolve => Self::Resolve,
+            self::LockMode::Skip => Self::Skip,
+        }
+    }
+}
+
+/// Output format of `graph`.
+#[derive(EnumString, VariantNames, IntoStaticStr, Clone, Copy, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+impl From<self::GraphFormat> for crate::GraphFormat {
+    fn from(format: self::GraphFormat) -> Self {
+        match format {
+            self::GraphFormat::Dot => Self::Dot,
+            self::GraphFormat::Mermaid => Self::Mermaid,
+            self::GraphFormat::Json => Self::Json,
+        }
+    }
+}
+
+/// Output format of `changed`.
+#[derive(EnumString, VariantNames, IntoStaticStr, Clone, Copy, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ChangedFormat {
+    Names,
+    Paths,
+    Flags,
+    Json,
+}
+
+impl From<self::ChangedFormat> for crate::ChangedFormat {
+    fn from(format: self::ChangedFormat) -> Self {
+        match format {
+            self::ChangedFormat::Names => Self::Names,
+            self::ChangedFormat::Paths => Self::Paths,
+            self::ChangedFormat::Flags => Self::Flags,
+            self::ChangedFormat::Json => Self::Json,
+        }
+    }
+}
+
+impl From<self::ColorChoice> for WriteStyle {
+    fn from(choice: self::ColorChoice) -> Self {
+        match choice {
+            self::ColorChoice::Auto => Self::Auto,
+            self::ColorChoice::Always => Self::Always,
+            self::ColorChoice::Never => Self::Never,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Context<W> {
+    cwd: PathBuf,
+    stderr: W,
+    stderr_redirection: Stdio,
+}
+
+impl<W> Context<W> {
+    pub fn new(stderr: W) -> anyhow::Result<Self> {
+        let cwd = env::current_dir()
+            .with_context(|| "failed to get CWD")?
+            .try_into()
+            .expect("CWD is not UTF-8");
+        let stderr_redirection = Stdio::inherit();
+        Ok(Self {
+            cwd,
+            stderr,
+            stderr_redirection,
+        })
+    }
+}
+
+pub fn init_logger(color: self::ColorChoice) {
+    env_logger::Builder::from_default_env()
+        .write_style(color.into())
+        .init();
+}
+
+pub fn stderr(color: self::ColorChoice) -> BufferedStandardStream {
+    BufferedStandardStream::stderr(match color {
+        self::ColorChoice::Auto if atty::is(atty::Stream::Stderr) => termcolor::ColorChoice::Auto,
+        self::ColorChoice::Always => termcolor::ColorChoice::Always,
+        self::ColorChoice::Auto | self::ColorChoice::Never => termcolor::ColorChoice::Never,
+    })
+}
+
+pub fn exit_with_error(error: anyhow::Error, color: self::ColorChoice) -> ! {
+    let mut stderr = BufferedStandardStream::stderr(match color {
+        self::ColorChoice::Auto if atty::is(atty::Stream::Stderr) => termcolor::ColorChoice::Auto,
+        self::ColorChoice::Always => termcolor::ColorChoice::Always,
+        self::ColorChoice::Auto | self::ColorChoice::Never => termcolor::ColorChoice::Never,
+    });
+
+    let _ = stderr.set_color(
+        ColorSpec::new()
+            .set_fg(Some(termcolor::Color::Red))
+            .set_bold(true)
+            .set_reset(false),
+    );
+    let _ = stderr.write_all(b"error: ");
+    let _ = stderr.reset();
+    let _ = writeln!(stderr, "{}", error);
+
+    for error in error.chain().skip(1) {
+        let _ = writeln!(stderr, "\nCaused by:\n  {}", error);
+    }
+
+    let _ = stderr.flush();
+    process::exit(101);
+}
+
+pub fn run(opt: CargoMember, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
     match opt {
         CargoMember::Include(opt) => include(opt, ctx),
         CargoMember::Exclude(opt) => exclude(opt, ctx),
         CargoMember::Deactivate(opt) => deactivate(opt, ctx),
+        CargoMember::Sort(opt) => sort(opt, ctx),
+        CargoMember::Stash(opt) => stash(opt, ctx),
         CargoMember::Focus(opt) => focus(opt, ctx),
         CargoMember::New(opt) => new(opt, ctx),
         CargoMember::Cp(opt) => cp(opt, ctx),
         CargoMember::Rm(opt) => rm(opt, ctx),
         CargoMember::Mv(opt) => mv(opt, ctx),
+        CargoMember::Trash(opt) => trash(opt, ctx),
+        CargoMember::Undo(opt) => undo(opt, ctx),
+        CargoMember::Eject(opt) => eject(opt, ctx),
+        CargoMember::Split(opt) => split(opt, ctx),
+        CargoMember::Merge(opt) => merge(opt, ctx),
+        CargoMember::Fork(opt) => fork(opt, ctx),
+        CargoMember::Unfork(opt) => unfork(opt, ctx),
+        CargoMember::Patch(opt) => patch(opt, ctx),
+        CargoMember::Unpatch(opt) => unpatch(opt, ctx),
+        CargoMember::Link(opt) => link(opt, ctx),
+        CargoMember::Unlink(opt) => unlink(opt, ctx),
+        CargoMember::Version(opt) => version(opt, ctx),
+        CargoMember::PublishOrder(opt) => publish_order(opt, ctx),
+        CargoMember::CheckPublish(opt) => check_publish(opt, ctx),
+        CargoMember::Graph(opt) => graph(opt, ctx),
+        CargoMember::Which(opt) => which(opt, ctx),
+        CargoMember::Changed(opt) => changed(opt, ctx),
+        CargoMember::Exec(opt) => exec(opt, ctx),
+    }
+}
+
+fn include(opt: CargoMemberInclude, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberInclude {
+        manifest_path,
+        offline,
+        lock_mode,
+        sort,
+        no_sort,
+        force,
+        absorb,
+        dry_run,
+        paths,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let possibly_empty_workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
+    let config = Config::discover(&possibly_empty_workspace_root)?;
+    let paths = paths.into_iter().map(|p| cwd.join(p.trim_leading_dots()));
+
+    Include::new(&possibly_empty_workspace_root, paths)
+        .force(force)
+        .absorb(absorb)
+        .offline(offline)
+        .dry_run(dry_run)
+        .journal(Some(&target_directory(&possibly_empty_workspace_root)))
+        .sort_members(sort || !no_sort && config.sort_members)
+        .multiline_threshold(config.multiline_threshold)
+        .lock_mode(lock_mode.map_or_else(|| config.lock_mode.unwrap_or_default(), Into::into))
+        .stderr(stderr)
+        .exec()
+}
+
+fn exclude(opt: CargoMemberExclude, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberExclude {
+        selection,
+        manifest_path,
+        offline,
+        lock_mode,
+        sort,
+        no_sort,
+        dry_run,
+        paths,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+    let config = Config::discover(&metadata.workspace_root)?;
+    let selected = selected_dirs(&metadata, selection, &mut stderr)?;
+    let paths = paths
+        .into_iter()
+        .map(|p| cwd.join(p.trim_leading_dots()))
+        .chain(selected);
+
+    Exclude::new(&metadata.workspace_root, paths)
+        .dry_run(dry_run)
+        .journal(Some(&metadata.target_directory))
+        .offline(offline)
+        .sort_members(sort || !no_sort && config.sort_members)
+        .multiline_threshold(config.multiline_threshold)
+        .lock_mode(lock_mode.map_or_else(|| config.lock_mode.unwrap_or_default(), Into::into))
+        .stderr(stderr)
+        .exec()
+}
+
+fn deactivate(opt: CargoMemberDeactivate, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberDeactivate {
+        selection,
+        manifest_path,
+        offline,
+        lock_mode,
+        sort,
+        no_sort,
+        dry_run,
+        paths,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+    let config = Config::discover(&metadata.workspace_root)?;
+    let selected = selected_dirs(&metadata, selection, &mut stderr)?;
+    let paths = paths
+        .into_iter()
+        .map(|p| cwd.join(p.trim_leading_dots()))
+        .chain(selected);
+
+    Deactivate::new(&metadata.workspace_root, paths)
+        .dry_run(dry_run)
+        .journal(Some(&metadata.target_directory))
+        .offline(offline)
+        .sort_members(sort || !no_sort && config.sort_members)
+        .multiline_threshold(config.multiline_threshold)
+        .lock_mode(lock_mode.map_or_else(|| config.lock_mode.unwrap_or_default(), Into::into))
+        .stderr(stderr)
+        .exec()
+}
+
+fn sort(opt: CargoMemberSort, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberSort {
+        multiline_threshold,
+        check,
+        dry_run,
+        manifest_path,
+        offline,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+    let config = Config::discover(&metadata.workspace_root)?;
+
+    Sort::from_metadata(&metadata)
+        .multiline_threshold(multiline_threshold.or(config.multiline_threshold))
+        .check(check)
+        .dry_run(dry_run)
+        .stderr(stderr)
+        .exec()
+}
+
+fn stash(opt: CargoMemberStash, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let Context { cwd, stderr, .. } = ctx;
+
+    match opt {
+        CargoMemberStash::Push(CargoMemberStashPush {
+            dry_run,
+            manifest_path,
+            offline,
+            name,
+            ..
+        }) => {
+            let metadata =
+                crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+            StashPush::from_metadata(&metadata)
+                .name(name.as_deref())
+                .dry_run(dry_run)
+                .stderr(stderr)
+                .exec()
+        }
+        CargoMemberStash::List(CargoMemberStashList {
+            manifest_path,
+            offline,
+            ..
+        }) => {
+            let metadata =
+                crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+            StashList::from_metadata(&metadata)
+                .stdout(io::stdout().lock())
+                .exec()
+        }
+        CargoMemberStash::Pop(opt) => stash_apply(opt, true, cwd, stderr),
+        CargoMemberStash::Apply(opt) => stash_apply(opt, false, cwd, stderr),
     }
 }
 
-fn include(opt: CargoMemberInclude, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberInclude {
+fn stash_apply(
+    opt: CargoMemberStashApply,
+    pop: bool,
+    cwd: PathBuf,
+    stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let CargoMemberStashApply {
+        dry_run,
+        manifest_path,
+        offline,
+        lock_mode,
+        sort,
+        no_sort,
+        stash,
+        ..
+    } = opt;
+
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+    let config = Config::discover(&metadata.workspace_root)?;
+
+    StashApply::from_metadata(&metadata)
+        .stash(stash.as_deref())
+        .pop(pop)
+        .dry_run(dry_run)
+        .offline(offline)
+        .sort_members(sort || !no_sort && config.sort_members)
+        .multiline_threshold(config.multiline_threshold)
+        .lock_mode(lock_mode.map_or_else(|| config.lock_mode.unwrap_or_default(), Into::into))
+        .stderr(stderr)
+        .exec()
+}
+
+fn focus(opt: CargoMemberFocus, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberFocus {
+        exclude,
+        deactivate,
+        dry_run,
+        manifest_path,
+        offline,
+        path,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let Metadata {
+        workspace_root,
+        target_directory,
+        ..
+    } = crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+    let config = Config::discover(&workspace_root)?;
+    let path = cwd.join(path.trim_leading_dots());
+
+    Focus::new(&workspace_root, &path)
+        .dry_run(dry_run)
+        .journal(Some(&target_directory))
+        .offline(offline)
+        .exclude(exclude || !deactivate && config.focus == Some(FocusMode::Exclude))
+        .stderr(stderr)
+        .exec()
+}
+
+fn new(opt: CargoMemberNew, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberNew {
+        manifest_path,
+        registry,
+        vcs,
+        bin,
+        lib,
+        name,
+        offline,
+        dry_run,
+        path,
+        ..
+    } = opt;
+
+    let Context {
+        cwd,
+        stderr,
+        stderr_redirection,
+    } = ctx;
+
+    let possibly_empty_workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
+    let config = Config::discover(&possibly_empty_workspace_root)?;
+    let path = config
+        .member_path(&path)
+        .unwrap_or_else(|| cwd.join(path.trim_leading_dots()));
+    let name = name.or_else(|| {
+        let dir_name = path.file_name()?;
+        config
+            .name_prefix
+            .as_deref()
+            .map(|prefix| crate::prefixed_name(Some(prefix), dir_name))
+    });
+
+    New::new(&possibly_empty_workspace_root, &path)
+        .cargo_new_registry(registry)
+        .cargo_new_vcs(vcs.or_else(|| config.new.vcs.clone()))
+        .cargo_new_lib(lib || !bin && config.new.template == Some(Template::Lib))
+        .cargo_new_name(name)
+        .cargo_new_stderr_redirection(stderr_redirection)
+        .offline(offline)
+        .dry_run(dry_run)
+        .journal(Some(&target_directory(&possibly_empty_workspace_root)))
+        .stderr(stderr)
+        .exec()
+}
+
+fn cp(opt: CargoMemberCp, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberCp {
+        manifest_path,
+        offline,
+        dry_run,
+        no_rename,
+        src,
+        dst,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+    let config = Config::discover(&metadata.workspace_root)?;
+    let dst = config
+        .member_path(&dst)
+        .unwrap_or_else(|| cwd.join(dst.trim_leading_dots()));
+
+    Cp::from_metadata(&metadata, &src, &dst)
+        .dry_run(dry_run)
+        .journal(Some(&metadata.target_directory))
+        .no_rename(no_rename)
+        .name_prefix(config.name_prefix.as_deref())
+        .stderr(stderr)
+        .exec()
+}
+
+fn rm(opt: CargoMemberRm, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberRm {
+        selection,
+        manifest_path,
+        offline,
+        lock_mode,
+        sort,
+        no_sort,
+        force,
+        permanent,
+        dry_run,
+        paths,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+    let config = Config::discover(&metadata.workspace_root)?;
+    let selected = selected_dirs(&metadata, selection, &mut stderr)?;
+    let paths = paths
+        .into_iter()
+        .map(|p| cwd.join(p.trim_leading_dots()))
+        .chain(selected);
+
+    Rm::new(&metadata.workspace_root, paths)
+        .target_directory(&metadata.target_directory)
+        .force(force)
+        .permanent(permanent)
+        .dry_run(dry_run)
+        .journal(Some(&metadata.target_directory))
+        .offline(offline)
+        .sort_members(sort || !no_sort && config.sort_members)
+        .multiline_threshold(config.multiline_threshold)
+        .lock_mode(lock_mode.map_or_else(|| config.lock_mode.unwrap_or_default(), Into::into))
+        .stderr(stderr)
+        .exec()
+}
+
+fn mv(opt: CargoMemberMv, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberMv {
         manifest_path,
         offline,
-        force,
+        lock_mode,
         dry_run,
-        paths,
+        no_rename,
+        to_workspace,
+        src,
+        dst,
         ..
     } = opt;
 
     let Context { cwd, stderr, .. } = ctx;
 
-    let possibly_empty_workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
-    let paths = paths.into_iter().map(|p| cwd.join(p.trim_leading_dots()));
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+    let config = Config::discover(&metadata.workspace_root)?;
+    let dst = config
+        .member_path(&dst)
+        .filter(|_| to_workspace.is_none())
+        .unwrap_or_else(|| cwd.join(dst.trim_leading_dots()));
+    let to_workspace = to_workspace.map(|p| {
+        let p = cwd.join(p.trim_leading_dots());
+        match p.parent() {
+            Some(parent) if p.ends_with("Cargo.toml") => parent.to_owned(),
+            _ => p,
+        }
+    });
 
-    Include::new(&possibly_empty_workspace_root, paths)
-        .force(force)
+    Mv::from_metadata(&metadata, &src, &dst)
+        .to_workspace(to_workspace.as_deref())
+        .dry_run(dry_run)
+        .journal(Some(&metadata.target_directory))
+        .lock_mode(lock_mode.map_or_else(|| config.lock_mode.unwrap_or_default(), Into::into))
         .offline(offline)
+        .no_rename(no_rename)
+        .name_prefix(config.name_prefix.as_deref())
+        .stderr(stderr)
+        .exec()
+}
+
+fn trash(opt: CargoMemberTrash, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let Context { cwd, stderr, .. } = ctx;
+
+    match opt {
+        CargoMemberTrash::List(CargoMemberTrashList {
+            manifest_path,
+            offline,
+            ..
+        }) => {
+            let metadata =
+                crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+            TrashList::from_metadata(&metadata)
+                .stdout(io::stdout().lock())
+                .exec()
+        }
+        CargoMemberTrash::Restore(CargoMemberTrashRestore {
+            dry_run,
+            manifest_path,
+            offline,
+            lock_mode,
+            sort,
+            no_sort,
+            item,
+            ..
+        }) => {
+            let metadata =
+                crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+            let config = Config::discover(&metadata.workspace_root)?;
+            TrashRestore::from_metadata(&metadata)
+                .item(item.as_deref())
+                .dry_run(dry_run)
+                .offline(offline)
+                .journal(Some(&metadata.target_directory))
+                .sort_members(sort || !no_sort && config.sort_members)
+                .multiline_threshold(config.multiline_threshold)
+                .lock_mode(
+                    lock_mode.map_or_else(|| config.lock_mode.unwrap_or_default(), Into::into),
+                )
+                .stderr(stderr)
+                .exec()
+        }
+        CargoMemberTrash::Empty(CargoMemberTrashEmpty {
+            dry_run,
+            manifest_path,
+            offline,
+            ..
+        }) => {
+            let metadata =
+                crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+            TrashEmpty::from_metadata(&metadata)
+                .dry_run(dry_run)
+                .stderr(stderr)
+                .exec()
+        }
+    }
+}
+
+fn undo(opt: CargoMemberUndo, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberUndo {
+        steps,
+        dry_run,
+        manifest_path,
+        offline,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
+
+    Undo::new(&target_directory(&workspace_root))
+        .steps(steps)
         .dry_run(dry_run)
+        .offline(offline)
         .stderr(stderr)
         .exec()
 }
 
-fn exclude(opt: CargoMemberExclude, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberExclude {
-        package,
+fn eject(opt: CargoMemberEject, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberEject {
         manifest_path,
         offline,
         dry_run,
-        paths,
+        keep_paths,
+        remove_source,
+        src,
+        dst,
         ..
     } = opt;
 
@@ -482,21 +2133,23 @@ fn exclude(opt: CargoMemberExclude, ctx: Context<impl WriteColor>) -> anyhow::Re
 
     let metadata =
         crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let paths = paths.into_iter().map(|p| cwd.join(p.trim_leading_dots()));
+    let dst = cwd.join(dst.trim_leading_dots());
 
-    Exclude::from_metadata(&metadata, paths, package)
+    Eject::from_metadata(&metadata, &src, &dst)
+        .keep_paths(keep_paths)
+        .remove_source(remove_source)
         .dry_run(dry_run)
         .stderr(stderr)
         .exec()
 }
 
-fn deactivate(opt: CargoMemberDeactivate, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberDeactivate {
-        package,
+fn split(opt: CargoMemberSplit, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberSplit {
         manifest_path,
         offline,
         dry_run,
-        paths,
+        into,
+        specs,
         ..
     } = opt;
 
@@ -504,21 +2157,23 @@ fn deactivate(opt: CargoMemberDeactivate, ctx: Context<impl WriteColor>) -> anyh
 
     let metadata =
         crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let paths = paths.into_iter().map(|p| cwd.join(p.trim_leading_dots()));
+    let into = cwd.join(into.trim_leading_dots());
 
-    Deactivate::from_metadata(&metadata, paths, package)
+    Split::from_metadata(&metadata, specs, &into)
         .dry_run(dry_run)
+        .offline(offline)
         .stderr(stderr)
         .exec()
 }
 
-fn focus(opt: CargoMemberFocus, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberFocus {
-        exclude,
-        dry_run,
+fn merge(opt: CargoMemberMerge, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberMerge {
         manifest_path,
         offline,
-        path,
+        dry_run,
+        prefix,
+        remove_source,
+        other,
         ..
     } = opt;
 
@@ -526,82 +2181,138 @@ fn focus(opt: CargoMemberFocus, ctx: Context<impl WriteColor>) -> anyhow::Result
 
     let Metadata { workspace_root, .. } =
         crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let path = cwd.join(path.trim_leading_dots());
+    let other = cwd.join(other.trim_leading_dots());
 
-    Focus::new(&workspace_root, &path)
+    Merge::new(&workspace_root, &other)
+        .prefix(prefix.unwrap_or_default())
+        .remove_source(remove_source)
         .dry_run(dry_run)
         .offline(offline)
+        .stderr(stderr)
+        .exec()
+}
+
+fn fork(opt: CargoMemberFork, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberFork {
+        manifest_path,
+        offline,
+        dry_run,
+        dir,
+        exclude,
+        spec,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+
+    Fork::from_metadata(&metadata, &spec)
+        .dir(dir)
         .exclude(exclude)
+        .dry_run(dry_run)
+        .offline(offline)
         .stderr(stderr)
         .exec()
 }
 
-fn new(opt: CargoMemberNew, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberNew {
+fn unfork(opt: CargoMemberUnfork, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberUnfork {
         manifest_path,
-        registry,
-        vcs,
-        lib,
+        offline,
+        remove,
+        dry_run,
         name,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+
+    Unfork::from_metadata(&metadata, &name)
+        .remove(remove)
+        .dry_run(dry_run)
+        .offline(offline)
+        .journal(Some(&metadata.target_directory))
+        .stderr(stderr)
+        .exec()
+}
+
+fn patch(opt: CargoMemberPatch, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberPatch {
+        manifest_path,
         offline,
         dry_run,
+        list,
+        registry,
+        git,
+        name,
         path,
         ..
     } = opt;
 
-    let Context {
-        cwd,
-        stderr,
-        stderr_redirection,
-    } = ctx;
+    let Context { cwd, stderr, .. } = ctx;
 
-    let possibly_empty_workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
-    let path = cwd.join(path.trim_leading_dots());
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
 
-    New::new(&possibly_empty_workspace_root, &path)
-        .cargo_new_registry(registry)
-        .cargo_new_vcs(vcs)
-        .cargo_new_lib(lib)
-        .cargo_new_name(name)
-        .cargo_new_stderr_redirection(stderr_redirection)
-        .offline(offline)
+    if list {
+        return PatchList::from_metadata(&metadata)
+            .stdout(io::stdout().lock())
+            .exec();
+    }
+
+    let name = name.expect("required unless `--list`");
+    let path = cwd.join(path.expect("required unless `--list`").trim_leading_dots());
+
+    Patch::from_metadata(&metadata, &name, &path)
+        .registry(registry)
+        .git(git)
         .dry_run(dry_run)
+        .offline(offline)
         .stderr(stderr)
         .exec()
 }
 
-fn cp(opt: CargoMemberCp, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberCp {
+fn unpatch(opt: CargoMemberUnpatch, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberUnpatch {
         manifest_path,
         offline,
         dry_run,
-        no_rename,
-        src,
-        dst,
+        registry,
+        git,
+        name,
         ..
     } = opt;
 
     let Context { cwd, stderr, .. } = ctx;
 
-    let metadata =
+    let Metadata { workspace_root, .. } =
         crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let dst = cwd.join(dst.trim_leading_dots());
 
-    Cp::from_metadata(&metadata, &src, &dst)
+    Unpatch::new(&workspace_root, &name)
+        .source(git.or(registry))
         .dry_run(dry_run)
-        .no_rename(no_rename)
+        .offline(offline)
         .stderr(stderr)
         .exec()
 }
 
-fn rm(opt: CargoMemberRm, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberRm {
-        package,
+fn link(opt: CargoMemberLink, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberLink {
         manifest_path,
         offline,
-        force,
         dry_run,
-        paths,
+        dev,
+        build,
+        optional,
+        features,
+        version_req,
+        from,
+        to,
         ..
     } = opt;
 
@@ -609,23 +2320,34 @@ fn rm(opt: CargoMemberRm, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
 
     let metadata =
         crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let paths = paths.into_iter().map(|p| cwd.join(p.trim_leading_dots()));
-
-    Rm::from_metadata(&metadata, paths, package)
-        .force(force)
+    let kind = if dev {
+        DependencyKind::Development
+    } else if build {
+        DependencyKind::Build
+    } else {
+        DependencyKind::Normal
+    };
+
+    Link::from_metadata(&metadata, &from, &to)
+        .kind(kind)
+        .optional(optional)
+        .features(features)
+        .version_req(version_req.as_deref())
         .dry_run(dry_run)
+        .offline(offline)
         .stderr(stderr)
         .exec()
 }
 
-fn mv(opt: CargoMemberMv, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberMv {
+fn unlink(opt: CargoMemberUnlink, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberUnlink {
         manifest_path,
         offline,
         dry_run,
-        no_rename,
-        src,
-        dst,
+        dev,
+        build,
+        from,
+        to,
         ..
     } = opt;
 
@@ -633,15 +2355,240 @@ fn mv(opt: CargoMemberMv, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
 
     let metadata =
         crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let dst = cwd.join(dst.trim_leading_dots());
+    let kind = if dev {
+        Some(DependencyKind::Development)
+    } else if build {
+        Some(DependencyKind::Build)
+    } else {
+        None
+    };
+
+    Unlink::from_metadata(&metadata, &from, &to)
+        .kind(kind)
+        .dry_run(dry_run)
+        .offline(offline)
+        .stderr(stderr)
+        .exec()
+}
 
-    Mv::from_metadata(&metadata, &src, &dst)
+fn version(opt: CargoMemberVersion, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberVersion {
+        manifest_path,
+        offline,
+        dry_run,
+        mut selection,
+        cascade,
+        args,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let (bump, specs) = args.split_last().expect("`required(true)`");
+    let bump = bump
+        .parse::<Bump>()
+        .with_context(|| format!("invalid bump: {:?}", bump))?;
+    selection.package.extend(specs.iter().cloned());
+    let selection = Selection::from(selection);
+    if selection.is_empty() {
+        bail!("specify packages or `--all`");
+    }
+
+    let metadata =
+        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
+    let selected = selection.select(&metadata, &mut stderr)?;
+    if selected.is_empty() {
+        return Ok(());
+    }
+
+    Version::from_ids(&metadata, selected.iter().map(|p| &p.id), bump)
+        .cascade(cascade)
         .dry_run(dry_run)
-        .no_rename(no_rename)
         .stderr(stderr)
         .exec()
 }
 
+fn publish_order(
+    opt: CargoMemberPublishOrder,
+    ctx: Context<impl WriteColor>,
+) -> anyhow::Result<()> {
+    let CargoMemberPublishOrder {
+        manifest_path,
+        offline,
+        format,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let metadata = crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+
+    PublishOrder::from_metadata(&metadata)
+        .json(format == self::OutputFormat::Json)
+        .stdout(io::stdout().lock())
+        .stderr(stderr)
+        .exec()
+}
+
+fn check_publish(
+    opt: CargoMemberCheckPublish,
+    ctx: Context<impl WriteColor>,
+) -> anyhow::Result<()> {
+    let CargoMemberCheckPublish {
+        manifest_path,
+        offline,
+        format,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let metadata = crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+
+    CheckPublish::from_metadata(&metadata)
+        .json(format == self::OutputFormat::Json)
+        .stdout(io::stdout().lock())
+        .stderr(stderr)
+        .exec()
+}
+
+fn graph(opt: CargoMemberGraph, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberGraph {
+        manifest_path,
+        offline,
+        format,
+        focus,
+        depth,
+        all,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let metadata = crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+
+    Graph::from_metadata(&metadata)
+        .format(format.into())
+        .focus(focus.as_deref())
+        .depth(depth)
+        .all(all)
+        .stdout(io::stdout().lock())
+        .stderr(stderr)
+        .exec()
+}
+
+fn which(opt: CargoMemberWhich, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberWhich {
+        manifest_path,
+        offline,
+        format,
+        paths,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let paths = if paths.is_empty() {
+        io::stdin()
+            .lines()
+            .filter_ok(|line| !line.trim().is_empty())
+            .map_ok(|line| PathBuf::from(line.trim()))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        paths
+    };
+    let paths = paths.into_iter().map(|p| cwd.join(p.trim_leading_dots()));
+
+    let metadata = crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+
+    Which::from_metadata(&metadata, paths)
+        .json(format == self::OutputFormat::Json)
+        .stdout(io::stdout().lock())
+        .stderr(stderr)
+        .exec()
+}
+
+fn changed(opt: CargoMemberChanged, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberChanged {
+        manifest_path,
+        offline,
+        since,
+        dev,
+        format,
+        ..
+    } = opt;
+
+    let Context { cwd, .. } = ctx;
+
+    let metadata = crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+
+    Changed::from_metadata(&metadata, &since)
+        .dev(dev)
+        .format(format.into())
+        .stdout(io::stdout().lock())
+        .exec()
+}
+
+fn exec(opt: CargoMemberExec, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberExec {
+        selection,
+        manifest_path,
+        offline,
+        with_dependencies,
+        with_dependents,
+        jobs,
+        fail_fast,
+        command,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let metadata = crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+    let (program, args) = command.split_first().expect("`command` is required");
+
+    Exec::from_metadata(&metadata, program, args)
+        .selection(selection.into())
+        .with_dependencies(with_dependencies)
+        .with_dependents(with_dependents)
+        .jobs(jobs)
+        .fail_fast(fail_fast)
+        .stdout(io::stdout().lock())
+        .stderr(stderr)
+        .exec()
+}
+
+fn selected_dirs(
+    metadata: &Metadata,
+    selection: SelectionOpt,
+    stderr: impl WriteColor,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let selected = Selection::from(selection).select(metadata, stderr)?;
+    Ok(selected
+        .iter()
+        .map(|p| {
+            p.manifest_path
+                .parent()
+                .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                .to_owned()
+        })
+        .collect())
+}
+
+/// The target directory of the workspace, which may not be loadable yet. Only for the commands
+/// that do not load the workspace otherwise; the others take `Metadata::target_directory`.
+fn target_directory(workspace_root: &Path) -> PathBuf {
+    MetadataCommand::new()
+        .manifest_path(workspace_root.join("Cargo.toml"))
+        .no_deps()
+        .other_options(vec!["--offline".to_owned()])
+        .exec()
+        .map(|metadata| metadata.target_directory)
+        .unwrap_or_else(|_| workspace_root.join("target"))
+}
+
 fn find_root_manifest(manifest_path: Option<&Path>, cwd: &Path) -> anyhow::Result<PathBuf> {
     let mut path = cargo_locate_project(manifest_path, cwd)?;
     path.pop();
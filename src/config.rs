@@ -0,0 +1,99 @@
+//! Per-workspace defaults, read from `.cargo-member.toml` or `[workspace.metadata.cargo-member]`
+//! in the root manifest.
+
+use crate::LockMode;
+use anyhow::Context as _;
+use camino::{Utf8Component, Utf8Path as Path, Utf8PathBuf as PathBuf};
+use log::debug;
+use serde::Deserialize;
+
+const FILE_NAME: &str = ".cargo-member.toml";
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct Config {
+    /// Whether `focus` deactivates or excludes the other members.
+    pub(crate) focus: Option<FocusMode>,
+    pub(crate) lock_mode: Option<LockMode>,
+    /// Sort `workspace.{members, exclude, default-members}` when `include`, `exclude`,
+    /// `deactivate`, or `rm` modifies them.
+    #[serde(default)]
+    pub(crate) sort_members: bool,
+    /// Put each entry of those arrays on its own line when they have more entries than this.
+    pub(crate) multiline_threshold: Option<usize>,
+    /// Where `new`, `cp`, and `mv` put a package given by a bare name, relative to the
+    /// workspace root.
+    pub(crate) default_dir: Option<PathBuf>,
+    /// Prepended to the package names derived from directory names.
+    pub(crate) name_prefix: Option<String>,
+    #[serde(default)]
+    pub(crate) new: NewConfig,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct NewConfig {
+    pub(crate) template: Option<Template>,
+    pub(crate) vcs: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum FocusMode {
+    Deactivate,
+    Exclude,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Template {
+    Bin,
+    Lib,
+}
+
+impl Config {
+    /// Looks for the configuration from `start` upwards. The first `.cargo-member.toml` or
+    /// manifest with a `workspace` table decides it.
+    pub(crate) fn discover(start: &Path) -> anyhow::Result<Self> {
+        for dir in start.ancestors() {
+            let file_path = dir.join(FILE_NAME);
+            let mut config = if file_path.exists() {
+                crate::fs::read_toml::<Self, _>(&file_path)?
+            } else if dir.join("Cargo.toml").exists() {
+                let manifest_path = dir.join("Cargo.toml");
+                let mut manifest = crate::fs::read_toml::<toml::Table, _>(&manifest_path)?;
+                let Some(workspace) = manifest.remove("workspace") else {
+                    continue;
+                };
+                match workspace
+                    .get("metadata")
+                    .and_then(|m| m.get("cargo-member"))
+                {
+                    Some(config) => config.clone().try_into().with_context(|| {
+                        format!(
+                            "invalid `workspace.metadata.cargo-member` in {}",
+                            manifest_path,
+                        )
+                    })?,
+                    None => Self::default(),
+                }
+            } else {
+                continue;
+            };
+            debug!("config: {:?} (at {})", config, dir);
+            config.default_dir = config.default_dir.map(|d| dir.join(d));
+            return Ok(config);
+        }
+        Ok(Self::default())
+    }
+
+    /// Puts a bare name given to `new`, `cp`, or `mv` under `default-dir`.
+    pub(crate) fn member_path(&self, path: &Path) -> Option<PathBuf> {
+        let default_dir = self.default_dir.as_ref()?;
+        let is_bare_name = matches!(
+            *path.components().collect::<Vec<_>>(),
+            [Utf8Component::Normal(_)]
+        );
+        is_bare_name.then(|| default_dir.join(path))
+    }
+}
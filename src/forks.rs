@@ -0,0 +1,31 @@
+//! The checkouts created by `fork`, kept in `target/cargo-member/forks.toml` so that `unfork`
+//! deletes only those.
+
+use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct Forks {
+    #[serde(default, rename = "fork")]
+    pub(crate) paths: Vec<PathBuf>,
+}
+
+impl Forks {
+    pub(crate) fn path(target_dir: &Path) -> PathBuf {
+        target_dir.join("cargo-member").join("forks.toml")
+    }
+
+    pub(crate) fn read(target_dir: &Path) -> anyhow::Result<Self> {
+        let path = Self::path(target_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        crate::fs::read_toml(path)
+    }
+
+    pub(crate) fn write(&self, target_dir: &Path, dry_run: bool) -> anyhow::Result<()> {
+        let path = Self::path(target_dir);
+        crate::fs::create_dir_all(path.parent().expect("should not be root"), dry_run)?;
+        crate::fs::write(path, toml::to_string(self)?, dry_run)
+    }
+}
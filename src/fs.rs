@@ -20,7 +20,7 @@ pub(crate) fn read_toml_edit(path: impl AsRef<Path>) -> anyhow::Result<toml_edit
     Ok(edit)
 }
 
-fn read_to_string(path: &Path) -> anyhow::Result<String> {
+pub(crate) fn read_to_string(path: &Path) -> anyhow::Result<String> {
     std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path))
 }
 
@@ -31,6 +31,7 @@ pub(crate) fn write(
 ) -> anyhow::Result<()> {
     let path = path.as_ref();
     if !dry_run {
+        crate::journal::watch(path);
         std::fs::write(path, contents).with_context(|| format!("failed to write {}", path))?;
     }
     debug!("{}Wrote {}", if dry_run { "[dry-run] " } else { "" }, path,);
@@ -82,3 +83,84 @@ pub(crate) fn remove_dir_all(path: impl AsRef<Path>, dry_run: bool) -> anyhow::R
     );
     Ok(())
 }
+
+pub(crate) fn remove_file(path: impl AsRef<Path>, dry_run: bool) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    if !dry_run {
+        crate::journal::watch(path);
+        std::fs::remove_file(path).with_context(|| format!("failed to remove `{}`", path))?;
+    }
+    debug!(
+        "{}Removed {}",
+        if dry_run { "[dry-run] " } else { "" },
+        path,
+    );
+    Ok(())
+}
+
+/// Renames `src` to `dst`, copying and removing it if they are on different file systems.
+pub(crate) fn rename(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    if !dry_run {
+        match std::fs::rename(src, dst) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+                copy_dir_all(src, dst)?;
+                remove_dir_all::remove_dir_all(src)
+                    .with_context(|| format!("failed to remove `{}`", src))?;
+            }
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to rename `{}` to `{}`", src, dst));
+            }
+        }
+    }
+    debug!(
+        "{}Renamed {} to {}",
+        if dry_run { "[dry-run] " } else { "" },
+        src,
+        dst,
+    );
+    Ok(())
+}
+
+fn copy_dir_all(src: &Path, dst: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dst).with_context(|| format!("failed to create `{}`", dst))?;
+    for entry in src
+        .read_dir_utf8()
+        .with_context(|| format!("failed to read `{}`", src))?
+    {
+        let entry = entry?;
+        let dst = dst.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            copy_symlink(entry.path(), &dst)?;
+        } else if file_type.is_dir() {
+            copy_dir_all(entry.path(), &dst)?;
+        } else {
+            std::fs::copy(entry.path(), &dst)
+                .with_context(|| format!("failed to copy `{}` to `{}`", entry.path(), dst))?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates a symlink at `dst` pointing where `src` points, without following it.
+fn copy_symlink(src: &Path, dst: &Path) -> anyhow::Result<()> {
+    let target = src
+        .read_link_utf8()
+        .with_context(|| format!("failed to read the symlink `{}`", src))?;
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(&target, dst);
+    #[cfg(windows)]
+    let result = if src.is_dir() {
+        std::os::windows::fs::symlink_dir(&target, dst)
+    } else {
+        std::os::windows::fs::symlink_file(&target, dst)
+    };
+    result.with_context(|| format!("failed to create the symlink `{}` to `{}`", dst, target))
+}
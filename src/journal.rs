@@ -0,0 +1,261 @@
+//! The undo journal in `target/cargo-member/journal.toml`, oldest first.
+//!
+//! While a command records, the files written through [`crate::fs`] are read before their first
+//! write, along with the root `Cargo.toml` and the `Cargo.lock` next to each manifest, and the
+//! directories created or trashed are noted. `undo` replays the entries backwards.
+
+use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::BTreeMap, time::UNIX_EPOCH};
+
+/// How many entries are kept.
+const CAPACITY: usize = 20;
+
+thread_local! {
+    static RECORDING: RefCell<Option<Recording>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug)]
+struct Recording {
+    command: &'static str,
+    target_dir: PathBuf,
+    before: BTreeMap<PathBuf, Option<String>>,
+    created: Vec<PathBuf>,
+    trashed: Vec<Trashed>,
+    deleted: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct Journal {
+    #[serde(default, rename = "entry")]
+    pub(crate) entries: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Entry {
+    pub(crate) command: String,
+    #[serde(default)]
+    pub(crate) files: Vec<File>,
+    #[serde(default)]
+    pub(crate) created: Vec<Created>,
+    #[serde(default)]
+    pub(crate) trashed: Vec<Trashed>,
+    /// Directories deleted for good, which make the entry impossible to undo.
+    #[serde(default)]
+    pub(crate) deleted: Vec<PathBuf>,
+}
+
+/// A file as it was before and after the command. `None` means it did not exist.
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct File {
+    pub(crate) path: PathBuf,
+    pub(crate) before: Option<String>,
+    pub(crate) after: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Created {
+    pub(crate) path: PathBuf,
+    pub(crate) fingerprint: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct Trashed {
+    pub(crate) path: PathBuf,
+    pub(crate) trash: PathBuf,
+}
+
+impl Journal {
+    pub(crate) fn path(target_dir: &Path) -> PathBuf {
+        target_dir.join("cargo-member").join("journal.toml")
+    }
+
+    pub(crate) fn read(target_dir: &Path) -> anyhow::Result<Self> {
+        let path = Self::path(target_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        crate::fs::read_toml(path)
+    }
+
+    pub(crate) fn write(&self, target_dir: &Path, dry_run: bool) -> anyhow::Result<()> {
+        let path = Self::path(target_dir);
+        crate::fs::create_dir_all(path.parent().expect("should not be root"), dry_run)?;
+        crate::fs::write(path, toml::to_string(self)?, dry_run)
+    }
+}
+
+/// Records the changes until [`Guard::finish`] is called. Does nothing if `target_dir` is `None`
+/// or a recording is already in progress, so that nested commands are recorded as one.
+pub(crate) fn start(command: &'static str, target_dir: Option<&Path>) -> Guard {
+    let Some(target_dir) = target_dir else {
+        return Guard(false);
+    };
+    let started = RECORDING.with(|recording| {
+        let mut recording = recording.borrow_mut();
+        if recording.is_some() {
+            return false;
+        }
+        *recording = Some(Recording {
+            command,
+            target_dir: target_dir.to_owned(),
+            before: BTreeMap::new(),
+            created: vec![],
+            trashed: vec![],
+            deleted: vec![],
+        });
+        true
+    });
+    Guard(started)
+}
+
+/// Remembers the content of `path` before it is first modified. A `Cargo.toml` brings the
+/// `Cargo.lock` next to it, which `cargo` may rewrite. Files in the target directory, such as the
+/// trash index, are not watched.
+pub(crate) fn watch(path: &Path) {
+    RECORDING.with(|recording| {
+        if let Some(recording) = &mut *recording.borrow_mut() {
+            if path.starts_with(&recording.target_dir) {
+                return;
+            }
+            let lock_path = path
+                .parent()
+                .filter(|_| path.file_name() == Some("Cargo.toml"))
+                .map(|dir| dir.join("Cargo.lock"));
+            for path in [Some(path.to_owned()), lock_path].into_iter().flatten() {
+                recording
+                    .before
+                    .entry(path)
+                    .or_insert_with_key(|path| std::fs::read_to_string(path).ok());
+            }
+        }
+    });
+}
+
+pub(crate) fn created(path: &Path) {
+    RECORDING.with(|recording| {
+        if let Some(recording) = &mut *recording.borrow_mut() {
+            recording.created.push(path.to_owned());
+        }
+    });
+}
+
+/// Notes that `path` was moved into the trash at `trash`.
+pub(crate) fn trashed(path: &Path, trash: PathBuf) {
+    RECORDING.with(|recording| {
+        if let Some(recording) = &mut *recording.borrow_mut() {
+            recording.trashed.push(Trashed {
+                path: path.to_owned(),
+                trash,
+            });
+        }
+    });
+}
+
+/// Notes that `path` is about to be deleted for good.
+pub(crate) fn deleted(path: &Path) {
+    RECORDING.with(|recording| {
+        if let Some(recording) = &mut *recording.borrow_mut() {
+            recording.deleted.push(path.to_owned());
+        }
+    });
+}
+
+#[must_use]
+pub(crate) struct Guard(bool);
+
+impl Guard {
+    /// Appends the recorded changes to the journal.
+    pub(crate) fn finish(mut self) -> anyhow::Result<()> {
+        if !self.0 {
+            return Ok(());
+        }
+        self.0 = false;
+        let Recording {
+            command,
+            target_dir,
+            before,
+            created,
+            trashed,
+            deleted,
+        } = RECORDING
+            .with(|recording| recording.borrow_mut().take())
+            .expect("should be recording");
+
+        let files = before
+            .into_iter()
+            .filter(|(path, _)| !created.iter().any(|dir| path.starts_with(dir)))
+            .filter_map(|(path, before)| {
+                let after = std::fs::read_to_string(&path).ok();
+                (before != after).then_some(File {
+                    path,
+                    before,
+                    after,
+                })
+            })
+            .collect::<Vec<_>>();
+        let created = created
+            .into_iter()
+            .map(|path| {
+                let fingerprint = fingerprint(&path)?;
+                Ok(Created { path, fingerprint })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if files.is_empty() && created.is_empty() && trashed.is_empty() && deleted.is_empty() {
+            return Ok(());
+        }
+
+        let mut journal = Journal::read(&target_dir)?;
+        journal.entries.push(Entry {
+            command: command.to_owned(),
+            files,
+            created,
+            trashed,
+            deleted,
+        });
+        let excess = journal.entries.len().saturating_sub(CAPACITY);
+        journal.entries.drain(..excess);
+        journal.write(&target_dir, false)
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if self.0 {
+            RECORDING.with(|recording| recording.borrow_mut().take());
+        }
+    }
+}
+
+/// A digest of the paths, sizes, and modification times of the files under `dir`.
+pub(crate) fn fingerprint(dir: &Path) -> anyhow::Result<String> {
+    fn walk(dir: &Path, acc: &mut Vec<String>) -> anyhow::Result<()> {
+        for entry in dir.read_dir_utf8()? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                walk(entry.path(), acc)?;
+            } else {
+                let mtime = metadata
+                    .modified()?
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos())
+                    .unwrap_or_default();
+                acc.push(format!("{}:{}:{}", entry.path(), metadata.len(), mtime));
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = vec![];
+    walk(dir, &mut files)?;
+    files.sort();
+
+    // FNV-1a, which unlike `DefaultHasher` is stable across builds.
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for byte in files.join("\n").bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    Ok(format!("{:016x}", hash))
+}
@@ -1,34 +1,67 @@
 #[doc(hidden)]
 pub mod cli;
+mod config;
+mod forks;
 mod fs;
+mod journal;
+mod lockfile;
+mod manifest;
+mod pkgid;
+mod stash;
+mod trash;
 
 use anyhow::{anyhow, bail, ensure, Context as _};
 use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
-use cargo_metadata::{Metadata, MetadataCommand, Package, Resolve};
+use cargo_metadata::{semver, DependencyKind, Metadata, MetadataCommand, Package, PackageId};
 use easy_ext::ext;
 use ignore::{Walk, WalkBuilder};
 use itertools::Itertools as _;
 use log::debug;
 use serde::Deserialize;
 use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     env,
     ffi::{OsStr, OsString},
     fmt::{self, Debug, Display},
+    io::BufRead as _,
     io::{self, Sink},
+    iter,
     ops::Deref,
-    process::{Command, Stdio},
-    slice, str, vec,
+    process::{Command, ExitStatus, Stdio},
+    slice, str,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+    vec,
 };
+use strum::IntoStaticStr;
 use termcolor::{ColorSpec, NoColor, WriteColor};
-use url::Url;
+
+/// How `Cargo.lock` is updated after members are added, removed, or moved.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum LockMode {
+    /// Edit the entries of the path packages directly, resolving instead if that is not enough.
+    #[default]
+    Edit,
+    /// Resolve the dependencies with `cargo metadata`.
+    Resolve,
+    /// Leave `Cargo.lock` untouched.
+    Skip,
+}
 
 #[derive(Debug)]
 pub struct Include<W> {
     possibly_empty_workspace_root: anyhow::Result<PathBuf>,
     paths: anyhow::Result<Vec<PathBuf>>,
     force: bool,
+    absorb: bool,
     dry_run: bool,
     offline: bool,
+    lock_mode: LockMode,
+    sort_members: bool,
+    multiline_threshold: Option<usize>,
+    journal: Option<PathBuf>,
     stderr: W,
 }
 
@@ -41,8 +74,13 @@ impl Include<NoColor<Sink>> {
             possibly_empty_workspace_root: ensure_absolute(possibly_empty_workspace_root),
             paths: paths.into_iter().map(ensure_absolute).collect(),
             force: false,
+            absorb: false,
             dry_run: false,
             offline: false,
+            lock_mode: LockMode::default(),
+            sort_members: false,
+            multiline_threshold: None,
+            journal: None,
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -53,6 +91,10 @@ impl<W: WriteColor> Include<W> {
         Self { force, ..self }
     }
 
+    pub fn absorb(self, absorb: bool) -> Self {
+        Self { absorb, ..self }
+    }
+
     pub fn dry_run(self, dry_run: bool) -> Self {
         Self { dry_run, ..self }
     }
@@ -61,30 +103,69 @@ impl<W: WriteColor> Include<W> {
         Self { offline, ..self }
     }
 
+    pub fn lock_mode(self, lock_mode: LockMode) -> Self {
+        Self { lock_mode, ..self }
+    }
+
+    pub fn sort_members(self, sort_members: bool) -> Self {
+        Self {
+            sort_members,
+            ..self
+        }
+    }
+
+    pub fn multiline_threshold(self, multiline_threshold: Option<usize>) -> Self {
+        Self {
+            multiline_threshold,
+            ..self
+        }
+    }
+
+    /// Records the changes in the undo journal in `<target_directory>/cargo-member`.
+    pub fn journal(self, target_directory: Option<&Path>) -> Self {
+        let journal = target_directory.map(ToOwned::to_owned);
+        Self { journal, ..self }
+    }
+
     pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Include<W2> {
         Include {
+            journal: self.journal,
             possibly_empty_workspace_root: self.possibly_empty_workspace_root,
             paths: self.paths,
             force: self.force,
+            absorb: self.absorb,
             dry_run: self.dry_run,
             offline: self.offline,
+            lock_mode: self.lock_mode,
+            sort_members: self.sort_members,
+            multiline_threshold: self.multiline_threshold,
             stderr,
         }
     }
 
     pub fn exec(self) -> anyhow::Result<()> {
         let Self {
+            journal,
             possibly_empty_workspace_root,
             paths,
             force,
+            absorb,
             dry_run,
             offline,
+            lock_mode,
+            sort_members,
+            multiline_threshold,
             mut stderr,
         } = self;
 
         let (possibly_empty_workspace_root, paths) = (possibly_empty_workspace_root?, paths?);
 
-        let modified = paths.iter().try_fold(false, |acc, path| {
+        let recording = crate::journal::start("include", journal.as_deref().filter(|_| !dry_run));
+        crate::journal::watch(&possibly_empty_workspace_root.join("Cargo.toml"));
+
+        let mut root_manifest = RootManifest::read(&possibly_empty_workspace_root)?;
+
+        for path in &paths {
             if !(force || path.join("Cargo.toml").exists()) {
                 return Err(
                     anyhow!("`{}` does not exist", path.join("Cargo.toml")).context(format!(
@@ -93,38 +174,59 @@ impl<W: WriteColor> Include<W> {
                     )),
                 );
             }
-            modify_members(
-                &possibly_empty_workspace_root,
-                &[path],
-                &[],
+
+            let absorbed = if absorb {
+                absorb_workspace(&mut root_manifest, path, dry_run, &mut stderr)?
+            } else {
+                if path.join("Cargo.toml").exists()
+                    && crate::fs::read_toml_edit(path.join("Cargo.toml"))?.contains_key("workspace")
+                {
+                    bail!(
+                        "`{}` is a workspace root. enable `--absorb` to dissolve it",
+                        path,
+                    );
+                }
+                None
+            };
+
+            let (members, exclude) = match &absorbed {
+                Some(absorbed) => (
+                    iter::once(&**path)
+                        .filter(|_| absorbed.is_package)
+                        .chain(absorbed.members.iter().map(Deref::deref))
+                        .collect(),
+                    absorbed.exclude.iter().map(Deref::deref).collect(),
+                ),
+                None => (vec![&**path], vec![]),
+            };
+
+            root_manifest.modify_members(
+                &members,
+                &exclude,
                 &[],
-                &[path],
+                &members,
                 dry_run,
                 &mut stderr,
-            )
-            .map(|p| acc | p)
-        })?;
+            )?;
+        }
 
-        if !modified {
+        if !root_manifest.write(sort_members, multiline_threshold, dry_run)? {
             stderr.warn("`workspace` unchanged")?;
         }
 
         if dry_run {
             stderr.warn("not modifying the manifest due to dry run")?;
         } else if paths.iter().all(|p| p.exists()) {
-            stderr.status("Updating", possibly_empty_workspace_root.join("Cargo.lock"))?;
-
-            cargo_metadata(
-                Some(&possibly_empty_workspace_root.join("Cargo.toml")),
-                false,
-                false,
-                offline,
+            update_lockfile(
                 &possibly_empty_workspace_root,
+                lock_mode,
+                offline,
+                &mut stderr,
             )?;
         } else if !force {
             bail!("some packages do not exist");
         }
-        Ok(())
+        recording.finish()
     }
 }
 
@@ -133,6 +235,11 @@ pub struct Exclude<W> {
     workspace_root: anyhow::Result<PathBuf>,
     paths: anyhow::Result<Vec<PathBuf>>,
     dry_run: bool,
+    offline: bool,
+    lock_mode: LockMode,
+    sort_members: bool,
+    multiline_threshold: Option<usize>,
+    journal: Option<PathBuf>,
     stderr: W,
 }
 
@@ -145,6 +252,11 @@ impl Exclude<NoColor<Sink>> {
             workspace_root: ensure_absolute(workspace_root),
             paths: paths.into_iter().map(ensure_absolute).collect(),
             dry_run: false,
+            offline: false,
+            lock_mode: LockMode::default(),
+            sort_members: false,
+            multiline_threshold: None,
+            journal: None,
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -165,7 +277,7 @@ impl Exclude<NoColor<Sink>> {
                 .into_iter()
                 .map(ensure_absolute)
                 .chain(specs.into_iter().map(|spec| {
-                    let member = metadata.query_for_member(Some(spec.as_ref()))?;
+                    let member = metadata.query_for_member(spec.as_ref())?;
                     Ok(member
                         .manifest_path
                         .parent()
@@ -174,6 +286,11 @@ impl Exclude<NoColor<Sink>> {
                 }))
                 .collect(),
             dry_run: false,
+            offline: false,
+            lock_mode: LockMode::default(),
+            sort_members: false,
+            multiline_threshold: None,
+            journal: None,
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -184,56 +301,81 @@ impl<W: WriteColor> Exclude<W> {
         Self { dry_run, ..self }
     }
 
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn lock_mode(self, lock_mode: LockMode) -> Self {
+        Self { lock_mode, ..self }
+    }
+
+    pub fn sort_members(self, sort_members: bool) -> Self {
+        Self {
+            sort_members,
+            ..self
+        }
+    }
+
+    pub fn multiline_threshold(self, multiline_threshold: Option<usize>) -> Self {
+        Self {
+            multiline_threshold,
+            ..self
+        }
+    }
+
+    /// Records the changes in the undo journal in `<target_directory>/cargo-member`.
+    pub fn journal(self, target_directory: Option<&Path>) -> Self {
+        let journal = target_directory.map(ToOwned::to_owned);
+        Self { journal, ..self }
+    }
+
     pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Exclude<W2> {
         Exclude {
+            journal: self.journal,
             workspace_root: self.workspace_root,
             paths: self.paths,
             dry_run: self.dry_run,
+            offline: self.offline,
+            lock_mode: self.lock_mode,
+            sort_members: self.sort_members,
+            multiline_threshold: self.multiline_threshold,
             stderr,
         }
     }
 
     pub fn exec(self) -> anyhow::Result<()> {
         let Self {
+            journal,
             mut stderr,
             workspace_root,
             paths,
             dry_run,
+            offline,
+            lock_mode,
+            sort_members,
+            multiline_threshold,
         } = self;
 
         let (workspace_root, paths) = (workspace_root?, paths?);
 
-        let modified = paths.iter().try_fold(false, |acc, path| {
-            modify_members(
-                &workspace_root,
-                &[],
-                &[path],
-                &[path],
-                &[],
-                dry_run,
-                &mut stderr,
-            )
-            .map(|p| acc | p)
-        })?;
+        let recording = crate::journal::start("exclude", journal.as_deref().filter(|_| !dry_run));
+        crate::journal::watch(&workspace_root.join("Cargo.toml"));
 
-        if !modified {
+        let mut root_manifest = RootManifest::read(&workspace_root)?;
+        for path in &paths {
+            root_manifest.modify_members(&[], &[path], &[path], &[], dry_run, &mut stderr)?;
+        }
+
+        if !root_manifest.write(sort_members, multiline_threshold, dry_run)? {
             stderr.warn("`workspace` unchanged")?;
         }
 
         if dry_run {
             stderr.warn("not modifying the manifest due to dry run")?;
         } else if !is_empty_workspace(&workspace_root.join("Cargo.toml"))? {
-            stderr.status("Updating", workspace_root.join("Cargo.lock"))?;
-
-            cargo_metadata(
-                Some(&workspace_root.join("Cargo.toml")),
-                false,
-                false,
-                false,
-                &workspace_root,
-            )?;
+            update_lockfile(&workspace_root, lock_mode, offline, &mut stderr)?;
         }
-        Ok(())
+        recording.finish()
     }
 }
 
@@ -242,6 +384,11 @@ pub struct Deactivate<W> {
     workspace_root: anyhow::Result<PathBuf>,
     paths: anyhow::Result<Vec<PathBuf>>,
     dry_run: bool,
+    offline: bool,
+    lock_mode: LockMode,
+    sort_members: bool,
+    multiline_threshold: Option<usize>,
+    journal: Option<PathBuf>,
     stderr: W,
 }
 
@@ -254,6 +401,11 @@ impl Deactivate<NoColor<Sink>> {
             workspace_root: ensure_absolute(workspace_root),
             paths: paths.into_iter().map(ensure_absolute).collect(),
             dry_run: false,
+            offline: false,
+            lock_mode: LockMode::default(),
+            sort_members: false,
+            multiline_threshold: None,
+            journal: None,
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -274,7 +426,7 @@ impl Deactivate<NoColor<Sink>> {
                 .into_iter()
                 .map(ensure_absolute)
                 .chain(specs.into_iter().map(|spec| {
-                    let member = metadata.query_for_member(Some(spec.as_ref()))?;
+                    let member = metadata.query_for_member(spec.as_ref())?;
                     Ok(member
                         .manifest_path
                         .parent()
@@ -283,6 +435,11 @@ impl Deactivate<NoColor<Sink>> {
                 }))
                 .collect(),
             dry_run: false,
+            offline: false,
+            lock_mode: LockMode::default(),
+            sort_members: false,
+            multiline_threshold: None,
+            journal: None,
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -293,102 +450,138 @@ impl<W: WriteColor> Deactivate<W> {
         Self { dry_run, ..self }
     }
 
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn lock_mode(self, lock_mode: LockMode) -> Self {
+        Self { lock_mode, ..self }
+    }
+
+    pub fn sort_members(self, sort_members: bool) -> Self {
+        Self {
+            sort_members,
+            ..self
+        }
+    }
+
+    pub fn multiline_threshold(self, multiline_threshold: Option<usize>) -> Self {
+        Self {
+            multiline_threshold,
+            ..self
+        }
+    }
+
+    /// Records the changes in the undo journal in `<target_directory>/cargo-member`.
+    pub fn journal(self, target_directory: Option<&Path>) -> Self {
+        let journal = target_directory.map(ToOwned::to_owned);
+        Self { journal, ..self }
+    }
+
     pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Deactivate<W2> {
         Deactivate {
+            journal: self.journal,
             workspace_root: self.workspace_root,
             paths: self.paths,
             dry_run: self.dry_run,
+            offline: self.offline,
+            lock_mode: self.lock_mode,
+            sort_members: self.sort_members,
+            multiline_threshold: self.multiline_threshold,
             stderr,
         }
     }
 
     pub fn exec(self) -> anyhow::Result<()> {
         let Self {
+            journal,
             mut stderr,
             workspace_root,
             paths,
             dry_run,
+            offline,
+            lock_mode,
+            sort_members,
+            multiline_threshold,
         } = self;
 
         let (workspace_root, paths) = (workspace_root?, paths?);
 
-        let modified = paths.iter().try_fold(false, |acc, path| {
-            modify_members(
-                &workspace_root,
-                &[],
-                &[],
-                &[path],
-                &[path],
-                dry_run,
-                &mut stderr,
-            )
-            .map(|p| acc | p)
-        })?;
+        let recording =
+            crate::journal::start("deactivate", journal.as_deref().filter(|_| !dry_run));
+        crate::journal::watch(&workspace_root.join("Cargo.toml"));
 
-        if !modified {
+        let mut root_manifest = RootManifest::read(&workspace_root)?;
+        for path in &paths {
+            root_manifest.modify_members(&[], &[], &[path], &[path], dry_run, &mut stderr)?;
+        }
+
+        if !root_manifest.write(sort_members, multiline_threshold, dry_run)? {
             stderr.warn("`workspace` unchanged")?;
         }
 
         if dry_run {
             stderr.warn("not modifying the manifest due to dry run")?;
         } else if !is_empty_workspace(&workspace_root.join("Cargo.toml"))? {
-            stderr.status("Updating", workspace_root.join("Cargo.lock"))?;
-
-            cargo_metadata(
-                Some(&workspace_root.join("Cargo.toml")),
-                false,
-                false,
-                false,
-                &workspace_root,
-            )?;
+            update_lockfile(&workspace_root, lock_mode, offline, &mut stderr)?;
         }
-        Ok(())
+        recording.finish()
     }
 }
 
 #[derive(Debug)]
-pub struct Focus<W> {
+pub struct Sort<W> {
     workspace_root: anyhow::Result<PathBuf>,
-    path: anyhow::Result<PathBuf>,
+    multiline_threshold: Option<usize>,
+    check: bool,
     dry_run: bool,
-    offline: bool,
-    exclude: bool,
     stderr: W,
 }
 
-impl Focus<NoColor<Sink>> {
-    pub fn new(workspace_root: &Path, path: &Path) -> Self {
+impl Sort<NoColor<Sink>> {
+    pub fn new(workspace_root: &Path) -> Self {
         Self {
             workspace_root: ensure_absolute(workspace_root),
-            path: ensure_absolute(path),
+            multiline_threshold: None,
+            check: false,
+            dry_run: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            workspace_root: Ok(metadata.workspace_root.clone()),
+            multiline_threshold: None,
+            check: false,
             dry_run: false,
-            offline: false,
-            exclude: false,
             stderr: NoColor::new(io::sink()),
         }
     }
 }
 
-impl<W: WriteColor> Focus<W> {
-    pub fn dry_run(self, dry_run: bool) -> Self {
-        Self { dry_run, ..self }
+impl<W: WriteColor> Sort<W> {
+    pub fn multiline_threshold(self, multiline_threshold: Option<usize>) -> Self {
+        Self {
+            multiline_threshold,
+            ..self
+        }
     }
 
-    pub fn offline(self, offline: bool) -> Self {
-        Self { offline, ..self }
+    pub fn check(self, check: bool) -> Self {
+        Self { check, ..self }
     }
 
-    pub fn exclude(self, exclude: bool) -> Self {
-        Self { exclude, ..self }
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
     }
 
-    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Focus<W2> {
-        Focus {
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Sort<W2> {
+        Sort {
             workspace_root: self.workspace_root,
-            path: self.path,
+            multiline_threshold: self.multiline_threshold,
+            check: self.check,
             dry_run: self.dry_run,
-            offline: self.offline,
-            exclude: self.exclude,
             stderr,
         }
     }
@@ -396,491 +589,5510 @@ impl<W: WriteColor> Focus<W> {
     pub fn exec(self) -> anyhow::Result<()> {
         let Self {
             workspace_root,
-            path,
+            multiline_threshold,
+            check,
             dry_run,
-            offline,
-            exclude,
             mut stderr,
         } = self;
 
-        let (workspace_root, path) = (workspace_root?, path?);
+        let workspace_root = workspace_root?;
 
-        let mut targets = vec![];
-        for entry in Walk::new(&workspace_root) {
-            match entry {
-                Ok(entry) => {
-                    if entry.path().ends_with("Cargo.toml") {
-                        let dir = entry
-                            .path()
-                            .parent()
-                            .expect("should not empty")
-                            .try_into()?;
-                        if ![&*workspace_root, &*path].contains(&dir) {
-                            targets.push(dir.to_owned());
-                        }
-                    }
-                }
-                Err(err) => stderr.warn(err)?,
+        let mut root_manifest = RootManifest::read(&workspace_root)?;
+        let changed = root_manifest.tidy(multiline_threshold);
+
+        if check {
+            if !changed.is_empty() {
+                bail!(
+                    "{} not sorted",
+                    changed
+                        .iter()
+                        .map(|f| format!("`workspace.{}`", f))
+                        .join(", "),
+                );
             }
+            return Ok(());
         }
-        let targets = targets.iter().map(Deref::deref).collect::<Vec<_>>();
-
-        modify_members(
-            &workspace_root,
-            &[&path],
-            if exclude { &targets } else { &[] },
-            &targets,
-            &[&path],
-            dry_run,
-            &mut stderr,
-        )?;
 
+        for field in &changed {
+            stderr.status("Sorting", format!("`workspace.{}`", field))?;
+        }
+        if !root_manifest.write(false, None, dry_run)? {
+            stderr.warn("`workspace` unchanged")?;
+        }
         if dry_run {
-            stderr.warn("not modifying `workspace` due to dry run")?;
-        } else {
-            stderr.status("Updating", workspace_root.join("Cargo.lock"))?;
-
-            cargo_metadata(
-                Some(&workspace_root.join("Cargo.toml")),
-                false,
-                false,
-                offline,
-                &workspace_root,
-            )?;
+            stderr.warn("not modifying the manifest due to dry run")?;
         }
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct New<W> {
-    possibly_empty_workspace_root: anyhow::Result<PathBuf>,
-    path: anyhow::Result<PathBuf>,
-    cargo_new_registry: Option<String>,
-    cargo_new_vcs: Option<String>,
-    cargo_new_lib: bool,
-    cargo_new_name: Option<String>,
-    cargo_new_stderr_redirection: Stdio,
-    offline: bool,
+pub struct StashPush<W> {
+    workspace_root: PathBuf,
+    target_directory: PathBuf,
+    name: Option<String>,
     dry_run: bool,
     stderr: W,
 }
 
-impl New<NoColor<Sink>> {
-    #[allow(clippy::self_named_constructors)]
-    pub fn new(possibly_empty_workspace_root: &Path, path: &Path) -> Self {
+impl StashPush<NoColor<Sink>> {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
         Self {
-            possibly_empty_workspace_root: ensure_absolute(possibly_empty_workspace_root),
-            path: ensure_absolute(path),
-            cargo_new_registry: None,
-            cargo_new_vcs: None,
-            cargo_new_lib: false,
-            cargo_new_name: None,
-            cargo_new_stderr_redirection: Stdio::null(),
-            offline: false,
+            workspace_root: metadata.workspace_root.clone(),
+            target_directory: metadata.target_directory.clone(),
+            name: None,
             dry_run: false,
             stderr: NoColor::new(io::sink()),
         }
     }
 }
 
-impl<W: WriteColor> New<W> {
-    pub fn cargo_new_registry<S: AsRef<str>>(self, cargo_new_registry: Option<S>) -> Self {
-        let cargo_new_registry = cargo_new_registry.map(|s| s.as_ref().to_owned());
+impl<W: WriteColor> StashPush<W> {
+    pub fn name(self, name: Option<&str>) -> Self {
         Self {
-            cargo_new_registry,
+            name: name.map(ToOwned::to_owned),
             ..self
         }
     }
 
-    pub fn cargo_new_vcs<S: AsRef<str>>(self, cargo_new_vcs: Option<S>) -> Self {
-        let cargo_new_vcs = cargo_new_vcs.map(|s| s.as_ref().to_owned());
-        Self {
-            cargo_new_vcs,
-            ..self
-        }
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
     }
 
-    pub fn cargo_new_lib(self, cargo_new_lib: bool) -> Self {
-        Self {
-            cargo_new_lib,
-            ..self
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> StashPush<W2> {
+        StashPush {
+            workspace_root: self.workspace_root,
+            target_directory: self.target_directory,
+            name: self.name,
+            dry_run: self.dry_run,
+            stderr,
         }
     }
 
-    pub fn cargo_new_name<S: AsRef<str>>(self, cargo_new_name: Option<S>) -> Self {
-        let cargo_new_name = cargo_new_name.map(|s| s.as_ref().to_owned());
-        Self {
-            cargo_new_name,
-            ..self
-        }
-    }
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            workspace_root,
+            target_directory,
+            name,
+            dry_run,
+            mut stderr,
+        } = self;
+
+        let mut stash = crate::stash::Stash::read(&target_directory)?;
+        if let Some(name) = &name {
+            if stash
+                .snapshots
+                .iter()
+                .any(|s| s.name.as_ref() == Some(name))
+            {
+                bail!("stash `{}` already exists", name);
+            }
+        }
+        if name.as_deref().is_some_and(|n| n.parse::<usize>().is_ok()) {
+            bail!("a stash name must not be a number");
+        }
+
+        let cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+        let snapshot = crate::stash::Snapshot::take(name, &cargo_toml);
+        stderr.status("Saving", &snapshot)?;
+        stash.snapshots.insert(0, snapshot);
+        stash.write(&target_directory, dry_run)?;
+
+        if dry_run {
+            stderr.warn("not saving the stash due to dry run")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct StashList<W> {
+    target_directory: PathBuf,
+    stdout: W,
+}
+
+impl StashList<Sink> {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            target_directory: metadata.target_directory.clone(),
+            stdout: io::sink(),
+        }
+    }
+}
+
+impl<W: io::Write> StashList<W> {
+    pub fn stdout<W2: io::Write>(self, stdout: W2) -> StashList<W2> {
+        StashList {
+            target_directory: self.target_directory,
+            stdout,
+        }
+    }
+
+    /// Prints `<index>: [<name>: ]members = [..], exclude = [..]` for each snapshot, newest first.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            target_directory,
+            mut stdout,
+        } = self;
+
+        let stash = crate::stash::Stash::read(&target_directory)?;
+        for (i, snapshot) in stash.snapshots.iter().enumerate() {
+            writeln!(stdout, "{}: {}", i, snapshot)?;
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct StashApply<W> {
+    workspace_root: PathBuf,
+    target_directory: PathBuf,
+    stash: Option<String>,
+    pop: bool,
+    dry_run: bool,
+    offline: bool,
+    lock_mode: LockMode,
+    sort_members: bool,
+    multiline_threshold: Option<usize>,
+    stderr: W,
+}
+
+impl StashApply<NoColor<Sink>> {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            workspace_root: metadata.workspace_root.clone(),
+            target_directory: metadata.target_directory.clone(),
+            stash: None,
+            pop: false,
+            dry_run: false,
+            offline: false,
+            lock_mode: LockMode::default(),
+            sort_members: false,
+            multiline_threshold: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> StashApply<W> {
+    /// The index or name of the snapshot. The newest one if `None`.
+    pub fn stash(self, stash: Option<&str>) -> Self {
+        Self {
+            stash: stash.map(ToOwned::to_owned),
+            ..self
+        }
+    }
+
+    /// Drops the snapshot once it is applied.
+    pub fn pop(self, pop: bool) -> Self {
+        Self { pop, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn lock_mode(self, lock_mode: LockMode) -> Self {
+        Self { lock_mode, ..self }
+    }
+
+    pub fn sort_members(self, sort_members: bool) -> Self {
+        Self {
+            sort_members,
+            ..self
+        }
+    }
+
+    pub fn multiline_threshold(self, multiline_threshold: Option<usize>) -> Self {
+        Self {
+            multiline_threshold,
+            ..self
+        }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> StashApply<W2> {
+        StashApply {
+            workspace_root: self.workspace_root,
+            target_directory: self.target_directory,
+            stash: self.stash,
+            pop: self.pop,
+            dry_run: self.dry_run,
+            offline: self.offline,
+            lock_mode: self.lock_mode,
+            sort_members: self.sort_members,
+            multiline_threshold: self.multiline_threshold,
+            stderr,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            workspace_root,
+            target_directory,
+            stash: spec,
+            pop,
+            dry_run,
+            offline,
+            lock_mode,
+            sort_members,
+            multiline_threshold,
+            mut stderr,
+        } = self;
+
+        let mut stash = crate::stash::Stash::read(&target_directory)?;
+        let i = stash.position(spec.as_deref())?;
+        let snapshot = stash.snapshots[i].clone();
+
+        let mut root_manifest = RootManifest::read(&workspace_root)?;
+        let current = crate::stash::Snapshot::take(None, &root_manifest.cargo_toml);
+        let same_paths = |a: &str, b: &str| {
+            crate::manifest::normalize(&workspace_root.join(a))
+                == crate::manifest::normalize(&workspace_root.join(b))
+        };
+        let to_paths = |entries: Vec<&str>| -> Vec<PathBuf> {
+            entries
+                .into_iter()
+                .map(|e| workspace_root.join(e))
+                .collect()
+        };
+
+        let (add_members, rm_members) =
+            snapshot.merge(&snapshot.members, &current.members, same_paths);
+        let mut restored_members = vec![];
+        for path in to_paths(add_members) {
+            let is_glob = path.as_str().contains(['*', '?', '[']);
+            if is_glob || path.join("Cargo.toml").exists() {
+                restored_members.push(path);
+            } else {
+                stderr.warn(format!("`{}` no longer exists. not restoring it", path))?;
+            }
+        }
+        let (add_exclude, rm_exclude) =
+            snapshot.merge(&snapshot.exclude, &current.exclude, same_paths);
+        let (rm_members, add_exclude, rm_exclude) = (
+            to_paths(rm_members),
+            to_paths(add_exclude),
+            to_paths(rm_exclude),
+        );
+        root_manifest.modify_members(
+            &restored_members
+                .iter()
+                .map(Deref::deref)
+                .collect::<Vec<_>>(),
+            &add_exclude.iter().map(Deref::deref).collect::<Vec<_>>(),
+            &rm_members.iter().map(Deref::deref).collect::<Vec<_>>(),
+            &rm_exclude.iter().map(Deref::deref).collect::<Vec<_>>(),
+            dry_run,
+            &mut stderr,
+        )?;
+
+        let saved_default_members = snapshot.default_members.clone().unwrap_or_default();
+        let current_default_members = current.default_members.clone().unwrap_or_default();
+        let (add, rm) =
+            snapshot.merge(&saved_default_members, &current_default_members, same_paths);
+        if !(add.is_empty() && rm.is_empty()) {
+            let (add, rm) = (to_paths(add), to_paths(rm));
+            root_manifest.modify_array(
+                "default-members",
+                &add.iter().map(Deref::deref).collect::<Vec<_>>(),
+                &rm.iter().map(Deref::deref).collect::<Vec<_>>(),
+                dry_run,
+                &mut stderr,
+            )?;
+            if snapshot.default_members.is_none() {
+                if let Some(workspace) = root_manifest
+                    .cargo_toml
+                    .get_mut("workspace")
+                    .and_then(toml_edit::Item::as_table_like_mut)
+                {
+                    if workspace
+                        .get("default-members")
+                        .and_then(toml_edit::Item::as_array)
+                        .is_some_and(toml_edit::Array::is_empty)
+                    {
+                        workspace.remove("default-members");
+                    }
+                }
+            }
+        }
+
+        let modified = root_manifest.write(sort_members, multiline_threshold, dry_run)?;
+        if !modified {
+            stderr.warn("`workspace` unchanged")?;
+        }
+
+        if pop {
+            stderr.status_with_color("Dropping", &snapshot, termcolor::Color::Red)?;
+            stash.snapshots.remove(i);
+            stash.write(&target_directory, dry_run)?;
+        }
+
+        if dry_run {
+            stderr.warn("not modifying the manifest due to dry run")?;
+        } else if modified && !is_empty_workspace(&workspace_root.join("Cargo.toml"))? {
+            update_lockfile(&workspace_root, lock_mode, offline, &mut stderr)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Focus<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    path: anyhow::Result<PathBuf>,
+    dry_run: bool,
+    offline: bool,
+    exclude: bool,
+    journal: Option<PathBuf>,
+    stderr: W,
+}
+
+impl Focus<NoColor<Sink>> {
+    pub fn new(workspace_root: &Path, path: &Path) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            path: ensure_absolute(path),
+            dry_run: false,
+            offline: false,
+            exclude: false,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Focus<W> {
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn exclude(self, exclude: bool) -> Self {
+        Self { exclude, ..self }
+    }
+
+    /// Records the changes in the undo journal in `<target_directory>/cargo-member`.
+    pub fn journal(self, target_directory: Option<&Path>) -> Self {
+        let journal = target_directory.map(ToOwned::to_owned);
+        Self { journal, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Focus<W2> {
+        Focus {
+            journal: self.journal,
+            workspace_root: self.workspace_root,
+            path: self.path,
+            dry_run: self.dry_run,
+            offline: self.offline,
+            exclude: self.exclude,
+            stderr,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            journal,
+            workspace_root,
+            path,
+            dry_run,
+            offline,
+            exclude,
+            mut stderr,
+        } = self;
+
+        let (workspace_root, path) = (workspace_root?, path?);
+
+        let recording = crate::journal::start("focus", journal.as_deref().filter(|_| !dry_run));
+        crate::journal::watch(&workspace_root.join("Cargo.toml"));
+
+        let mut targets = vec![];
+        for entry in Walk::new(&workspace_root) {
+            match entry {
+                Ok(entry) => {
+                    if entry.path().ends_with("Cargo.toml") {
+                        let dir = entry
+                            .path()
+                            .parent()
+                            .expect("should not empty")
+                            .try_into()?;
+                        if ![&*workspace_root, &*path].contains(&dir) {
+                            targets.push(dir.to_owned());
+                        }
+                    }
+                }
+                Err(err) => stderr.warn(err)?,
+            }
+        }
+        let targets = targets.iter().map(Deref::deref).collect::<Vec<_>>();
+
+        modify_members(
+            &workspace_root,
+            &[&path],
+            if exclude { &targets } else { &[] },
+            &targets,
+            &[&path],
+            dry_run,
+            &mut stderr,
+        )?;
+
+        if dry_run {
+            stderr.warn("not modifying `workspace` due to dry run")?;
+        } else {
+            stderr.status("Updating", workspace_root.join("Cargo.lock"))?;
+
+            cargo_metadata(
+                Some(&workspace_root.join("Cargo.toml")),
+                false,
+                false,
+                offline,
+                &workspace_root,
+            )?;
+        }
+        recording.finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct New<W> {
+    possibly_empty_workspace_root: anyhow::Result<PathBuf>,
+    path: anyhow::Result<PathBuf>,
+    cargo_new_registry: Option<String>,
+    cargo_new_vcs: Option<String>,
+    cargo_new_lib: bool,
+    cargo_new_name: Option<String>,
+    cargo_new_stderr_redirection: Stdio,
+    offline: bool,
+    dry_run: bool,
+    journal: Option<PathBuf>,
+    stderr: W,
+}
+
+impl New<NoColor<Sink>> {
+    #[allow(clippy::self_named_constructors)]
+    pub fn new(possibly_empty_workspace_root: &Path, path: &Path) -> Self {
+        Self {
+            possibly_empty_workspace_root: ensure_absolute(possibly_empty_workspace_root),
+            path: ensure_absolute(path),
+            cargo_new_registry: None,
+            cargo_new_vcs: None,
+            cargo_new_lib: false,
+            cargo_new_name: None,
+            cargo_new_stderr_redirection: Stdio::null(),
+            offline: false,
+            dry_run: false,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> New<W> {
+    pub fn cargo_new_registry<S: AsRef<str>>(self, cargo_new_registry: Option<S>) -> Self {
+        let cargo_new_registry = cargo_new_registry.map(|s| s.as_ref().to_owned());
+        Self {
+            cargo_new_registry,
+            ..self
+        }
+    }
+
+    pub fn cargo_new_vcs<S: AsRef<str>>(self, cargo_new_vcs: Option<S>) -> Self {
+        let cargo_new_vcs = cargo_new_vcs.map(|s| s.as_ref().to_owned());
+        Self {
+            cargo_new_vcs,
+            ..self
+        }
+    }
+
+    pub fn cargo_new_lib(self, cargo_new_lib: bool) -> Self {
+        Self {
+            cargo_new_lib,
+            ..self
+        }
+    }
+
+    pub fn cargo_new_name<S: AsRef<str>>(self, cargo_new_name: Option<S>) -> Self {
+        let cargo_new_name = cargo_new_name.map(|s| s.as_ref().to_owned());
+        Self {
+            cargo_new_name,
+            ..self
+        }
+    }
+
+    pub fn cargo_new_stderr_redirection(self, cargo_new_stderr_redirection: Stdio) -> Self {
+        Self {
+            cargo_new_stderr_redirection,
+            ..self
+        }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Records the changes in the undo journal in `<target_directory>/cargo-member`.
+    pub fn journal(self, target_directory: Option<&Path>) -> Self {
+        let journal = target_directory.map(ToOwned::to_owned);
+        Self { journal, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> New<W2> {
+        New {
+            journal: self.journal,
+            possibly_empty_workspace_root: self.possibly_empty_workspace_root,
+            path: self.path,
+            cargo_new_registry: self.cargo_new_registry,
+            cargo_new_vcs: self.cargo_new_vcs,
+            cargo_new_lib: self.cargo_new_lib,
+            cargo_new_name: self.cargo_new_name,
+            cargo_new_stderr_redirection: self.cargo_new_stderr_redirection,
+            offline: self.offline,
+            dry_run: self.dry_run,
+            stderr,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            journal,
+            possibly_empty_workspace_root,
+            path,
+            cargo_new_registry,
+            cargo_new_vcs,
+            cargo_new_lib,
+            cargo_new_name,
+            cargo_new_stderr_redirection,
+            offline,
+            dry_run,
+            mut stderr,
+        } = self;
+
+        let (possibly_empty_workspace_root, path) = (possibly_empty_workspace_root?, path?);
+
+        let recording = crate::journal::start("new", journal.as_deref().filter(|_| !dry_run));
+        crate::journal::watch(&possibly_empty_workspace_root.join("Cargo.toml"));
+
+        Include::new(&possibly_empty_workspace_root, [&path])
+            .force(true)
+            .dry_run(dry_run)
+            .stderr(&mut stderr)
+            .exec()?;
+
+        if dry_run {
+            stderr.warn("not creating a new package due to dry run")?;
+        } else {
+            let cargo_exe = env::var_os("CARGO").with_context(|| "`$CARGO` should be present")?;
+
+            let args = Args::new()
+                .arg("new")
+                .option(cargo_new_registry.as_ref(), "--registry")
+                .option(cargo_new_vcs.as_ref(), "--vcs")
+                .flag(cargo_new_lib, "--lib")
+                .option(cargo_new_name.as_ref(), "--name")
+                .flag(offline, "--offline")
+                .arg(&path);
+
+            let output = Command::new(&cargo_exe)
+                .args(&args)
+                .current_dir(&possibly_empty_workspace_root)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(cargo_new_stderr_redirection)
+                .output()
+                .with_context(|| format!("failed to execute `{}`", cargo_exe.to_string_lossy()))?;
+
+            stderr.write_all(&output.stderr)?;
+
+            if !output.status.success() {
+                bail!(
+                    "`{}{}` failed ({})",
+                    shell_escape::escape(cargo_exe.to_string_lossy()),
+                    args.0.iter().format_with("", |s, f| f(&format_args!(
+                        " {}",
+                        shell_escape::escape(s.to_string_lossy()),
+                    ))),
+                    output.status,
+                );
+            }
+            crate::journal::created(&path);
+
+            stderr.status("Updating", possibly_empty_workspace_root.join("Cargo.lock"))?;
+
+            cargo_metadata(None, false, false, offline, &possibly_empty_workspace_root)?;
+        }
+        recording.finish()
+    }
+}
+
+#[derive(Default, Debug)]
+struct Args(Vec<OsString>);
+
+impl Args {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn flag(mut self, val: bool, long: &'static str) -> Self {
+        if val {
+            self.0.push(long.to_owned().into());
+        }
+        self
+    }
+
+    fn option(mut self, val: Option<impl AsRef<OsStr>>, long: &'static str) -> Self {
+        if let Some(val) = val {
+            self.0.push(long.to_owned().into());
+            self.0.push(val.as_ref().to_owned());
+        }
+        self
+    }
+
+    fn arg(mut self, val: impl AsRef<OsStr>) -> Self {
+        self.0.push(val.as_ref().to_owned());
+        self
+    }
+}
+
+impl AsRef<[OsString]> for Args {
+    fn as_ref(&self) -> &[OsString] {
+        &self.0
+    }
+}
+
+impl Display for Args {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, fmt)
+    }
+}
+
+impl<'a> IntoIterator for &'a Args {
+    type Item = &'a OsString;
+    type IntoIter = slice::Iter<'a, OsString>;
+
+    fn into_iter(self) -> slice::Iter<'a, OsString> {
+        self.0.iter()
+    }
+}
+
+impl IntoIterator for Args {
+    type Item = OsString;
+    type IntoIter = vec::IntoIter<OsString>;
+
+    fn into_iter(self) -> vec::IntoIter<OsString> {
+        self.0.into_iter()
+    }
+}
+
+#[derive(Debug)]
+pub struct Cp<W> {
+    src: anyhow::Result<PathBuf>,
+    dst: anyhow::Result<PathBuf>,
+    dry_run: bool,
+    no_rename: bool,
+    name_prefix: Option<String>,
+    journal: Option<PathBuf>,
+    stderr: W,
+}
+
+impl Cp<NoColor<Sink>> {
+    pub fn new(src: &Path, dst: &Path) -> Self {
+        Self {
+            src: ensure_absolute(src),
+            dst: ensure_absolute(dst),
+            dry_run: false,
+            no_rename: false,
+            name_prefix: None,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    pub fn from_metadata(metadata: &Metadata, src: &str, dst: &Path) -> Self {
+        Self {
+            src: metadata.query_for_member(src).map(|member| {
+                member
+                    .manifest_path
+                    .parent()
+                    .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                    .to_owned()
+            }),
+            dst: ensure_absolute(dst),
+            dry_run: false,
+            no_rename: false,
+            name_prefix: None,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Cp<W> {
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn no_rename(self, no_rename: bool) -> Self {
+        Self { no_rename, ..self }
+    }
+
+    pub fn name_prefix(self, name_prefix: Option<&str>) -> Self {
+        let name_prefix = name_prefix.map(ToOwned::to_owned);
+        Self {
+            name_prefix,
+            ..self
+        }
+    }
+
+    /// Records the changes in the undo journal in `<target_directory>/cargo-member`.
+    pub fn journal(self, target_directory: Option<&Path>) -> Self {
+        let journal = target_directory.map(ToOwned::to_owned);
+        Self { journal, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Cp<W2> {
+        Cp {
+            journal: self.journal,
+            src: self.src,
+            dst: self.dst,
+            dry_run: self.dry_run,
+            no_rename: self.no_rename,
+            name_prefix: self.name_prefix,
+            stderr,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            journal,
+            mut stderr,
+            src,
+            dst,
+            dry_run,
+            no_rename,
+            name_prefix,
+        } = self;
+
+        let (src, dst) = (src?, dst?);
+
+        let recording = crate::journal::start("cp", journal.as_deref().filter(|_| !dry_run));
+
+        let dst = if dst.exists() {
+            dst.join(src.file_name().expect("should be absolute"))
+        } else {
+            dst
+        };
+
+        ensure!(!dst.exists(), "`{}` exists", dst);
+
+        let mut cargo_toml = crate::fs::read_toml_edit(src.join("Cargo.toml"))
+            .with_context(|| format!("`{}` does not seem to be a package", src))?;
+        if let Some(package) = cargo_toml["package"].as_table_mut() {
+            package.remove("workspace");
+            if !no_rename {
+                let file_name = dst.file_name().expect("should exist");
+                package["name"] =
+                    toml_edit::value(prefixed_name(name_prefix.as_deref(), file_name));
+            }
+        }
+
+        stderr.status("Copying", format!("`{}` to `{}`", src, dst))?;
+
+        copy_package(&src, &dst, &["Cargo.toml"], dry_run, &mut stderr)?;
+
+        crate::fs::write(dst.join("Cargo.toml"), cargo_toml.to_string(), dry_run)?;
+        crate::journal::created(&dst);
+
+        if let [_, dst_workspace_root] = &*manifest_dirs(&dst).collect::<Vec<_>>() {
+            stderr.status_with_color(
+                "Found",
+                format!("workspace at {}", dst_workspace_root),
+                termcolor::Color::Cyan,
+            )?;
+
+            modify_members(
+                dst_workspace_root,
+                &[&dst],
+                &[],
+                &[],
+                &[&dst],
+                dry_run,
+                &mut stderr,
+            )?;
+        }
+
+        if dry_run {
+            stderr.warn("not copying due to dry run")?;
+        }
+        recording.finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct Rm<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    target_directory: anyhow::Result<PathBuf>,
+    paths: anyhow::Result<Vec<PathBuf>>,
+    force: bool,
+    permanent: bool,
+    dry_run: bool,
+    offline: bool,
+    lock_mode: LockMode,
+    sort_members: bool,
+    multiline_threshold: Option<usize>,
+    journal: Option<PathBuf>,
+    stderr: W,
+}
+
+impl Rm<NoColor<Sink>> {
+    /// Removes the packages, moving them into the trash in `<workspace_root>/target`.
+    pub fn new<Ps: IntoIterator<Item = P>, P: AsRef<Path>>(
+        workspace_root: &Path,
+        paths: Ps,
+    ) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            target_directory: ensure_absolute(workspace_root.join("target")),
+            paths: paths.into_iter().map(ensure_absolute).collect(),
+            force: false,
+            permanent: false,
+            dry_run: false,
+            offline: false,
+            lock_mode: LockMode::default(),
+            sort_members: false,
+            multiline_threshold: None,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    pub fn from_metadata<
+        Ps: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+        Ss: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    >(
+        metadata: &Metadata,
+        paths: Ps,
+        specs: Ss,
+    ) -> Self {
+        Self {
+            workspace_root: Ok(metadata.workspace_root.clone()),
+            target_directory: Ok(metadata.target_directory.clone()),
+            paths: paths
+                .into_iter()
+                .map(ensure_absolute)
+                .chain(specs.into_iter().map(|spec| {
+                    let member = metadata.query_for_member(spec.as_ref())?;
+                    Ok(member
+                        .manifest_path
+                        .parent()
+                        .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                        .to_owned())
+                }))
+                .collect(),
+            force: false,
+            permanent: false,
+            dry_run: false,
+            offline: false,
+            lock_mode: LockMode::default(),
+            sort_members: false,
+            multiline_threshold: None,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Rm<W> {
+    pub fn force(self, force: bool) -> Self {
+        Self { force, ..self }
+    }
+
+    /// Uses the trash in `<target_directory>/cargo-member` instead of the default one.
+    pub fn target_directory(self, target_directory: &Path) -> Self {
+        let target_directory = ensure_absolute(target_directory);
+        Self {
+            target_directory,
+            ..self
+        }
+    }
+
+    /// Deletes the packages instead of moving them into the trash.
+    pub fn permanent(self, permanent: bool) -> Self {
+        Self { permanent, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn lock_mode(self, lock_mode: LockMode) -> Self {
+        Self { lock_mode, ..self }
+    }
+
+    pub fn sort_members(self, sort_members: bool) -> Self {
+        Self {
+            sort_members,
+            ..self
+        }
+    }
+
+    pub fn multiline_threshold(self, multiline_threshold: Option<usize>) -> Self {
+        Self {
+            multiline_threshold,
+            ..self
+        }
+    }
+
+    /// Records the changes in the undo journal in `<target_directory>/cargo-member`.
+    pub fn journal(self, target_directory: Option<&Path>) -> Self {
+        let journal = target_directory.map(ToOwned::to_owned);
+        Self { journal, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Rm<W2> {
+        Rm {
+            journal: self.journal,
+            stderr,
+            workspace_root: self.workspace_root,
+            target_directory: self.target_directory,
+            paths: self.paths,
+            force: self.force,
+            permanent: self.permanent,
+            dry_run: self.dry_run,
+            offline: self.offline,
+            lock_mode: self.lock_mode,
+            sort_members: self.sort_members,
+            multiline_threshold: self.multiline_threshold,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            journal,
+            mut stderr,
+            workspace_root,
+            target_directory,
+            paths,
+            force,
+            permanent,
+            dry_run,
+            offline,
+            lock_mode,
+            sort_members,
+            multiline_threshold,
+        } = self;
+
+        let (workspace_root, target_directory, paths) =
+            (workspace_root?, target_directory?, paths?);
+
+        let recording = crate::journal::start("rm", journal.as_deref().filter(|_| !dry_run));
+        crate::journal::watch(&workspace_root.join("Cargo.toml"));
+
+        let mut root_manifest = RootManifest::read(&workspace_root)?;
+
+        // Check every path before removing anything, so that an invalid path leaves both the
+        // directories and the manifest intact.
+        for path in &paths {
+            if !(force || path.join("Cargo.toml").exists()) {
+                return Err(
+                    anyhow!("`{}` does not exist", path.join("Cargo.toml")).context(format!(
+                        "`{}` does not seem to be a package. enable `--force` to remove",
+                        path,
+                    )),
+                );
+            }
+        }
+
+        for path in &paths {
+            stderr.status_with_color(
+                "Removing",
+                format!("directory `{}`", path),
+                termcolor::Color::Red,
+            )?;
+            if permanent {
+                crate::journal::deleted(path);
+                crate::fs::remove_dir_all(path, dry_run)?;
+            } else if !dry_run {
+                let trash = crate::trash::put(&target_directory, path)?;
+                crate::journal::trashed(path, trash);
+            }
+            root_manifest.modify_members(&[], &[], &[path], &[path], dry_run, &mut stderr)?;
+        }
+
+        if !root_manifest.write(sort_members, multiline_threshold, dry_run)? {
+            stderr.warn("`workspace` unchanged")?;
+        }
+
+        if dry_run {
+            stderr.warn("not modifying the manifest due to dry run")?;
+        } else if !is_empty_workspace(&workspace_root.join("Cargo.toml"))? {
+            update_lockfile(&workspace_root, lock_mode, offline, &mut stderr)?;
+        }
+        recording.finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct Mv<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    target_directory: anyhow::Result<PathBuf>,
+    src: anyhow::Result<PathBuf>,
+    dst: anyhow::Result<PathBuf>,
+    to_workspace: anyhow::Result<Option<PathBuf>>,
+    dry_run: bool,
+    offline: bool,
+    lock_mode: LockMode,
+    no_rename: bool,
+    name_prefix: Option<String>,
+    journal: Option<PathBuf>,
+    stderr: W,
+}
+
+impl Mv<NoColor<Sink>> {
+    /// Moves the package, putting the source into the trash in `<workspace_root>/target`.
+    pub fn new(workspace_root: &Path, src: &Path, dst: &Path) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            target_directory: ensure_absolute(workspace_root.join("target")),
+            src: ensure_absolute(src),
+            dst: ensure_absolute(dst),
+            to_workspace: Ok(None),
+            dry_run: false,
+            offline: false,
+            lock_mode: LockMode::default(),
+            no_rename: false,
+            name_prefix: None,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    pub fn from_metadata(metadata: &Metadata, src: &str, dst: &Path) -> Self {
+        Self {
+            workspace_root: Ok(metadata.workspace_root.clone()),
+            target_directory: Ok(metadata.target_directory.clone()),
+            src: metadata.query_for_member(src).map(|member| {
+                member
+                    .manifest_path
+                    .parent()
+                    .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                    .to_owned()
+            }),
+            dst: ensure_absolute(dst),
+            to_workspace: Ok(None),
+            dry_run: false,
+            offline: false,
+            lock_mode: LockMode::default(),
+            no_rename: false,
+            name_prefix: None,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Mv<W> {
+    /// Uses the trash in `<target_directory>/cargo-member` instead of the default one.
+    pub fn target_directory(self, target_directory: &Path) -> Self {
+        let target_directory = ensure_absolute(target_directory);
+        Self {
+            target_directory,
+            ..self
+        }
+    }
+
+    pub fn to_workspace(self, to_workspace: Option<&Path>) -> Self {
+        let to_workspace = to_workspace.map(ensure_absolute).transpose();
+        Self {
+            to_workspace,
+            ..self
+        }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn lock_mode(self, lock_mode: LockMode) -> Self {
+        Self { lock_mode, ..self }
+    }
+
+    pub fn no_rename(self, no_rename: bool) -> Self {
+        Self { no_rename, ..self }
+    }
+
+    pub fn name_prefix(self, name_prefix: Option<&str>) -> Self {
+        let name_prefix = name_prefix.map(ToOwned::to_owned);
+        Self {
+            name_prefix,
+            ..self
+        }
+    }
+
+    /// Records the changes in the undo journal in `<target_directory>/cargo-member`.
+    pub fn journal(self, target_directory: Option<&Path>) -> Self {
+        let journal = target_directory.map(ToOwned::to_owned);
+        Self { journal, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Mv<W2> {
+        Mv {
+            journal: self.journal,
+            stderr,
+            workspace_root: self.workspace_root,
+            target_directory: self.target_directory,
+            src: self.src,
+            dst: self.dst,
+            to_workspace: self.to_workspace,
+            dry_run: self.dry_run,
+            offline: self.offline,
+            lock_mode: self.lock_mode,
+            no_rename: self.no_rename,
+            name_prefix: self.name_prefix,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            journal,
+            mut stderr,
+            workspace_root,
+            target_directory,
+            src,
+            dst,
+            to_workspace,
+            dry_run,
+            offline,
+            lock_mode,
+            no_rename,
+            name_prefix,
+        } = self;
+
+        let (workspace_root, target_directory, src, dst) =
+            (workspace_root?, target_directory?, src?, dst?);
+
+        let recording = crate::journal::start("mv", journal.as_deref().filter(|_| !dry_run));
+        crate::journal::watch(&workspace_root.join("Cargo.toml"));
+
+        if let Some(dst_workspace_root) = to_workspace? {
+            move_to_workspace(
+                &workspace_root,
+                &target_directory,
+                &dst_workspace_root,
+                &src,
+                &dst,
+                no_rename,
+                name_prefix.as_deref(),
+                offline,
+                lock_mode,
+                dry_run,
+                stderr,
+            )?;
+            return recording.finish();
+        }
+
+        Cp::new(&src, &dst)
+            .dry_run(dry_run)
+            .no_rename(no_rename)
+            .name_prefix(name_prefix.as_deref())
+            .stderr(&mut stderr)
+            .exec()?;
+
+        Rm::new(&workspace_root, [src])
+            .target_directory(&target_directory)
+            .dry_run(dry_run)
+            .offline(offline)
+            .lock_mode(lock_mode)
+            .stderr(stderr)
+            .exec()?;
+        recording.finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct Undo<W> {
+    target_directory: anyhow::Result<PathBuf>,
+    steps: usize,
+    dry_run: bool,
+    offline: bool,
+    stderr: W,
+}
+
+impl Undo<NoColor<Sink>> {
+    /// Replays the journal in `<target_directory>/cargo-member`.
+    pub fn new(target_directory: &Path) -> Self {
+        Self {
+            target_directory: ensure_absolute(target_directory),
+            steps: 1,
+            dry_run: false,
+            offline: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            target_directory: Ok(metadata.target_directory.clone()),
+            steps: 1,
+            dry_run: false,
+            offline: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Undo<W> {
+    pub fn steps(self, steps: usize) -> Self {
+        Self { steps, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Undo<W2> {
+        Undo {
+            target_directory: self.target_directory,
+            steps: self.steps,
+            dry_run: self.dry_run,
+            offline: self.offline,
+            stderr,
+        }
+    }
+
+    /// Reverts the last `steps` recorded operations, newest first. Nothing is touched if a file or
+    /// directory they changed has been changed again since. `Cargo.lock` is resolved again instead
+    /// of restored in that case.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            target_directory,
+            steps,
+            dry_run,
+            offline,
+            mut stderr,
+        } = self;
+
+        let target_directory = target_directory?;
+
+        let mut journal = crate::journal::Journal::read(&target_directory)?;
+        if journal.entries.is_empty() {
+            bail!("nothing to undo");
+        }
+        if steps > journal.entries.len() {
+            bail!("only {} operation(s) can be undone", journal.entries.len(),);
+        }
+        let entries = journal.entries.split_off(journal.entries.len() - steps);
+
+        let is_lockfile = |path: &Path| path.file_name() == Some("Cargo.lock");
+
+        let mut contents = HashMap::<&Path, Option<String>>::new();
+        let mut dirs = HashMap::<&Path, bool>::new();
+        let mut conflicts = vec![];
+        for entry in entries.iter().rev() {
+            let command = &entry.command;
+            for file in &entry.files {
+                let current = match contents.get(&*file.path) {
+                    Some(current) => current.clone(),
+                    None => std::fs::read_to_string(&file.path).ok(),
+                };
+                if current != file.after && !is_lockfile(&file.path) {
+                    conflicts.push(format!("`{}` has changed since `{}`", file.path, command));
+                }
+                contents.insert(&file.path, file.before.clone());
+            }
+            for created in &entry.created {
+                let exists = dirs
+                    .get(&*created.path)
+                    .copied()
+                    .unwrap_or_else(|| created.path.exists());
+                if !exists
+                    || crate::journal::fingerprint(&created.path).ok().as_ref()
+                        != Some(&created.fingerprint)
+                {
+                    conflicts.push(format!(
+                        "`{}` has changed since `{}`",
+                        created.path, command
+                    ));
+                }
+                dirs.insert(&created.path, false);
+            }
+            for trashed in &entry.trashed {
+                let exists = dirs
+                    .get(&*trashed.path)
+                    .copied()
+                    .unwrap_or_else(|| trashed.path.exists());
+                if !trashed.trash.exists() {
+                    conflicts.push(format!("`{}` is no longer in the trash", trashed.trash));
+                } else if exists {
+                    conflicts.push(format!("`{}` exists", trashed.path));
+                }
+                dirs.insert(&trashed.path, true);
+            }
+            for deleted in &entry.deleted {
+                conflicts.push(format!(
+                    "`{}` was deleted permanently by `{}`",
+                    deleted, command,
+                ));
+            }
+        }
+        if !conflicts.is_empty() {
+            bail!(
+                "refusing to undo:\n{}",
+                conflicts.iter().map(|c| format!("  {}", c)).join("\n"),
+            );
+        }
+
+        let mut stale_lockfiles = vec![];
+        for entry in entries.iter().rev() {
+            stderr.status("Undoing", format!("`{}`", entry.command))?;
+            for created in &entry.created {
+                stderr.status_with_color(
+                    "Removing",
+                    format!("directory `{}`", created.path),
+                    termcolor::Color::Red,
+                )?;
+                if !dry_run {
+                    crate::trash::put(&target_directory, &created.path)?;
+                }
+            }
+            for trashed in &entry.trashed {
+                stderr.status("Restoring", format!("directory `{}`", trashed.path))?;
+                if let Some(parent) = trashed.path.parent() {
+                    crate::fs::create_dir_all(parent, dry_run)?;
+                }
+                crate::fs::rename(&trashed.trash, &trashed.path, dry_run)?;
+            }
+            for file in &entry.files {
+                if is_lockfile(&file.path) && std::fs::read_to_string(&file.path).ok() != file.after
+                {
+                    stale_lockfiles.push(file.path.parent().expect("should not be root"));
+                    continue;
+                }
+                stderr.status("Restoring", &file.path)?;
+                match &file.before {
+                    Some(before) => crate::fs::write(&file.path, before, dry_run)?,
+                    None => crate::fs::remove_file(&file.path, dry_run)?,
+                }
+            }
+        }
+        journal.write(&target_directory, dry_run)?;
+
+        if dry_run {
+            stderr.warn("not undoing due to dry run")?;
+        } else {
+            for workspace_root in stale_lockfiles.into_iter().unique() {
+                update_lockfile(workspace_root, LockMode::Resolve, offline, &mut stderr)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct TrashList<W> {
+    target_directory: PathBuf,
+    stdout: W,
+}
+
+impl TrashList<Sink> {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            target_directory: metadata.target_directory.clone(),
+            stdout: io::sink(),
+        }
+    }
+}
+
+impl<W: io::Write> TrashList<W> {
+    pub fn stdout<W2: io::Write>(self, stdout: W2) -> TrashList<W2> {
+        TrashList {
+            target_directory: self.target_directory,
+            stdout,
+        }
+    }
+
+    /// Prints `<index>: <date> <original path>` for each item in the trash, newest first.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            target_directory,
+            mut stdout,
+        } = self;
+
+        let trash = crate::trash::Trash::read(&target_directory)?;
+        for (i, item) in trash.items.iter().enumerate() {
+            writeln!(stdout, "{}: {}", i, item)?;
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct TrashRestore<W> {
+    workspace_root: PathBuf,
+    target_directory: PathBuf,
+    item: Option<String>,
+    dry_run: bool,
+    offline: bool,
+    lock_mode: LockMode,
+    sort_members: bool,
+    multiline_threshold: Option<usize>,
+    journal: Option<PathBuf>,
+    stderr: W,
+}
+
+impl TrashRestore<NoColor<Sink>> {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            workspace_root: metadata.workspace_root.clone(),
+            target_directory: metadata.target_directory.clone(),
+            item: None,
+            dry_run: false,
+            offline: false,
+            lock_mode: LockMode::default(),
+            sort_members: false,
+            multiline_threshold: None,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> TrashRestore<W> {
+    /// The index or directory name of the item. The newest one if `None`.
+    pub fn item(self, item: Option<&str>) -> Self {
+        Self {
+            item: item.map(ToOwned::to_owned),
+            ..self
+        }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn lock_mode(self, lock_mode: LockMode) -> Self {
+        Self { lock_mode, ..self }
+    }
+
+    pub fn sort_members(self, sort_members: bool) -> Self {
+        Self {
+            sort_members,
+            ..self
+        }
+    }
+
+    pub fn multiline_threshold(self, multiline_threshold: Option<usize>) -> Self {
+        Self {
+            multiline_threshold,
+            ..self
+        }
+    }
+
+    /// Records the changes in the undo journal in `<target_directory>/cargo-member`.
+    pub fn journal(self, target_directory: Option<&Path>) -> Self {
+        let journal = target_directory.map(ToOwned::to_owned);
+        Self { journal, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> TrashRestore<W2> {
+        TrashRestore {
+            workspace_root: self.workspace_root,
+            target_directory: self.target_directory,
+            item: self.item,
+            dry_run: self.dry_run,
+            offline: self.offline,
+            lock_mode: self.lock_mode,
+            sort_members: self.sort_members,
+            multiline_threshold: self.multiline_threshold,
+            journal: self.journal,
+            stderr,
+        }
+    }
+
+    /// Moves the item back to where it was and adds it to `workspace.members` if it is a package
+    /// inside the workspace.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            workspace_root,
+            target_directory,
+            item: spec,
+            dry_run,
+            offline,
+            lock_mode,
+            sort_members,
+            multiline_threshold,
+            journal,
+            mut stderr,
+        } = self;
+
+        let mut trash = crate::trash::Trash::read(&target_directory)?;
+        let i = trash.position(spec.as_deref())?;
+        let item = trash.items.remove(i);
+        ensure!(!item.path.exists(), "`{}` exists", item.path);
+        let is_package = item.trash.join("Cargo.toml").exists();
+
+        let recording =
+            crate::journal::start("trash restore", journal.as_deref().filter(|_| !dry_run));
+        crate::journal::watch(&workspace_root.join("Cargo.toml"));
+
+        stderr.status("Restoring", format!("directory `{}`", item.path))?;
+        if let Some(parent) = item.path.parent() {
+            crate::fs::create_dir_all(parent, dry_run)?;
+        }
+        crate::fs::rename(&item.trash, &item.path, dry_run)?;
+        crate::journal::created(&item.path);
+        trash.write(&target_directory, dry_run)?;
+
+        let mut modified = false;
+        if !is_package {
+            stderr.warn(format!(
+                "`{}` is not a package. not including it",
+                item.path,
+            ))?;
+        } else if !item.path.starts_with(&workspace_root) {
+            stderr.warn(format!(
+                "`{}` is not inside the workspace at {}. not including it",
+                item.path, workspace_root,
+            ))?;
+        } else {
+            let mut root_manifest = RootManifest::read(&workspace_root)?;
+            root_manifest.modify_members(
+                &[&item.path],
+                &[],
+                &[],
+                &[&item.path],
+                dry_run,
+                &mut stderr,
+            )?;
+            modified = root_manifest.write(sort_members, multiline_threshold, dry_run)?;
+        }
+
+        if dry_run {
+            stderr.warn("not restoring due to dry run")?;
+        } else if modified && !is_empty_workspace(&workspace_root.join("Cargo.toml"))? {
+            update_lockfile(&workspace_root, lock_mode, offline, &mut stderr)?;
+        }
+        recording.finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct TrashEmpty<W> {
+    target_directory: PathBuf,
+    dry_run: bool,
+    stderr: W,
+}
+
+impl TrashEmpty<NoColor<Sink>> {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            target_directory: metadata.target_directory.clone(),
+            dry_run: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> TrashEmpty<W> {
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> TrashEmpty<W2> {
+        TrashEmpty {
+            target_directory: self.target_directory,
+            dry_run: self.dry_run,
+            stderr,
+        }
+    }
+
+    /// Deletes everything in the trash for good.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            target_directory,
+            dry_run,
+            mut stderr,
+        } = self;
+
+        let trash = crate::trash::Trash::read(&target_directory)?;
+        let dir = crate::trash::dir(&target_directory);
+        if !dir.exists() {
+            stderr.warn("the trash is already empty")?;
+            return Ok(());
+        }
+        for item in &trash.items {
+            stderr.status_with_color("Removing", item, termcolor::Color::Red)?;
+        }
+        crate::fs::remove_dir_all(&dir, dry_run)?;
+
+        if dry_run {
+            stderr.warn("not removing due to dry run")?;
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn move_to_workspace(
+    src_workspace_root: &Path,
+    src_target_directory: &Path,
+    dst_workspace_root: &Path,
+    src: &Path,
+    dst: &Path,
+    no_rename: bool,
+    name_prefix: Option<&str>,
+    offline: bool,
+    lock_mode: LockMode,
+    dry_run: bool,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let src_workspace_root = &*crate::manifest::normalize(src_workspace_root);
+    let dst_workspace_root = &*crate::manifest::normalize(dst_workspace_root);
+    let src = &*crate::manifest::normalize(src);
+    let dst = crate::manifest::normalize(dst);
+    let dst = if dst.exists() {
+        dst.join(src.file_name().expect("should be absolute"))
+    } else {
+        dst
+    };
+
+    ensure!(!dst.exists(), "`{}` exists", dst);
+    ensure!(
+        dst.starts_with(dst_workspace_root),
+        "`{}` is not inside the workspace at {}",
+        dst,
+        dst_workspace_root,
+    );
+    ensure!(
+        src_workspace_root != dst_workspace_root,
+        "`{}` is the source workspace",
+        dst_workspace_root,
+    );
+
+    let src_root_manifest_path = src_workspace_root.join("Cargo.toml");
+    let dst_root_manifest_path = dst_workspace_root.join("Cargo.toml");
+    let src_root_cargo_toml = crate::fs::read_toml_edit(&src_root_manifest_path)?;
+    let dst_root_cargo_toml = crate::fs::read_toml_edit(&dst_root_manifest_path)?;
+
+    let mut cargo_toml = crate::fs::read_toml_edit(src.join("Cargo.toml"))
+        .with_context(|| format!("`{}` does not seem to be a package", src))?;
+    let orig_name = cargo_toml["package"]["name"]
+        .as_str()
+        .with_context(|| format!("missing `package.name` in {}", src.join("Cargo.toml")))?
+        .to_owned();
+    let name = if no_rename {
+        orig_name.clone()
+    } else {
+        prefixed_name(name_prefix, dst.file_name().expect("should exist"))
+    };
+    if let Some(package) = cargo_toml["package"].as_table_mut() {
+        package.remove("workspace");
+        package["name"] = toml_edit::value(&name);
+    }
+
+    crate::manifest::resolve_inheritance(
+        &mut cargo_toml,
+        &src_root_cargo_toml,
+        src_workspace_root,
+        src,
+    )?;
+    crate::manifest::for_each_dependency_mut(&mut cargo_toml, |_, dep| {
+        crate::manifest::rebase_dependency(dep, src, &dst);
+        Ok(())
+    })?;
+    crate::manifest::reinherit(
+        &mut cargo_toml,
+        &dst_root_cargo_toml,
+        dst_workspace_root,
+        &dst,
+    )?;
+
+    stderr.status("Moving", format!("`{}` to `{}`", src, dst))?;
+
+    copy_package(src, &dst, &["Cargo.toml"], dry_run, &mut stderr)?;
+    crate::fs::write(dst.join("Cargo.toml"), cargo_toml.to_string(), dry_run)?;
+    crate::journal::created(&dst);
+
+    stderr.status_with_color(
+        "Removing",
+        format!("directory `{}`", src),
+        termcolor::Color::Red,
+    )?;
+    if !dry_run {
+        let trash = crate::trash::put(src_target_directory, src)?;
+        crate::journal::trashed(src, trash);
+    }
+
+    modify_members(
+        src_workspace_root,
+        &[],
+        &[],
+        &[src],
+        &[src],
+        dry_run,
+        &mut stderr,
+    )?;
+    modify_members(
+        dst_workspace_root,
+        &[&dst],
+        &[],
+        &[],
+        &[&dst],
+        dry_run,
+        &mut stderr,
+    )?;
+
+    let mut src_root_cargo_toml = crate::fs::read_toml_edit(&src_root_manifest_path)?;
+    let mut dst_root_cargo_toml = crate::fs::read_toml_edit(&dst_root_manifest_path)?;
+
+    let workspace_dep = src_root_cargo_toml
+        .get_mut("workspace")
+        .and_then(|w| w.get_mut("dependencies"))
+        .and_then(toml_edit::Item::as_table_like_mut)
+        .and_then(|deps| {
+            deps.iter_mut().find(|(_, dep)| {
+                dep.get("path")
+                    .and_then(toml_edit::Item::as_str)
+                    .is_some_and(|p| crate::manifest::normalize(&src_workspace_root.join(p)) == src)
+            })
+        });
+    if let Some((key, dep)) = workspace_dep {
+        let key = key.get().to_owned();
+        let path = crate::manifest::relative_path(src_workspace_root, &dst);
+        dep["path"] = toml_edit::value(crate::manifest::to_manifest_str(&path));
+        if orig_name != name {
+            if let Some(dep) = dep.as_table_like_mut() {
+                if key == name {
+                    dep.remove("package");
+                } else {
+                    dep.insert("package", toml_edit::value(&name));
+                }
+            }
+        }
+        stderr.status(
+            "Rebasing",
+            format!(
+                "{:?} in `workspace.dependencies` of {}",
+                key, src_root_manifest_path
+            ),
+        )?;
+
+        let dst_workspace_deps = dst_root_cargo_toml["workspace"]["dependencies"]
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+            .with_context(|| "`workspace.dependencies` must be a table")?;
+        if !dst_workspace_deps.contains_key(&name) {
+            let mut dep = dep.clone();
+            crate::manifest::rebase_dependency(&mut dep, src_workspace_root, dst_workspace_root);
+            if let Some(dep) = dep.as_table_like_mut() {
+                dep.remove("package");
+            }
+            dst_workspace_deps.insert(&name, dep);
+            stderr.status(
+                "Adding",
+                format!(
+                    "{:?} to `workspace.dependencies` of {}",
+                    name, dst_root_manifest_path
+                ),
+            )?;
+        }
+
+        crate::fs::write(
+            &src_root_manifest_path,
+            src_root_cargo_toml.to_string(),
+            dry_run,
+        )?;
+        crate::fs::write(
+            &dst_root_manifest_path,
+            dst_root_cargo_toml.to_string(),
+            dry_run,
+        )?;
+    }
+
+    rebase_dependents(
+        src_workspace_root,
+        src,
+        &dst,
+        (orig_name != name).then_some(&*name),
+        dry_run,
+        &mut stderr,
+    )?;
+
+    if dry_run {
+        stderr.warn("not moving due to dry run")?;
+    } else {
+        for workspace_root in [src_workspace_root, dst_workspace_root] {
+            update_lockfile(workspace_root, lock_mode, offline, &mut stderr)?;
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites the path dependencies on `from` in the manifests under `root` so that they point to
+/// `to`.
+fn rebase_dependents(
+    root: &Path,
+    from: &Path,
+    to: &Path,
+    new_name: Option<&str>,
+    dry_run: bool,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    for entry in Walk::new(root) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                stderr.warn(err)?;
+                continue;
+            }
+        };
+        let manifest_path = Path::from_path(entry.path()).expect("not a valid utf-8 path");
+        if !manifest_path.ends_with("Cargo.toml") || manifest_path.starts_with(from) {
+            continue;
+        }
+        let dir = manifest_path.parent().expect("should not empty");
+
+        let mut cargo_toml = crate::fs::read_toml_edit(manifest_path)?;
+        let orig = cargo_toml.to_string();
+        crate::manifest::for_each_dependency_mut(&mut cargo_toml, |_, dep| {
+            let points_to_from = dep
+                .get("path")
+                .and_then(toml_edit::Item::as_str)
+                .is_some_and(|p| crate::manifest::normalize(&dir.join(p)) == from);
+            if let (true, Some(dep)) = (points_to_from, dep.as_table_like_mut()) {
+                let path = crate::manifest::relative_path(dir, to);
+                dep.insert(
+                    "path",
+                    toml_edit::value(crate::manifest::to_manifest_str(&path)),
+                );
+                if let Some(new_name) = new_name {
+                    dep.insert("package", toml_edit::value(new_name));
+                }
+            }
+            Ok(())
+        })?;
+        let cargo_toml = cargo_toml.to_string();
+        if cargo_toml != orig {
+            stderr.status(
+                "Rebasing",
+                format!("path dependencies in {}", manifest_path),
+            )?;
+            crate::fs::write(manifest_path, cargo_toml, dry_run)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct Eject<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    src: anyhow::Result<PathBuf>,
+    dst: anyhow::Result<PathBuf>,
+    keep_paths: bool,
+    remove_source: bool,
+    dry_run: bool,
+    stderr: W,
+}
+
+impl Eject<NoColor<Sink>> {
+    pub fn new(workspace_root: &Path, src: &Path, dst: &Path) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            src: ensure_absolute(src),
+            dst: ensure_absolute(dst),
+            keep_paths: false,
+            remove_source: false,
+            dry_run: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    pub fn from_metadata(metadata: &Metadata, src: &str, dst: &Path) -> Self {
+        Self {
+            workspace_root: Ok(metadata.workspace_root.clone()),
+            src: metadata.query_for_member(src).map(|member| {
+                member
+                    .manifest_path
+                    .parent()
+                    .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                    .to_owned()
+            }),
+            dst: ensure_absolute(dst),
+            keep_paths: false,
+            remove_source: false,
+            dry_run: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Eject<W> {
+    pub fn keep_paths(self, keep_paths: bool) -> Self {
+        Self { keep_paths, ..self }
+    }
+
+    pub fn remove_source(self, remove_source: bool) -> Self {
+        Self {
+            remove_source,
+            ..self
+        }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Eject<W2> {
+        Eject {
+            stderr,
+            workspace_root: self.workspace_root,
+            src: self.src,
+            dst: self.dst,
+            keep_paths: self.keep_paths,
+            remove_source: self.remove_source,
+            dry_run: self.dry_run,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            mut stderr,
+            workspace_root,
+            src,
+            dst,
+            keep_paths,
+            remove_source,
+            dry_run,
+        } = self;
+
+        let (workspace_root, src, dst) = (workspace_root?, src?, dst?);
+
+        let dst = if dst.exists() {
+            dst.join(src.file_name().expect("should be absolute"))
+        } else {
+            dst
+        };
+
+        ensure!(!dst.exists(), "`{}` exists", dst);
+        ensure!(
+            !dst.starts_with(&workspace_root),
+            "`{}` is inside the workspace at {}",
+            dst,
+            workspace_root,
+        );
+
+        stderr.status("Ejecting", format!("`{}` to `{}`", src, dst))?;
+
+        let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+        let mut cargo_toml = crate::fs::read_toml_edit(src.join("Cargo.toml"))
+            .with_context(|| format!("`{}` does not seem to be a package", src))?;
+        if let Some(package) = cargo_toml["package"].as_table_mut() {
+            package.remove("workspace");
+        }
+
+        crate::manifest::resolve_inheritance(
+            &mut cargo_toml,
+            &root_cargo_toml,
+            &workspace_root,
+            &src,
+        )?;
+
+        if let Some(package) = cargo_toml["package"].as_table_like_mut() {
+            for key in ["readme", "license-file"] {
+                if let Some(path) = package.get_mut(key) {
+                    if let Some(s) = path.as_str() {
+                        if !crate::manifest::normalize(&src.join(s)).starts_with(&src) {
+                            *path = toml_edit::value(crate::manifest::rebase(&src, &dst, s));
+                        }
+                    }
+                }
+            }
+        }
+
+        crate::manifest::for_each_dependency_mut(&mut cargo_toml, |name, dep| {
+            let path = match dep
+                .as_table_like()
+                .and_then(|d| d.get("path"))
+                .and_then(toml_edit::Item::as_str)
+            {
+                Some(path) => path.to_owned(),
+                None => return Ok(()),
+            };
+            let target = crate::manifest::normalize(&src.join(&path));
+            let dep = dep.as_table_like_mut().expect("checked");
+
+            if keep_paths || !target.starts_with(&workspace_root) {
+                let rebased = crate::manifest::rebase(&src, &dst, &path);
+                dep.insert("path", toml_edit::value(rebased));
+                return Ok(());
+            }
+
+            let version = match dep.get("version").and_then(toml_edit::Item::as_str) {
+                Some(version) => version.to_owned(),
+                None => {
+                    let target_cargo_toml = crate::fs::read_toml_edit(target.join("Cargo.toml"))?;
+                    crate::manifest::package_version(&target_cargo_toml, &root_cargo_toml)
+                        .with_context(|| format!("could not find the version of `{}`", target))?
+                }
+            };
+            dep.remove("path");
+            dep.insert("version", toml_edit::value(&version));
+            stderr.status(
+                "Replacing",
+                format!("path dependency {:?} with version {:?}", name, version),
+            )?;
+            Ok(())
+        })?;
+        crate::manifest::for_each_dependency_mut(&mut cargo_toml, |_, dep| {
+            crate::manifest::simplify_dependency(dep);
+            Ok(())
+        })?;
+
+        copy_package(&src, &dst, &["Cargo.toml"], dry_run, &mut stderr)?;
+
+        crate::fs::write(dst.join("Cargo.toml"), cargo_toml.to_string(), dry_run)?;
+        crate::journal::created(&dst);
+
+        if remove_source {
+            Rm::new(&workspace_root, [src])
+                .dry_run(dry_run)
+                .stderr(&mut stderr)
+                .exec()?;
+        } else if dry_run {
+            stderr.warn("not ejecting due to dry run")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Split<'a, W> {
+    metadata: &'a Metadata,
+    members: anyhow::Result<Vec<&'a Package>>,
+    into: anyhow::Result<PathBuf>,
+    dry_run: bool,
+    offline: bool,
+    stderr: W,
+}
+
+impl<'a> Split<'a, NoColor<Sink>> {
+    pub fn from_metadata<Ss: IntoIterator<Item = S>, S: AsRef<str>>(
+        metadata: &'a Metadata,
+        specs: Ss,
+        into: &Path,
+    ) -> Self {
+        Self {
+            metadata,
+            members: specs
+                .into_iter()
+                .map(|spec| metadata.query_for_member(spec.as_ref()))
+                .collect(),
+            into: ensure_absolute(into),
+            dry_run: false,
+            offline: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<'a, W: WriteColor> Split<'a, W> {
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Split<'a, W2> {
+        Split {
+            stderr,
+            metadata: self.metadata,
+            members: self.members,
+            into: self.into,
+            dry_run: self.dry_run,
+            offline: self.offline,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            mut stderr,
+            metadata,
+            members,
+            into,
+            dry_run,
+            offline,
+        } = self;
+
+        let (members, into) = (members?, into?);
+        let workspace_root = &metadata.workspace_root;
+
+        ensure!(!members.is_empty(), "no packages specified");
+        ensure!(
+            !into.exists() || into.read_dir()?.next().is_none(),
+            "`{}` exists and is not empty",
+            into,
+        );
+
+        // `resolve` only has the dependencies enabled by the default features, so the manifests
+        // are checked to catch the optional ones too.
+        let split_ids = members.iter().map(|p| &p.id).collect::<HashSet<_>>();
+        let split_dirs = members
+            .iter()
+            .map(|p| {
+                p.manifest_path
+                    .parent()
+                    .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+            })
+            .collect::<HashSet<_>>();
+        for package in metadata.workspace_packages() {
+            if split_ids.contains(&package.id) {
+                continue;
+            }
+            if let Some(dep) = package
+                .dependencies
+                .iter()
+                .find(|d| d.path.as_deref().is_some_and(|p| split_dirs.contains(p)))
+            {
+                bail!(
+                    "`{}` depends on `{}`, which is going to be split",
+                    package.name,
+                    dep.name,
+                );
+            }
+        }
+
+        let moves = members
+            .iter()
+            .map(|member| {
+                let src = member
+                    .manifest_path
+                    .parent()
+                    .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                    .to_owned();
+                let dst = match src.strip_prefix(workspace_root) {
+                    Ok(rel) => into.join(rel),
+                    Err(_) => into.join(src.file_name().expect("should be absolute")),
+                };
+                (src, dst)
+            })
+            .collect::<Vec<_>>();
+        let map_path = |path: &Path| -> PathBuf {
+            moves
+                .iter()
+                .find_map(|(src, dst)| Some(dst.join(path.strip_prefix(src).ok()?)))
+                .map_or_else(|| path.to_owned(), |p| crate::manifest::normalize(&p))
+        };
+
+        let root_manifest_path = workspace_root.join("Cargo.toml");
+        let root_cargo_toml = crate::fs::read_toml_edit(&root_manifest_path)?;
+
+        let mut cargo_tomls = vec![];
+        let (mut inherited_fields, mut inherited_deps, mut inherits_lints) =
+            (BTreeSet::new(), BTreeSet::new(), false);
+        for (src, dst) in &moves {
+            let mut cargo_toml = crate::fs::read_toml_edit(src.join("Cargo.toml"))?;
+            if let Some(package) = cargo_toml["package"].as_table_like_mut() {
+                package.remove("workspace");
+                for (key, item) in package.iter() {
+                    if crate::manifest::is_inherited(item) {
+                        inherited_fields.insert(key.to_owned());
+                    }
+                }
+            }
+            inherits_lints |= cargo_toml
+                .get("lints")
+                .is_some_and(crate::manifest::is_inherited);
+            crate::manifest::for_each_dependency_mut(&mut cargo_toml, |name, dep| {
+                if crate::manifest::is_inherited(dep) {
+                    inherited_deps.insert(name.to_owned());
+                }
+                Ok(())
+            })?;
+            remap_path_dependencies(&mut cargo_toml, src, dst, map_path)?;
+            cargo_tomls.push(cargo_toml);
+        }
+
+        let mut new_root = toml_edit::DocumentMut::new();
+        new_root["workspace"] = toml_edit::table();
+        if let Some(resolver) = root_cargo_toml
+            .get("workspace")
+            .and_then(|w| w.get("resolver"))
+        {
+            new_root["workspace"]["resolver"] = resolver.clone();
+        }
+        new_root["workspace"]["members"] = toml_edit::value(
+            moves
+                .iter()
+                .map(|(_, dst)| {
+                    crate::manifest::to_manifest_str(&crate::manifest::relative_path(&into, dst))
+                })
+                .collect::<toml_edit::Array>(),
+        );
+        if let Some(package) = crate::manifest::workspace_table(&root_cargo_toml, "package") {
+            let mut subset = toml_edit::Table::new();
+            for (key, item) in package.iter() {
+                if inherited_fields.contains(key) {
+                    let mut item = item.clone();
+                    if ["readme", "license-file"].contains(&key) {
+                        if let Some(s) = item.as_str() {
+                            item =
+                                toml_edit::value(crate::manifest::rebase(workspace_root, &into, s));
+                        }
+                    }
+                    subset.insert(key, item);
+                }
+            }
+            if !subset.is_empty() {
+                new_root["workspace"]["package"] = toml_edit::Item::Table(subset);
+            }
+        }
+        if let Some(deps) = crate::manifest::workspace_table(&root_cargo_toml, "dependencies") {
+            let mut subset = toml_edit::Table::new();
+            for (name, dep) in deps.iter() {
+                if inherited_deps.contains(name) {
+                    let mut dep = dep.clone();
+                    if let Some(path) = dep.get("path").and_then(toml_edit::Item::as_str) {
+                        let target =
+                            map_path(&crate::manifest::normalize(&workspace_root.join(path)));
+                        let path = crate::manifest::relative_path(&into, &target);
+                        dep["path"] = toml_edit::value(crate::manifest::to_manifest_str(&path));
+                    }
+                    subset.insert(name, dep);
+                }
+            }
+            if !subset.is_empty() {
+                new_root["workspace"]["dependencies"] = toml_edit::Item::Table(subset);
+            }
+        }
+        if inherits_lints {
+            if let Some(lints) = crate::manifest::workspace_table(&root_cargo_toml, "lints") {
+                new_root["workspace"]["lints"] =
+                    crate::manifest::detach(&toml_edit::Item::Table(lints.clone()));
+            }
+        }
+
+        stderr.status("Creating", format!("workspace at {}", into))?;
+        crate::fs::create_dir_all(&into, dry_run)?;
+        crate::fs::write(into.join("Cargo.toml"), new_root.to_string(), dry_run)?;
+
+        for ((src, dst), cargo_toml) in moves.iter().zip(&cargo_tomls) {
+            stderr.status("Moving", format!("`{}` to `{}`", src, dst))?;
+            copy_package(src, dst, &["Cargo.toml"], dry_run, &mut stderr)?;
+            crate::fs::write(dst.join("Cargo.toml"), cargo_toml.to_string(), dry_run)?;
+            stderr.status_with_color(
+                "Removing",
+                format!("directory `{}`", src),
+                termcolor::Color::Red,
+            )?;
+            crate::fs::remove_dir_all(src, dry_run)?;
+        }
+
+        let lock_path = workspace_root.join("Cargo.lock");
+        if lock_path.exists() {
+            let mut lock = crate::fs::read_toml_edit(&lock_path)?;
+            let roots = members
+                .iter()
+                .map(|&p| crate::lockfile::LockedPackage::from(p))
+                .collect::<Vec<_>>();
+            let reachable = crate::lockfile::reachable(&lock, &roots)?;
+            crate::lockfile::retain_packages(&mut lock, |p| reachable.contains(p));
+            stderr.status("Writing", into.join("Cargo.lock"))?;
+            crate::fs::write(into.join("Cargo.lock"), lock.to_string(), dry_run)?;
+        }
+
+        let src_paths = moves.iter().map(|(src, _)| &**src).collect::<Vec<_>>();
+        modify_members(
+            workspace_root,
+            &[],
+            &[],
+            &src_paths,
+            &src_paths,
+            dry_run,
+            &mut stderr,
+        )?;
+
+        let mut root_cargo_toml = crate::fs::read_toml_edit(&root_manifest_path)?;
+        if let Some(deps) = root_cargo_toml
+            .get_mut("workspace")
+            .and_then(|w| w.get_mut("dependencies"))
+            .and_then(toml_edit::Item::as_table_like_mut)
+        {
+            let split = deps
+                .iter()
+                .filter(|(_, dep)| {
+                    dep.get("path")
+                        .and_then(toml_edit::Item::as_str)
+                        .is_some_and(|p| {
+                            let p = crate::manifest::normalize(&workspace_root.join(p));
+                            src_paths.contains(&&*p)
+                        })
+                })
+                .map(|(name, _)| name.to_owned())
+                .collect::<Vec<_>>();
+            for name in &split {
+                deps.remove(name);
+                stderr.status_with_color(
+                    "Removing",
+                    format!("{:?} from `workspace.dependencies`", name),
+                    termcolor::Color::Red,
+                )?;
+            }
+            if !split.is_empty() {
+                if deps.is_empty() {
+                    root_cargo_toml["workspace"]
+                        .as_table_like_mut()
+                        .expect("should be a table")
+                        .remove("dependencies");
+                }
+                crate::fs::write(&root_manifest_path, root_cargo_toml.to_string(), dry_run)?;
+            }
+        }
+
+        if dry_run {
+            stderr.warn("not splitting due to dry run")?;
+        } else {
+            for root in [&into, workspace_root] {
+                stderr.status("Updating", root.join("Cargo.lock"))?;
+                cargo_metadata(Some(&root.join("Cargo.toml")), false, false, offline, root)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Merge<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    other: anyhow::Result<PathBuf>,
+    prefix: PathBuf,
+    remove_source: bool,
+    dry_run: bool,
+    offline: bool,
+    stderr: W,
+}
+
+impl Merge<NoColor<Sink>> {
+    pub fn new(workspace_root: &Path, other: &Path) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            other: ensure_absolute(other),
+            prefix: PathBuf::new(),
+            remove_source: false,
+            dry_run: false,
+            offline: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Merge<W> {
+    pub fn prefix(self, prefix: impl AsRef<Path>) -> Self {
+        let prefix = prefix.as_ref().to_owned();
+        Self { prefix, ..self }
+    }
+
+    pub fn remove_source(self, remove_source: bool) -> Self {
+        Self {
+            remove_source,
+            ..self
+        }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Merge<W2> {
+        Merge {
+            stderr,
+            workspace_root: self.workspace_root,
+            other: self.other,
+            prefix: self.prefix,
+            remove_source: self.remove_source,
+            dry_run: self.dry_run,
+            offline: self.offline,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            mut stderr,
+            workspace_root,
+            other,
+            prefix,
+            remove_source,
+            dry_run,
+            offline,
+        } = self;
+
+        let (workspace_root, other) = (workspace_root?, other?);
+
+        ensure!(
+            workspace_root != other,
+            "cannot merge a workspace into itself",
+        );
+
+        let other_manifest_path = other.join("Cargo.toml");
+        let other_cargo_toml = crate::fs::read_toml_edit(&other_manifest_path)?;
+        ensure!(
+            !other_cargo_toml.contains_key("package"),
+            "`{}` is not a virtual manifest. use `include --absorb` instead",
+            other_manifest_path,
+        );
+        let other_workspace = other_cargo_toml
+            .get("workspace")
+            .and_then(toml_edit::Item::as_table)
+            .with_context(|| format!("`{}` is not a workspace root", other_manifest_path))?;
+
+        let metadata = cargo_metadata(
+            Some(&workspace_root.join("Cargo.toml")),
+            false,
+            false,
+            offline,
+            &workspace_root,
+        )?;
+        let other_metadata =
+            cargo_metadata(Some(&other_manifest_path), false, false, offline, &other)?;
+
+        let member_names = metadata
+            .workspace_members
+            .iter()
+            .map(|id| &*metadata[id].name)
+            .collect::<HashSet<_>>();
+        let mut moves = vec![];
+        for id in &other_metadata.workspace_members {
+            let package = &other_metadata[id];
+            ensure!(
+                !member_names.contains(&*package.name),
+                "both of the workspaces have a member named `{}`",
+                package.name,
+            );
+            let src = package
+                .manifest_path
+                .parent()
+                .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                .to_owned();
+            let rel = src
+                .strip_prefix(&other)
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|_| src.file_name().expect("should be absolute").into());
+            let dst = crate::manifest::normalize(&workspace_root.join(&prefix).join(rel));
+            ensure!(!dst.exists(), "`{}` exists", dst);
+            moves.push((src, dst));
+        }
+        let map_path = |path: &Path| -> PathBuf {
+            moves
+                .iter()
+                .find_map(|(src, dst)| Some(dst.join(path.strip_prefix(src).ok()?)))
+                .map_or_else(|| path.to_owned(), |p| crate::manifest::normalize(&p))
+        };
+        let map_root_path = |path: &str| -> String {
+            let target = map_path(&crate::manifest::normalize(&other.join(path)));
+            crate::manifest::to_manifest_str(&crate::manifest::relative_path(
+                &workspace_root,
+                &target,
+            ))
+        };
+
+        // The root manifest is written after everything is copied, so that a failure midway
+        // leaves it untouched.
+        let dsts = moves.iter().map(|(_, dst)| &**dst).collect::<Vec<_>>();
+        let mut root_manifest = RootManifest::read(&workspace_root)?;
+        root_manifest.modify_members(&dsts, &[], &[], &dsts, dry_run, &mut stderr)?;
+
+        let root_manifest_path = workspace_root.join("Cargo.toml");
+        let root_cargo_toml = &mut root_manifest.cargo_toml;
+
+        let conflicts = merge_workspace_tables(
+            root_cargo_toml,
+            &root_manifest_path,
+            other_workspace,
+            map_root_path,
+            &mut stderr,
+        )?;
+
+        if let Some(patch) = other_cargo_toml
+            .get("patch")
+            .and_then(toml_edit::Item::as_table_like)
+        {
+            for (source, patches) in patch.iter() {
+                let patches = patches
+                    .as_table_like()
+                    .with_context(|| format!("`patch.{}` must be a table", source))?;
+                let patches = patches
+                    .iter()
+                    .map(|(name, dep)| {
+                        let mut dep = dep.clone();
+                        if let Some(path) = dep.get("path").and_then(toml_edit::Item::as_str) {
+                            dep["path"] = toml_edit::value(map_root_path(path));
+                        }
+                        (name, dep)
+                    })
+                    .fold(toml_edit::Table::new(), |mut acc, (name, dep)| {
+                        acc.insert(name, dep);
+                        acc
+                    });
+                let dst = root_cargo_toml["patch"][source]
+                    .or_insert(toml_edit::table())
+                    .as_table_mut()
+                    .with_context(|| format!("`patch.{}` must be a table", source))?;
+                let merged = crate::manifest::merge_table(dst, &patches);
+                for key in &merged.added {
+                    stderr.status("Merging", format!("{:?} into `patch.{}`", key, source))?;
+                }
+                for key in &merged.conflicts {
+                    stderr.warn(format!(
+                        "`patch.{}.{}` conflicts. keeping the one in {}",
+                        source, key, root_manifest_path,
+                    ))?;
+                }
+            }
+        }
+        for key in ["profile", "replace"] {
+            if other_cargo_toml.contains_key(key) {
+                stderr.warn(format!("dropping `{}` in {}", key, other_manifest_path))?;
+            }
+        }
+
+        for (src, dst) in &moves {
+            let manifest_path = src.join("Cargo.toml");
+            let mut cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+            if let Some(package) = cargo_toml["package"].as_table_mut() {
+                package.remove("workspace");
+            }
+
+            let mut inherits_conflicting = conflicts.lints
+                && cargo_toml
+                    .get("lints")
+                    .is_some_and(crate::manifest::is_inherited);
+            if let Some(package) = cargo_toml["package"].as_table_like() {
+                inherits_conflicting |= package.iter().any(|(key, item)| {
+                    crate::manifest::is_inherited(item)
+                        && conflicts.package.iter().any(|k| k == key)
+                });
+            }
+            crate::manifest::for_each_dependency_mut(&mut cargo_toml, |name, dep| {
+                inherits_conflicting |= crate::manifest::is_inherited(dep)
+                    && conflicts.dependencies.iter().any(|k| k == name);
+                Ok(())
+            })?;
+
+            if inherits_conflicting {
+                stderr.status(
+                    "Resolving",
+                    format!("conflicting inherited fields in {}", manifest_path),
+                )?;
+                crate::manifest::resolve_inheritance(
+                    &mut cargo_toml,
+                    &other_cargo_toml,
+                    &other,
+                    src,
+                )?;
+            }
+            remap_path_dependencies(&mut cargo_toml, src, dst, map_path)?;
+            if inherits_conflicting {
+                crate::manifest::reinherit(&mut cargo_toml, root_cargo_toml, &workspace_root, dst)?;
+            }
+
+            if remove_source {
+                stderr.status("Moving", format!("`{}` to `{}`", src, dst))?;
+            } else {
+                stderr.status("Copying", format!("`{}` to `{}`", src, dst))?;
+            }
+            copy_package(src, dst, &["Cargo.toml"], dry_run, &mut stderr)?;
+            crate::fs::write(dst.join("Cargo.toml"), cargo_toml.to_string(), dry_run)?;
+            if remove_source {
+                stderr.status_with_color(
+                    "Removing",
+                    format!("directory `{}`", src),
+                    termcolor::Color::Red,
+                )?;
+                crate::fs::remove_dir_all(src, dry_run)?;
+            }
+        }
+
+        root_manifest.write(false, None, dry_run)?;
+
+        let lock_path = workspace_root.join("Cargo.lock");
+        let other_lock_path = other.join("Cargo.lock");
+        if other_lock_path.exists() {
+            stderr.status("Merging", format!("{} into {}", other_lock_path, lock_path))?;
+            let other_lock = crate::fs::read_toml_edit(&other_lock_path)?;
+            let lock = if lock_path.exists() {
+                let mut lock = crate::fs::read_toml_edit(&lock_path)?;
+                crate::lockfile::merge(&mut lock, &other_lock);
+                lock
+            } else {
+                other_lock
+            };
+            crate::fs::write(&lock_path, lock.to_string(), dry_run)?;
+        }
+
+        if remove_source {
+            for path in [&other_manifest_path, &other_lock_path] {
+                if path.exists() {
+                    stderr.status_with_color("Removing", path, termcolor::Color::Red)?;
+                    crate::fs::remove_file(path, dry_run)?;
+                }
+            }
+        }
+
+        if dry_run {
+            stderr.warn("not merging due to dry run")?;
+        } else {
+            stderr.status("Updating", &lock_path)?;
+            cargo_metadata(
+                Some(&root_manifest_path),
+                false,
+                false,
+                offline,
+                &workspace_root,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Fork<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    target_directory: PathBuf,
+    package: anyhow::Result<Package>,
+    dir: PathBuf,
+    exclude: bool,
+    dry_run: bool,
+    offline: bool,
+    stderr: W,
+}
+
+impl Fork<NoColor<Sink>> {
+    pub fn from_metadata(metadata: &Metadata, spec: &str) -> Self {
+        Self {
+            workspace_root: Ok(metadata.workspace_root.clone()),
+            target_directory: metadata.target_directory.clone(),
+            package: metadata.query_for_registry_package(spec).cloned(),
+            dir: "forks".into(),
+            exclude: false,
+            dry_run: false,
+            offline: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Fork<W> {
+    pub fn dir(self, dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_owned(),
+            ..self
+        }
+    }
+
+    pub fn exclude(self, exclude: bool) -> Self {
+        Self { exclude, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Fork<W2> {
+        Fork {
+            stderr,
+            workspace_root: self.workspace_root,
+            target_directory: self.target_directory,
+            package: self.package,
+            dir: self.dir,
+            exclude: self.exclude,
+            dry_run: self.dry_run,
+            offline: self.offline,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            mut stderr,
+            workspace_root,
+            target_directory,
+            package,
+            dir,
+            exclude,
+            dry_run,
+            offline,
+        } = self;
+
+        let (workspace_root, package) = (workspace_root?, package?);
+
+        let src = package
+            .manifest_path
+            .parent()
+            .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+        ensure!(
+            src.exists(),
+            "`{}` is not found in the local registry cache",
+            src,
+        );
+
+        let dst = crate::manifest::normalize(&workspace_root.join(&dir).join(&package.name));
+        ensure!(!dst.exists(), "`{}` exists", dst);
+        ensure!(
+            dst.starts_with(&workspace_root) && dst != workspace_root,
+            "`{}` is not inside the workspace at {}",
+            dst,
+            workspace_root,
+        );
+
+        let root_manifest_path = workspace_root.join("Cargo.toml");
+        let patch_key = patch_key(package.source.as_ref().expect("should be a registry"));
+        let root_cargo_toml = crate::fs::read_toml_edit(&root_manifest_path)?;
+        if root_cargo_toml
+            .get("patch")
+            .and_then(|p| p.get(&patch_key))
+            .and_then(|p| p.get(&package.name))
+            .is_some()
+        {
+            bail!(
+                "`{}` is already in `patch.{}` of {}",
+                package.name,
+                patch_key,
+                root_manifest_path,
+            );
+        }
+
+        let cargo_toml = original_manifest(src).or_else(|err| {
+            stderr.warn(format!(
+                "could not restore `Cargo.toml.orig` of `{} v{}` ({}). using the normalized one",
+                package.name, package.version, err,
+            ))?;
+            crate::fs::read_toml_edit(&package.manifest_path)
+        })?;
+
+        stderr.status(
+            "Forking",
+            format!("`{} v{}` to `{}`", package.name, package.version, dst),
+        )?;
+
+        copy_package(
+            src,
+            &dst,
+            &[
+                "Cargo.toml",
+                "Cargo.toml.orig",
+                "Cargo.lock",
+                ".cargo-ok",
+                ".cargo_vcs_info.json",
+            ],
+            dry_run,
+            &mut stderr,
+        )?;
+        crate::fs::write(dst.join("Cargo.toml"), cargo_toml.to_string(), dry_run)?;
+
+        let mut forks = crate::forks::Forks::read(&target_directory)?;
+        forks.paths.push(dst.clone());
+        forks.write(&target_directory, dry_run)?;
+
+        if exclude {
+            modify_members(
+                &workspace_root,
+                &[],
+                &[&dst],
+                &[],
+                &[],
+                dry_run,
+                &mut stderr,
+            )?;
+        } else {
+            modify_members(
+                &workspace_root,
+                &[&dst],
+                &[],
+                &[],
+                &[&dst],
+                dry_run,
+                &mut stderr,
+            )?;
+        }
+
+        let mut root_cargo_toml = crate::fs::read_toml_edit(&root_manifest_path)?;
+        let patch = root_cargo_toml["patch"]
+            .or_insert({
+                let mut patch = toml_edit::Table::new();
+                patch.set_implicit(true);
+                toml_edit::Item::Table(patch)
+            })
+            .as_table_mut()
+            .context("`patch` must be a table")?;
+        let patch = patch[&patch_key]
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+            .with_context(|| format!("`patch.{}` must be a table", patch_key))?;
+        let mut dep = toml_edit::InlineTable::new();
+        dep.insert(
+            "path",
+            crate::manifest::to_manifest_str(&crate::manifest::relative_path(
+                &workspace_root,
+                &dst,
+            ))
+            .into(),
+        );
+        patch[&package.name] = toml_edit::value(dep);
+        stderr.status(
+            "Adding",
+            format!("{:?} to `patch.{}`", package.name, patch_key),
+        )?;
+        crate::fs::write(&root_manifest_path, root_cargo_toml.to_string(), dry_run)?;
+
+        if dry_run {
+            stderr.warn("not forking due to dry run")?;
+        } else {
+            stderr.status("Updating", workspace_root.join("Cargo.lock"))?;
+            cargo_metadata(
+                Some(&root_manifest_path),
+                false,
+                false,
+                offline,
+                &workspace_root,
+            )?;
+        }
+        return Ok(());
+
+        /// Reads `Cargo.toml.orig`, which may inherit from its own `workspace` table.
+        fn original_manifest(src: &Path) -> anyhow::Result<toml_edit::DocumentMut> {
+            let mut cargo_toml = crate::fs::read_toml_edit(src.join("Cargo.toml.orig"))?;
+            let own_root = cargo_toml.clone();
+            crate::manifest::resolve_inheritance(&mut cargo_toml, &own_root, src, src)?;
+            cargo_toml.remove("workspace");
+            if let Some(package) = cargo_toml["package"].as_table_like_mut() {
+                package.remove("workspace");
+                for key in ["readme", "license-file"] {
+                    if let Some(path) = package.get(key).and_then(toml_edit::Item::as_str) {
+                        if !src.join(path).exists() {
+                            package.remove(key);
+                        }
+                    }
+                }
+            }
+            crate::manifest::for_each_dependency_mut(&mut cargo_toml, |name, dep| {
+                if let Some(path) = dep.get("path").and_then(toml_edit::Item::as_str) {
+                    if !src.join(path).join("Cargo.toml").exists() {
+                        let dep = dep.as_table_like_mut().expect("should be a table");
+                        ensure!(
+                            dep.contains_key("version"),
+                            "path dependency `{}` is not in the package",
+                            name,
+                        );
+                        dep.remove("path");
+                    }
+                }
+                Ok(())
+            })?;
+            Ok(cargo_toml)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Unfork<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    target_directory: anyhow::Result<PathBuf>,
+    name: String,
+    remove: bool,
+    dry_run: bool,
+    offline: bool,
+    journal: Option<PathBuf>,
+    stderr: W,
+}
+
+impl Unfork<NoColor<Sink>> {
+    pub fn new(workspace_root: &Path, name: &str) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            target_directory: ensure_absolute(workspace_root.join("target")),
+            name: name.to_owned(),
+            remove: false,
+            dry_run: false,
+            offline: false,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    pub fn from_metadata(metadata: &Metadata, name: &str) -> Self {
+        Self {
+            workspace_root: Ok(metadata.workspace_root.clone()),
+            target_directory: Ok(metadata.target_directory.clone()),
+            name: name.to_owned(),
+            remove: false,
+            dry_run: false,
+            offline: false,
+            journal: None,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Unfork<W> {
+    /// Moves the patching directory into the trash even if `fork` did not create it.
+    pub fn remove(self, remove: bool) -> Self {
+        Self { remove, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    /// Records the changes in the undo journal in `<target_directory>/cargo-member`.
+    pub fn journal(self, target_directory: Option<&Path>) -> Self {
+        let journal = target_directory.map(ToOwned::to_owned);
+        Self { journal, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Unfork<W2> {
+        Unfork {
+            journal: self.journal,
+            stderr,
+            workspace_root: self.workspace_root,
+            target_directory: self.target_directory,
+            name: self.name,
+            remove: self.remove,
+            dry_run: self.dry_run,
+            offline: self.offline,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            journal,
+            mut stderr,
+            workspace_root,
+            target_directory,
+            name,
+            remove,
+            dry_run,
+            offline,
+        } = self;
+
+        let (workspace_root, target_directory) = (workspace_root?, target_directory?);
+
+        let recording = crate::journal::start("unfork", journal.as_deref().filter(|_| !dry_run));
+        let root_manifest_path = workspace_root.join("Cargo.toml");
+        crate::journal::watch(&root_manifest_path);
+        let mut root_cargo_toml = crate::fs::read_toml_edit(&root_manifest_path)?;
+
+        let (patch_key, entry) = remove_patch(&mut root_cargo_toml, &name, None)
+            .with_context(|| format!("`{}` is not patched in {}", name, root_manifest_path))?;
+        let path = entry
+            .get("path")
+            .and_then(toml_edit::Item::as_str)
+            .map(|path| workspace_root.join(path))
+            .with_context(|| {
+                format!(
+                    "`{}` is not patched with a path in {}",
+                    name, root_manifest_path,
+                )
+            })?;
+        stderr.status_with_color(
+            "Removing",
+            format!(
+                "{:?} from `patch.{}`",
+                name,
+                toml_edit::Key::new(&patch_key).display_repr()
+            ),
+            termcolor::Color::Red,
+        )?;
+        crate::fs::write(&root_manifest_path, root_cargo_toml.to_string(), dry_run)?;
+
+        let path = crate::manifest::normalize(&path);
+        let mut forks = crate::forks::Forks::read(&target_directory)?;
+        let forked = forks.paths.contains(&path);
+        if !(path.starts_with(&workspace_root) && path != workspace_root) {
+            stderr.warn(format!(
+                "keeping `{}` since it is outside of the workspace",
+                path,
+            ))?;
+        } else if !(forked || remove) {
+            stderr.warn(format!(
+                "keeping `{}` since `fork` did not create it. enable `--remove` to remove",
+                path,
+            ))?;
+        } else {
+            stderr.status_with_color(
+                "Removing",
+                format!("directory `{}`", path),
+                termcolor::Color::Red,
+            )?;
+            if !dry_run {
+                let trash = crate::trash::put(&target_directory, &path)?;
+                crate::journal::trashed(&path, trash);
+            }
+            modify_members(
+                &workspace_root,
+                &[],
+                &[],
+                &[&path],
+                &[&path],
+                dry_run,
+                &mut stderr,
+            )?;
+            if forked {
+                forks.paths.retain(|p| *p != path);
+                forks.write(&target_directory, dry_run)?;
+            }
+        }
+
+        if dry_run {
+            stderr.warn("not unforking due to dry run")?;
+        } else {
+            stderr.status("Updating", workspace_root.join("Cargo.lock"))?;
+            cargo_metadata(
+                Some(&root_manifest_path),
+                false,
+                false,
+                offline,
+                &workspace_root,
+            )?;
+        }
+        recording.finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct Patch<'a, W> {
+    metadata: &'a Metadata,
+    name: String,
+    path: anyhow::Result<PathBuf>,
+    registry: Option<String>,
+    git: Option<String>,
+    dry_run: bool,
+    offline: bool,
+    stderr: W,
+}
+
+impl<'a> Patch<'a, NoColor<Sink>> {
+    pub fn from_metadata(metadata: &'a Metadata, name: &str, path: &Path) -> Self {
+        Self {
+            metadata,
+            name: name.to_owned(),
+            path: ensure_absolute(path),
+            registry: None,
+            git: None,
+            dry_run: false,
+            offline: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<'a, W: WriteColor> Patch<'a, W> {
+    pub fn registry<S: AsRef<str>>(self, registry: Option<S>) -> Self {
+        Self {
+            registry: registry.map(|s| s.as_ref().to_owned()),
+            ..self
+        }
+    }
+
+    pub fn git<S: AsRef<str>>(self, git: Option<S>) -> Self {
+        Self {
+            git: git.map(|s| s.as_ref().to_owned()),
+            ..self
+        }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Patch<'a, W2> {
+        Patch {
+            stderr,
+            metadata: self.metadata,
+            name: self.name,
+            path: self.path,
+            registry: self.registry,
+            git: self.git,
+            dry_run: self.dry_run,
+            offline: self.offline,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            mut stderr,
+            metadata,
+            name,
+            path,
+            registry,
+            git,
+            dry_run,
+            offline,
+        } = self;
+
+        let path = crate::manifest::normalize(&path?);
+        let workspace_root = &metadata.workspace_root;
+
+        ensure!(
+            registry.is_none() || git.is_none(),
+            "`registry` and `git` are exclusive",
+        );
+
+        let package = read_package(&path.join("Cargo.toml"))?;
+        ensure!(
+            package.name == name,
+            "`{}` is not `{}` but `{}`",
+            path,
+            name,
+            package.name,
+        );
+
+        let patch_key = match (&registry, &git) {
+            (_, Some(git)) => git.clone(),
+            (Some(registry), None) => registry.clone(),
+            (None, None) => "crates-io".to_owned(),
+        };
+        let registry_source = match (&registry, &git) {
+            (Some(registry), None) if registry != "crates-io" => {
+                Some(self::registry_source(workspace_root, registry)?)
+            }
+            _ => None,
+        };
+        let is_patched_source = |source: &str| match (&git, &registry_source) {
+            (Some(git), _) => source.trim_start_matches("git+").starts_with(git.as_str()),
+            (None, Some(registry_source)) => source == registry_source,
+            (None, None) => {
+                source == "registry+https://github.com/rust-lang/crates.io-index"
+                    || source == "sparse+https://index.crates.io/"
+            }
+        };
+
+        let reqs = metadata
+            .packages
+            .iter()
+            .flat_map(|p| &p.dependencies)
+            .filter(|d| d.name == name && d.source.as_deref().is_some_and(is_patched_source))
+            .map(|d| &d.req)
+            .unique()
+            .collect::<Vec<_>>();
+        if reqs.is_empty() {
+            stderr.warn(format!(
+                "no package in the workspace depends on `{}` from `{}`",
+                name, patch_key,
+            ))?;
+        } else if !reqs.iter().any(|r| r.matches(&package.version)) {
+            bail!(
+                "`{} v{}` at `{}` does not match any of the requirements: {}",
+                name,
+                package.version,
+                path,
+                reqs.iter().format(", "),
+            );
+        }
+
+        let root_manifest_path = workspace_root.join("Cargo.toml");
+        let mut root_cargo_toml = crate::fs::read_toml_edit(&root_manifest_path)?;
+        let patch = root_cargo_toml["patch"]
+            .or_insert({
+                let mut patch = toml_edit::Table::new();
+                patch.set_implicit(true);
+                toml_edit::Item::Table(patch)
+            })
+            .as_table_mut()
+            .context("`patch` must be a table")?;
+        let patch = patch[&patch_key]
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+            .with_context(|| format!("`patch.{}` must be a table", patch_key))?;
+        ensure!(
+            !patch.contains_key(&name),
+            "`{}` is already in `patch.{}` of {}",
+            name,
+            patch_key,
+            root_manifest_path,
+        );
+        let mut dep = toml_edit::InlineTable::new();
+        dep.insert(
+            "path",
+            crate::manifest::to_manifest_str(&crate::manifest::relative_path(
+                workspace_root,
+                &path,
+            ))
+            .into(),
+        );
+        patch[&name] = toml_edit::value(dep);
+        stderr.status(
+            "Adding",
+            format!(
+                "{:?} to `patch.{}`",
+                name,
+                toml_edit::Key::new(&patch_key).display_repr()
+            ),
+        )?;
+        crate::fs::write(&root_manifest_path, root_cargo_toml.to_string(), dry_run)?;
+
+        if dry_run {
+            stderr.warn("not patching due to dry run")?;
+        } else {
+            let lock_path = workspace_root.join("Cargo.lock");
+            if lock_path.exists() {
+                let mut lock = crate::fs::read_toml_edit(&lock_path)?;
+                crate::lockfile::forget(&mut lock, &name, is_patched_source);
+                crate::fs::write(&lock_path, lock.to_string(), dry_run)?;
+            }
+            stderr.status("Updating", &lock_path)?;
+            cargo_metadata(
+                Some(&root_manifest_path),
+                false,
+                false,
+                offline,
+                workspace_root,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Unpatch<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    name: String,
+    source: Option<String>,
+    dry_run: bool,
+    offline: bool,
+    stderr: W,
+}
+
+impl Unpatch<NoColor<Sink>> {
+    pub fn new(workspace_root: &Path, name: &str) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            name: name.to_owned(),
+            source: None,
+            dry_run: false,
+            offline: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Unpatch<W> {
+    /// Registry name or Git URL. If `None`, looks through every source.
+    pub fn source<S: AsRef<str>>(self, source: Option<S>) -> Self {
+        Self {
+            source: source.map(|s| s.as_ref().to_owned()),
+            ..self
+        }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Unpatch<W2> {
+        Unpatch {
+            stderr,
+            workspace_root: self.workspace_root,
+            name: self.name,
+            source: self.source,
+            dry_run: self.dry_run,
+            offline: self.offline,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            mut stderr,
+            workspace_root,
+            name,
+            source,
+            dry_run,
+            offline,
+        } = self;
+
+        let workspace_root = workspace_root?;
+
+        let root_manifest_path = workspace_root.join("Cargo.toml");
+        let mut root_cargo_toml = crate::fs::read_toml_edit(&root_manifest_path)?;
+
+        let (patch_key, _) = remove_patch(&mut root_cargo_toml, &name, source.as_deref())
+            .with_context(|| format!("`{}` is not patched in {}", name, root_manifest_path))?;
+        stderr.status_with_color(
+            "Removing",
+            format!(
+                "{:?} from `patch.{}`",
+                name,
+                toml_edit::Key::new(&patch_key).display_repr()
+            ),
+            termcolor::Color::Red,
+        )?;
+        crate::fs::write(&root_manifest_path, root_cargo_toml.to_string(), dry_run)?;
+
+        if dry_run {
+            stderr.warn("not unpatching due to dry run")?;
+        } else {
+            stderr.status("Updating", workspace_root.join("Cargo.lock"))?;
+            cargo_metadata(
+                Some(&root_manifest_path),
+                false,
+                false,
+                offline,
+                &workspace_root,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PatchList<'a, W> {
+    metadata: &'a Metadata,
+    stdout: W,
+}
+
+impl<'a> PatchList<'a, Sink> {
+    pub fn from_metadata(metadata: &'a Metadata) -> Self {
+        Self {
+            metadata,
+            stdout: io::sink(),
+        }
+    }
+}
+
+impl<'a, W: io::Write> PatchList<'a, W> {
+    pub fn stdout<W2: io::Write>(self, stdout: W2) -> PatchList<'a, W2> {
+        PatchList {
+            stdout,
+            metadata: self.metadata,
+        }
+    }
+
+    /// Prints `patch.<source>.<name> = <location> (used|unused)` for each entry.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            metadata,
+            mut stdout,
+        } = self;
+
+        let workspace_root = &metadata.workspace_root;
+        let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+
+        let patch = match root_cargo_toml
+            .get("patch")
+            .and_then(toml_edit::Item::as_table_like)
+        {
+            Some(patch) => patch,
+            None => return Ok(()),
+        };
+
+        for (source, entries) in patch.iter() {
+            let entries = match entries.as_table_like() {
+                Some(entries) => entries,
+                None => continue,
+            };
+            for (key, entry) in entries.iter() {
+                let name = entry
+                    .get("package")
+                    .and_then(toml_edit::Item::as_str)
+                    .unwrap_or(key);
+                let (location, used) = if let Some(path) =
+                    entry.get("path").and_then(toml_edit::Item::as_str)
+                {
+                    let manifest_path =
+                        crate::manifest::normalize(&workspace_root.join(path).join("Cargo.toml"));
+                    let used = metadata
+                        .packages
+                        .iter()
+                        .any(|p| p.name == name && p.manifest_path == manifest_path);
+                    (path.to_owned(), used)
+                } else if let Some(git) = entry.get("git").and_then(toml_edit::Item::as_str) {
+                    let used = metadata.packages.iter().any(|p| {
+                        p.name == name
+                            && p.source
+                                .as_ref()
+                                .is_some_and(|s| s.repr.trim_start_matches("git+").starts_with(git))
+                    });
+                    (git.to_owned(), used)
+                } else {
+                    let version = entry.get("version").and_then(toml_edit::Item::as_str);
+                    let used = metadata.packages.iter().any(|p| {
+                        p.name == name && version.is_some_and(|v| p.version.to_string() == v)
+                    });
+                    (version.unwrap_or("*").to_owned(), used)
+                };
+                writeln!(
+                    stdout,
+                    "patch.{}.{} = {} ({})",
+                    toml_edit::Key::new(source).display_repr(),
+                    toml_edit::Key::new(key).display_repr(),
+                    location,
+                    if used { "used" } else { "unused" },
+                )?;
+            }
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Link<'a, W> {
+    metadata: &'a Metadata,
+    from: anyhow::Result<&'a Package>,
+    to: anyhow::Result<&'a Package>,
+    kind: DependencyKind,
+    optional: bool,
+    features: Vec<String>,
+    version_req: anyhow::Result<Option<semver::VersionReq>>,
+    dry_run: bool,
+    offline: bool,
+    stderr: W,
+}
+
+impl<'a> Link<'a, NoColor<Sink>> {
+    pub fn from_metadata(metadata: &'a Metadata, from: &str, to: &str) -> Self {
+        Self {
+            metadata,
+            from: metadata.query_for_member(from),
+            to: metadata.query_for_member(to),
+            kind: DependencyKind::Normal,
+            optional: false,
+            features: vec![],
+            version_req: Ok(None),
+            dry_run: false,
+            offline: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<'a, W: WriteColor> Link<'a, W> {
+    pub fn kind(self, kind: DependencyKind) -> Self {
+        Self { kind, ..self }
+    }
+
+    pub fn optional(self, optional: bool) -> Self {
+        Self { optional, ..self }
+    }
+
+    pub fn features<Ss: IntoIterator<Item = S>, S: AsRef<str>>(self, features: Ss) -> Self {
+        Self {
+            features: features
+                .into_iter()
+                .map(|s| s.as_ref().to_owned())
+                .collect(),
+            ..self
+        }
+    }
+
+    /// The version requirement to write instead of the current version of the dependency.
+    pub fn version_req(self, version_req: Option<&str>) -> Self {
+        let version_req = version_req
+            .map(|req| {
+                semver::VersionReq::parse(req)
+                    .with_context(|| format!("invalid version requirement `{}`", req))
+            })
+            .transpose();
+        Self {
+            version_req,
+            ..self
+        }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Link<'a, W2> {
+        Link {
+            stderr,
+            metadata: self.metadata,
+            from: self.from,
+            to: self.to,
+            kind: self.kind,
+            optional: self.optional,
+            features: self.features,
+            version_req: self.version_req,
+            dry_run: self.dry_run,
+            offline: self.offline,
+        }
+    }
+
+    /// Adds a path dependency. Unless [`version_req`](Self::version_req) is given, the
+    /// requirement is the current version of the dependency, such as `"0.3.1"`. Cargo reads it as
+    /// the caret requirement `^0.3.1`, which is also what `cargo add` writes.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            mut stderr,
+            metadata,
+            from,
+            to,
+            kind,
+            optional,
+            features,
+            version_req,
+            dry_run,
+            offline,
+        } = self;
+
+        let (from, to, version_req) = (from?, to?, version_req?);
+        if let Some(version_req) = &version_req {
+            ensure!(
+                version_req.matches(&to.version),
+                "`{}` does not match `{} v{}`",
+                version_req,
+                to.name,
+                to.version,
+            );
+        }
+        let workspace_root = &metadata.workspace_root;
+        let field = dependency_table_name(kind)?;
+
+        ensure!(from.id != to.id, "cannot link `{}` to itself", from.name);
+        ensure!(
+            !from
+                .dependencies
+                .iter()
+                .any(|d| d.name == to.name && d.kind == kind && d.path.is_some()),
+            "`{}` already has `{}` in `{}`",
+            from.name,
+            to.name,
+            field,
+        );
+
+        if kind != DependencyKind::Development {
+            if let Some(cycle) = metadata.find_non_dev_path(&to.id, &from.id) {
+                bail!(
+                    "linking `{}` to `{}` creates a cycle: {} -> {}",
+                    from.name,
+                    to.name,
+                    from.name,
+                    cycle.iter().map(|id| &metadata[id].name).format(" -> "),
+                );
+            }
+        }
+
+        let from_dir = from
+            .manifest_path
+            .parent()
+            .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+        let to_dir = to
+            .manifest_path
+            .parent()
+            .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+
+        let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+        let workspace_key = crate::manifest::workspace_table(&root_cargo_toml, "dependencies")
+            .and_then(|deps| {
+                deps.iter().find_map(|(key, dep)| {
+                    let path = dep.get("path")?.as_str()?;
+                    (crate::manifest::normalize(&workspace_root.join(path)) == to_dir)
+                        .then(|| key.to_owned())
+                })
+            });
+
+        let mut dep = toml_edit::InlineTable::new();
+        let key = if let Some(workspace_key) = workspace_key {
+            dep.insert("workspace", true.into());
+            workspace_key
+        } else {
+            dep.insert(
+                "path",
+                crate::manifest::to_manifest_str(&crate::manifest::relative_path(from_dir, to_dir))
+                    .into(),
+            );
+            let version_req = match &version_req {
+                Some(version_req) => version_req.to_string(),
+                None => to.version.to_string(),
+            };
+            dep.insert("version", version_req.into());
+            to.name.clone()
+        };
+        if !features.is_empty() {
+            dep.insert(
+                "features",
+                features.iter().collect::<toml_edit::Array>().into(),
+            );
+        }
+        if optional {
+            dep.insert("optional", true.into());
+        }
+        let dep = if dep.len() == 1 && dep.contains_key("workspace") {
+            crate::manifest::inherited()
+        } else {
+            toml_edit::value(dep)
+        };
+
+        let mut cargo_toml = crate::fs::read_toml_edit(&from.manifest_path)?;
+        let deps = cargo_toml[field]
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+            .with_context(|| format!("`{}` must be a table", field))?;
+        ensure!(
+            !deps.contains_key(&key),
+            "`{}` is already in `{}` of {}",
+            key,
+            field,
+            from.manifest_path,
+        );
+        let was_sorted = deps
+            .iter()
+            .map(|(k, _)| k)
+            .tuple_windows()
+            .all(|(a, b)| a <= b);
+        deps.insert(&key, dep);
+        if was_sorted {
+            deps.sort_values();
+        }
+        stderr.status(
+            "Adding",
+            format!("{:?} to `{}` of {}", key, field, from.manifest_path),
+        )?;
+        crate::fs::write(&from.manifest_path, cargo_toml.to_string(), dry_run)?;
+
+        if dry_run {
+            stderr.warn("not linking due to dry run")?;
+        } else {
+            stderr.status("Updating", workspace_root.join("Cargo.lock"))?;
+            cargo_metadata(
+                Some(&workspace_root.join("Cargo.toml")),
+                false,
+                false,
+                offline,
+                workspace_root,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Unlink<'a, W> {
+    metadata: &'a Metadata,
+    from: anyhow::Result<&'a Package>,
+    to: anyhow::Result<&'a Package>,
+    kind: Option<DependencyKind>,
+    dry_run: bool,
+    offline: bool,
+    stderr: W,
+}
+
+impl<'a> Unlink<'a, NoColor<Sink>> {
+    pub fn from_metadata(metadata: &'a Metadata, from: &str, to: &str) -> Self {
+        Self {
+            metadata,
+            from: metadata.query_for_member(from),
+            to: metadata.query_for_member(to),
+            kind: None,
+            dry_run: false,
+            offline: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<'a, W: WriteColor> Unlink<'a, W> {
+    /// If `None`, removes the dependency from every kind of dependency table.
+    pub fn kind(self, kind: Option<DependencyKind>) -> Self {
+        Self { kind, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Unlink<'a, W2> {
+        Unlink {
+            stderr,
+            metadata: self.metadata,
+            from: self.from,
+            to: self.to,
+            kind: self.kind,
+            dry_run: self.dry_run,
+            offline: self.offline,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            mut stderr,
+            metadata,
+            from,
+            to,
+            kind,
+            dry_run,
+            offline,
+        } = self;
+
+        let (from, to) = (from?, to?);
+        let workspace_root = &metadata.workspace_root;
+        let fields = match kind {
+            Some(kind) => vec![dependency_table_name(kind)?],
+            None => crate::manifest::DEPENDENCY_KINDS.to_vec(),
+        };
+
+        let from_dir = from
+            .manifest_path
+            .parent()
+            .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+        let to_dir = to
+            .manifest_path
+            .parent()
+            .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+
+        let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+        let workspace_deps = crate::manifest::workspace_table(&root_cargo_toml, "dependencies");
+        let points_to = |key: &str, dep: &toml_edit::Item| -> bool {
+            let (base, dep) = if crate::manifest::is_inherited(dep) {
+                match workspace_deps.and_then(|deps| deps.get(key)) {
+                    Some(dep) => (&**workspace_root, dep),
+                    None => return false,
+                }
+            } else {
+                (from_dir, dep)
+            };
+            dep.get("path")
+                .and_then(toml_edit::Item::as_str)
+                .is_some_and(|path| crate::manifest::normalize(&base.join(path)) == to_dir)
+        };
+
+        let mut cargo_toml = crate::fs::read_toml_edit(&from.manifest_path)?;
+        let mut removed = vec![];
+        let mut remove_from = |table: &mut toml_edit::Item, prefix: &str| -> io::Result<()> {
+            for field in &fields {
+                let Some(deps) = table
+                    .get_mut(field)
+                    .and_then(toml_edit::Item::as_table_like_mut)
+                else {
+                    continue;
+                };
+                let keys = deps
+                    .iter()
+                    .filter(|(key, dep)| points_to(key, dep))
+                    .map(|(key, _)| key.to_owned())
+                    .collect::<Vec<_>>();
+                let removes_any = !keys.is_empty();
+                for key in keys {
+                    let optional = deps
+                        .remove(&key)
+                        .and_then(|d| d.get("optional").and_then(toml_edit::Item::as_bool))
+                        .unwrap_or(false);
+                    stderr.status_with_color(
+                        "Removing",
+                        format!(
+                            "{:?} from `{}{}` of {}",
+                            key, prefix, field, from.manifest_path
+                        ),
+                        termcolor::Color::Red,
+                    )?;
+                    removed.push((key, optional));
+                }
+                if removes_any && deps.is_empty() {
+                    table.as_table_like_mut().expect("checked").remove(field);
+                }
+            }
+            Ok(())
+        };
+        remove_from(cargo_toml.as_item_mut(), "")?;
+        if let Some(targets) = cargo_toml
+            .get_mut("target")
+            .and_then(toml_edit::Item::as_table_like_mut)
+        {
+            for (cfg, target) in targets.iter_mut() {
+                remove_from(target, &format!("target.{}.", cfg.display_repr()))?;
+            }
+        }
+
+        ensure!(
+            !removed.is_empty(),
+            "`{}` does not depend on `{}`{}",
+            from.name,
+            to.name,
+            match kind {
+                Some(kind) => format!(" in `{}`", dependency_table_name(kind)?),
+                None => "".to_owned(),
+            },
+        );
+
+        let optional_keys = removed
+            .iter()
+            .filter(|(_, optional)| *optional)
+            .map(|(key, _)| &**key)
+            .collect::<Vec<_>>();
+        if let Some(features) = cargo_toml
+            .get_mut("features")
+            .and_then(toml_edit::Item::as_table_like_mut)
+        {
+            for (feature, values) in features.iter_mut() {
+                let Some(values) = values.as_array_mut() else {
+                    continue;
+                };
+                let refers_removed = |value: &toml_edit::Value| {
+                    value.as_str().is_some_and(|v| {
+                        optional_keys.iter().any(|key| {
+                            v == *key
+                                || v == format!("dep:{}", key)
+                                || v.starts_with(&format!("{}/", key))
+                                || v.starts_with(&format!("{}?/", key))
+                        })
+                    })
+                };
+                for value in values.iter().filter(|v| refers_removed(v)) {
+                    stderr.status_with_color(
+                        "Removing",
+                        format!(
+                            "{:?} from `features.{}`",
+                            value.as_str().unwrap_or_default(),
+                            feature
+                        ),
+                        termcolor::Color::Red,
+                    )?;
+                }
+                if values.iter().any(refers_removed) {
+                    values.retain(|v| !refers_removed(v));
+                    values.fmt();
+                }
+            }
+        }
+
+        crate::fs::write(&from.manifest_path, cargo_toml.to_string(), dry_run)?;
+
+        if dry_run {
+            stderr.warn("not unlinking due to dry run")?;
+        } else {
+            stderr.status("Updating", workspace_root.join("Cargo.lock"))?;
+            cargo_metadata(
+                Some(&workspace_root.join("Cargo.toml")),
+                false,
+                false,
+                offline,
+                workspace_root,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PublishOrder<'a, O, E> {
+    metadata: &'a Metadata,
+    json: bool,
+    stdout: O,
+    stderr: E,
+}
+
+impl<'a> PublishOrder<'a, Sink, NoColor<Sink>> {
+    pub fn from_metadata(metadata: &'a Metadata) -> Self {
+        Self {
+            metadata,
+            json: false,
+            stdout: io::sink(),
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<'a, O: io::Write, E: WriteColor> PublishOrder<'a, O, E> {
+    pub fn json(self, json: bool) -> Self {
+        Self { json, ..self }
+    }
+
+    pub fn stdout<O2: io::Write>(self, stdout: O2) -> PublishOrder<'a, O2, E> {
+        PublishOrder {
+            stdout,
+            metadata: self.metadata,
+            json: self.json,
+            stderr: self.stderr,
+        }
+    }
+
+    pub fn stderr<E2: WriteColor>(self, stderr: E2) -> PublishOrder<'a, O, E2> {
+        PublishOrder {
+            stderr,
+            metadata: self.metadata,
+            json: self.json,
+            stdout: self.stdout,
+        }
+    }
+
+    /// Prints the publishable members level by level. The members in a level only depend on the
+    /// ones in the previous levels.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            metadata,
+            json,
+            mut stdout,
+            mut stderr,
+        } = self;
+
+        let levels = metadata.publish_levels(&mut stderr)?;
+
+        if json {
+            let levels = levels
+                .iter()
+                .map(|level| {
+                    level
+                        .iter()
+                        .map(|p| {
+                            serde_json::json!({
+                                "name": p.name,
+                                "version": p.version.to_string(),
+                                "manifest_path": p.manifest_path,
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_writer(&mut stdout, &serde_json::json!({ "levels": levels }))?;
+            writeln!(stdout)?;
+        } else {
+            for level in &levels {
+                writeln!(stdout, "{}", level.iter().map(|p| &p.name).format(" "))?;
+            }
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct CheckPublish<'a, O, E> {
+    metadata: &'a Metadata,
+    json: bool,
+    stdout: O,
+    stderr: E,
+}
+
+impl<'a> CheckPublish<'a, Sink, NoColor<Sink>> {
+    pub fn from_metadata(metadata: &'a Metadata) -> Self {
+        Self {
+            metadata,
+            json: false,
+            stdout: io::sink(),
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<'a, O: io::Write, E: WriteColor> CheckPublish<'a, O, E> {
+    pub fn json(self, json: bool) -> Self {
+        Self { json, ..self }
+    }
+
+    pub fn stdout<O2: io::Write>(self, stdout: O2) -> CheckPublish<'a, O2, E> {
+        CheckPublish {
+            stdout,
+            metadata: self.metadata,
+            json: self.json,
+            stderr: self.stderr,
+        }
+    }
+
+    pub fn stderr<E2: WriteColor>(self, stderr: E2) -> CheckPublish<'a, O, E2> {
+        CheckPublish {
+            stderr,
+            metadata: self.metadata,
+            json: self.json,
+            stdout: self.stdout,
+        }
+    }
+
+    /// Prints the problems that would block or degrade publishing each publishable member.
+    /// Fails if any are found.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            metadata,
+            json,
+            mut stdout,
+            mut stderr,
+        } = self;
+
+        let workspace_root = &metadata.workspace_root;
+        let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+        let by_dir = metadata
+            .packages
+            .iter()
+            .flat_map(|p| Some((p.manifest_path.parent()?, p)))
+            .collect::<HashMap<_, _>>();
+
+        let mut publishable = metadata
+            .packages
+            .iter()
+            .filter(|p| metadata.is_publishable(p))
+            .collect::<Vec<_>>();
+        publishable.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut reports = vec![];
+        for package in &publishable {
+            let package_dir = package
+                .manifest_path
+                .parent()
+                .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+            let mut problems = vec![];
+
+            for dep in &package.dependencies {
+                if dep.kind == DependencyKind::Development {
+                    continue;
+                }
+                let Some(path) = &dep.path else {
+                    continue;
+                };
+                if dep.req == semver::VersionReq::STAR {
+                    problems.push(format!("path dependency `{}` has no `version`", dep.name));
+                }
+                if by_dir
+                    .get(&**path)
+                    .is_some_and(|p| p.publish.as_ref().is_some_and(Vec::is_empty))
+                {
+                    problems.push(format!(
+                        "depends on `{}`, which has `publish = false`",
+                        dep.name,
+                    ));
+                }
+            }
+
+            if package.description.is_none() {
+                problems.push("`package.description` is missing".to_owned());
+            }
+            if package.license.is_none() && package.license_file.is_none() {
+                problems.push("`package.license` or `package.license-file` is missing".to_owned());
+            }
+            if package.repository.is_none() {
+                problems.push("`package.repository` is missing".to_owned());
+            }
+
+            let mut cargo_toml = crate::fs::read_toml_edit(&package.manifest_path)?;
+            crate::manifest::resolve_inheritance(
+                &mut cargo_toml,
+                &root_cargo_toml,
+                workspace_root,
+                package_dir,
+            )?;
+            let patterns = |field: &str| -> Vec<String> {
+                cargo_toml
+                    .get("package")
+                    .and_then(|p| p.get(field))
+                    .and_then(toml_edit::Item::as_array)
+                    .map(|a| a.iter().flat_map(|s| s.as_str()).map(Into::into).collect())
+                    .unwrap_or_default()
+            };
+            let (include, exclude) = (patterns("include"), patterns("exclude"));
+            let (field, patterns) = if !include.is_empty() {
+                ("include", include)
+            } else {
+                ("exclude", exclude)
+            };
+            if !patterns.is_empty() {
+                let mut builder = ignore::gitignore::GitignoreBuilder::new(package_dir);
+                for pattern in &patterns {
+                    builder.add_line(None, pattern)?;
+                }
+                let matcher = builder.build()?;
+
+                // Cargo adds `readme` and `license-file` by itself.
+                let referenced = package.targets.iter().map(|t| {
+                    let referrer = if t.kind.iter().any(|k| k == "custom-build") {
+                        "`package.build`".to_owned()
+                    } else {
+                        format!("{} target `{}`", t.kind.iter().format("/"), t.name)
+                    };
+                    (referrer, &t.src_path)
+                });
+                for (referrer, path) in referenced {
+                    let path = crate::manifest::normalize(path);
+                    let Ok(relative) = path.strip_prefix(package_dir) else {
+                        continue;
+                    };
+                    let matched = matcher
+                        .matched_path_or_any_parents(relative, false)
+                        .is_ignore();
+                    if matched == (field == "exclude") {
+                        problems.push(format!(
+                            "`{}` for {} is dropped by `package.{}`",
+                            relative, referrer, field,
+                        ));
+                    }
+                }
+            }
+
+            reports.push((*package, problems));
+        }
+
+        if json {
+            let reports = reports
+                .iter()
+                .map(|(package, problems)| {
+                    serde_json::json!({
+                        "name": package.name,
+                        "manifest_path": package.manifest_path,
+                        "problems": problems,
+                    })
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_writer(&mut stdout, &reports)?;
+            writeln!(stdout)?;
+        } else {
+            for (package, problems) in &reports {
+                for problem in problems {
+                    writeln!(stdout, "{}: {}", package.name, problem)?;
+                }
+            }
+        }
+        stdout.flush()?;
+
+        let num_problems = reports.iter().map(|(_, p)| p.len()).sum::<usize>();
+        stderr.status(
+            "Checked",
+            format!(
+                "{} package{}",
+                reports.len(),
+                if reports.len() == 1 { "" } else { "s" },
+            ),
+        )?;
+        ensure!(
+            num_problems == 0,
+            "found {} problem{}",
+            num_problems,
+            if num_problems == 1 { "" } else { "s" },
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Which<'a, O, E> {
+    metadata: &'a Metadata,
+    paths: anyhow::Result<Vec<PathBuf>>,
+    json: bool,
+    stdout: O,
+    stderr: E,
+}
+
+impl<'a> Which<'a, Sink, NoColor<Sink>> {
+    pub fn from_metadata<Ps: IntoIterator<Item = P>, P: AsRef<Path>>(
+        metadata: &'a Metadata,
+        paths: Ps,
+    ) -> Self {
+        Self {
+            metadata,
+            paths: paths.into_iter().map(ensure_absolute).collect(),
+            json: false,
+            stdout: io::sink(),
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<'a, O: io::Write, E: WriteColor> Which<'a, O, E> {
+    pub fn json(self, json: bool) -> Self {
+        Self { json, ..self }
+    }
+
+    pub fn stdout<O2: io::Write>(self, stdout: O2) -> Which<'a, O2, E> {
+        Which {
+            stdout,
+            metadata: self.metadata,
+            paths: self.paths,
+            json: self.json,
+            stderr: self.stderr,
+        }
+    }
+
+    pub fn stderr<E2: WriteColor>(self, stderr: E2) -> Which<'a, O, E2> {
+        Which {
+            stderr,
+            metadata: self.metadata,
+            paths: self.paths,
+            json: self.json,
+            stdout: self.stdout,
+        }
+    }
+
+    /// Prints the package that owns each path, which is the one with the nearest `Cargo.toml`
+    /// in the workspace. The paths do not have to exist.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            metadata,
+            paths,
+            json,
+            mut stdout,
+            mut stderr,
+        } = self;
+
+        let paths = paths?;
+        let workspace_root = &metadata.workspace_root;
+        let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+        let excluded_dirs = excluded_dirs(workspace_root, &root_cargo_toml);
+        let members = metadata
+            .packages
+            .iter()
+            .filter(|p| metadata.workspace_members.contains(&p.id))
+            .flat_map(|p| Some((p.manifest_path.parent()?, p)))
+            .collect::<HashMap<_, _>>();
+
+        let mut owners = vec![];
+        for path in &paths {
+            let path = crate::manifest::normalize(path);
+            let display_path = path
+                .strip_prefix(workspace_root)
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|_| path.clone());
+
+            let Some(dir) = manifest_dirs(&path)
+                .next()
+                .filter(|d| d.starts_with(workspace_root))
+            else {
+                stderr.warn(format!("`{}` is outside of the workspace", path))?;
+                owners.push((display_path, None));
+                continue;
+            };
+
+            let manifest_path = dir.join("Cargo.toml");
+            let (name, status) = if let Some(member) = members.get(dir) {
+                (Some(member.name.clone()), PackageStatus::Member)
+            } else {
+                let name = crate::fs::read_toml_edit(&manifest_path)?
+                    .get("package")
+                    .and_then(|p| p.get("name"))
+                    .and_then(toml_edit::Item::as_str)
+                    .map(ToOwned::to_owned);
+                let status = if dir == workspace_root {
+                    PackageStatus::Root
+                } else if excluded_dirs.iter().any(|d| dir.starts_with(d)) {
+                    PackageStatus::Excluded
+                } else {
+                    PackageStatus::Inactive
+                };
+                (name, status)
+            };
+            owners.push((display_path, Some((name, manifest_path, status))));
+        }
+
+        if json {
+            let owners = owners
+                .iter()
+                .map(|(path, owner)| {
+                    let owner = owner.as_ref().map(|(name, manifest_path, status)| {
+                        serde_json::json!({
+                            "name": name,
+                            "manifest_path": manifest_path,
+                            "status": <&str>::from(*status),
+                        })
+                    });
+                    serde_json::json!({ "path": path, "owner": owner })
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_writer(&mut stdout, &owners)?;
+            writeln!(stdout)?;
+        } else {
+            for (path, owner) in &owners {
+                if let Some((name, manifest_path, _)) = owner {
+                    let owner = match name {
+                        Some(name) => name.clone(),
+                        None => crate::manifest::relative_path(workspace_root, manifest_path)
+                            .into_string(),
+                    };
+                    writeln!(stdout, "{}: {}", path, owner)?;
+                }
+            }
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// Output format of [`Changed`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChangedFormat {
+    Names,
+    Paths,
+    Flags,
+    Json,
+}
+
+#[derive(Debug)]
+pub struct Changed<'a, W> {
+    metadata: &'a Metadata,
+    since: String,
+    dev: bool,
+    format: ChangedFormat,
+    stdout: W,
+}
 
-    pub fn cargo_new_stderr_redirection(self, cargo_new_stderr_redirection: Stdio) -> Self {
+impl<'a> Changed<'a, Sink> {
+    pub fn from_metadata(metadata: &'a Metadata, since: &str) -> Self {
         Self {
-            cargo_new_stderr_redirection,
-            ..self
+            metadata,
+            since: since.to_owned(),
+            dev: false,
+            format: ChangedFormat::Names,
+            stdout: io::sink(),
         }
     }
+}
 
-    pub fn offline(self, offline: bool) -> Self {
-        Self { offline, ..self }
+impl<'a, W: io::Write> Changed<'a, W> {
+    /// Also treats the members that have the affected ones as dev-dependencies as affected.
+    pub fn dev(self, dev: bool) -> Self {
+        Self { dev, ..self }
     }
 
-    pub fn dry_run(self, dry_run: bool) -> Self {
-        Self { dry_run, ..self }
+    pub fn format(self, format: ChangedFormat) -> Self {
+        Self { format, ..self }
     }
 
-    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> New<W2> {
-        New {
-            possibly_empty_workspace_root: self.possibly_empty_workspace_root,
-            path: self.path,
-            cargo_new_registry: self.cargo_new_registry,
-            cargo_new_vcs: self.cargo_new_vcs,
-            cargo_new_lib: self.cargo_new_lib,
-            cargo_new_name: self.cargo_new_name,
-            cargo_new_stderr_redirection: self.cargo_new_stderr_redirection,
-            offline: self.offline,
-            dry_run: self.dry_run,
-            stderr,
+    pub fn stdout<W2: io::Write>(self, stdout: W2) -> Changed<'a, W2> {
+        Changed {
+            stdout,
+            metadata: self.metadata,
+            since: self.since,
+            dev: self.dev,
+            format: self.format,
         }
     }
 
+    /// Prints the members affected by the changes in the working tree since a git revision.
     pub fn exec(self) -> anyhow::Result<()> {
         let Self {
-            possibly_empty_workspace_root,
-            path,
-            cargo_new_registry,
-            cargo_new_vcs,
-            cargo_new_lib,
-            cargo_new_name,
-            cargo_new_stderr_redirection,
-            offline,
-            dry_run,
-            mut stderr,
+            metadata,
+            since,
+            dev,
+            format,
+            mut stdout,
         } = self;
 
-        let (possibly_empty_workspace_root, path) = (possibly_empty_workspace_root?, path?);
+        let changed = metadata.changed_members(&since, dev)?;
 
-        Include::new(&possibly_empty_workspace_root, [&path])
-            .force(true)
-            .dry_run(dry_run)
-            .stderr(&mut stderr)
-            .exec()?;
+        match format {
+            ChangedFormat::Names => {
+                for (package, _) in &changed {
+                    writeln!(stdout, "{}", package.name)?;
+                }
+            }
+            ChangedFormat::Paths => {
+                for (package, _) in &changed {
+                    let dir = package
+                        .manifest_path
+                        .parent()
+                        .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+                    let dir = crate::manifest::relative_path(&metadata.workspace_root, dir);
+                    writeln!(stdout, "{}", dir)?;
+                }
+            }
+            ChangedFormat::Flags => {
+                let flags = changed.iter().map(|(p, _)| format!("-p {}", p.name));
+                writeln!(stdout, "{}", flags.format(" "))?;
+            }
+            ChangedFormat::Json => {
+                let changed = changed
+                    .iter()
+                    .map(|(package, reason)| {
+                        serde_json::json!({
+                            "name": package.name,
+                            "version": package.version.to_string(),
+                            "manifest_path": package.manifest_path,
+                            "reason": reason,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::to_writer(&mut stdout, &changed)?;
+                writeln!(stdout)?;
+            }
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+}
 
-        if dry_run {
-            stderr.warn("not creating a new package due to dry run")?;
-        } else {
-            let cargo_exe = env::var_os("CARGO").with_context(|| "`$CARGO` should be present")?;
+/// A set of members chosen by package ID specifications, globs, and the relations between
+/// members.
+#[derive(Default, Clone, Debug)]
+pub struct Selection {
+    specs: Vec<String>,
+    all: bool,
+    exclude: Vec<String>,
+    paths: Vec<String>,
+    dependents_of: Vec<String>,
+    dependencies_of: Vec<String>,
+    changed_since: Option<String>,
+    has_feature: Vec<String>,
+}
 
-            let args = Args::new()
-                .arg("new")
-                .option(cargo_new_registry.as_ref(), "--registry")
-                .option(cargo_new_vcs.as_ref(), "--vcs")
-                .flag(cargo_new_lib, "--lib")
-                .option(cargo_new_name.as_ref(), "--name")
-                .flag(offline, "--offline")
-                .arg(&path);
+impl Selection {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-            let output = Command::new(&cargo_exe)
-                .args(&args)
-                .current_dir(&possibly_empty_workspace_root)
-                .stdin(Stdio::null())
-                .stdout(Stdio::null())
-                .stderr(cargo_new_stderr_redirection)
-                .output()
-                .with_context(|| format!("failed to execute `{}`", cargo_exe.to_string_lossy()))?;
+    /// Selects the members by package ID specifications or globs on their names.
+    pub fn specs<Ss: IntoIterator<Item = S>, S: AsRef<str>>(self, specs: Ss) -> Self {
+        let specs = specs.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        Self { specs, ..self }
+    }
 
-            stderr.write_all(&output.stderr)?;
+    pub fn all(self, all: bool) -> Self {
+        Self { all, ..self }
+    }
 
-            if !output.status.success() {
-                bail!(
-                    "`{}{}` failed ({})",
-                    shell_escape::escape(cargo_exe.to_string_lossy()),
-                    args.0.iter().format_with("", |s, f| f(&format_args!(
-                        " {}",
-                        shell_escape::escape(s.to_string_lossy()),
-                    ))),
-                    output.status,
-                );
-            }
+    /// Removes the members matching the specifications or globs from the selection.
+    pub fn exclude<Ss: IntoIterator<Item = S>, S: AsRef<str>>(self, exclude: Ss) -> Self {
+        let exclude = exclude.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        Self { exclude, ..self }
+    }
 
-            stderr.status("Updating", possibly_empty_workspace_root.join("Cargo.lock"))?;
+    /// Selects the members whose directories relative to the workspace root match the globs.
+    pub fn paths<Ss: IntoIterator<Item = S>, S: AsRef<str>>(self, paths: Ss) -> Self {
+        let paths = paths.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        Self { paths, ..self }
+    }
 
-            cargo_metadata(None, false, false, offline, &possibly_empty_workspace_root)?;
+    /// Selects the members depending on the matching ones, directly or indirectly, with normal
+    /// or build dependencies.
+    pub fn dependents_of<Ss: IntoIterator<Item = S>, S: AsRef<str>>(self, specs: Ss) -> Self {
+        let dependents_of = specs.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        Self {
+            dependents_of,
+            ..self
         }
-        Ok(())
     }
-}
-
-#[derive(Default, Debug)]
-struct Args(Vec<OsString>);
 
-impl Args {
-    fn new() -> Self {
-        Self::default()
+    /// Selects the members the matching ones depend on, directly or indirectly, with normal or
+    /// build dependencies.
+    pub fn dependencies_of<Ss: IntoIterator<Item = S>, S: AsRef<str>>(self, specs: Ss) -> Self {
+        let dependencies_of = specs.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        Self {
+            dependencies_of,
+            ..self
+        }
     }
 
-    fn flag(mut self, val: bool, long: &'static str) -> Self {
-        if val {
-            self.0.push(long.to_owned().into());
+    /// Selects the members affected by the changes since a git revision.
+    pub fn changed_since(self, changed_since: Option<&str>) -> Self {
+        let changed_since = changed_since.map(ToOwned::to_owned);
+        Self {
+            changed_since,
+            ..self
         }
-        self
     }
 
-    fn option(mut self, val: Option<impl AsRef<OsStr>>, long: &'static str) -> Self {
-        if let Some(val) = val {
-            self.0.push(long.to_owned().into());
-            self.0.push(val.as_ref().to_owned());
+    /// Keeps only the members that have all of the features.
+    pub fn has_feature<Ss: IntoIterator<Item = S>, S: AsRef<str>>(self, features: Ss) -> Self {
+        let has_feature = features
+            .into_iter()
+            .map(|s| s.as_ref().to_owned())
+            .collect();
+        Self {
+            has_feature,
+            ..self
         }
-        self
     }
 
-    fn arg(mut self, val: impl AsRef<OsStr>) -> Self {
-        self.0.push(val.as_ref().to_owned());
-        self
+    /// Returns `true` if nothing is specified.
+    pub fn is_empty(&self) -> bool {
+        !self.has_sources() && self.exclude.is_empty() && self.has_feature.is_empty()
     }
-}
 
-impl AsRef<[OsString]> for Args {
-    fn as_ref(&self) -> &[OsString] {
-        &self.0
+    fn has_sources(&self) -> bool {
+        self.all
+            || !self.specs.is_empty()
+            || !self.paths.is_empty()
+            || !self.dependents_of.is_empty()
+            || !self.dependencies_of.is_empty()
+            || self.changed_since.is_some()
     }
-}
 
-impl Display for Args {
-    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        Debug::fmt(&self.0, fmt)
-    }
-}
+    /// Resolves the selection into members sorted by name. Unless the selection only consists
+    /// of exact specifications, the result is printed to `stderr`. `exclude` is only allowed
+    /// with `all`, and `has_feature` only narrows down the members selected by the others.
+    pub fn select<'a>(
+        &self,
+        metadata: &'a Metadata,
+        mut stderr: impl WriteColor,
+    ) -> anyhow::Result<Vec<&'a Package>> {
+        let Self {
+            specs,
+            all,
+            exclude,
+            paths,
+            dependents_of,
+            dependencies_of,
+            changed_since,
+            has_feature,
+        } = self;
 
-impl<'a> IntoIterator for &'a Args {
-    type Item = &'a OsString;
-    type IntoIter = slice::Iter<'a, OsString>;
+        ensure!(
+            exclude.is_empty() || *all,
+            "`--exclude` can only be used with `--all`",
+        );
+        ensure!(
+            has_feature.is_empty() || self.has_sources(),
+            "`--has-feature` requires members to filter. specify packages or `--all`",
+        );
 
-    fn into_iter(self) -> slice::Iter<'a, OsString> {
-        self.0.iter()
+        let members = || metadata.workspace_members.iter();
+        let mut selected = BTreeSet::new();
+
+        if *all {
+            selected.extend(members());
+        }
+        for spec in specs {
+            selected.extend(metadata.match_members(spec)?);
+        }
+        for glob in paths {
+            let matcher = globset::GlobBuilder::new(glob)
+                .literal_separator(true)
+                .build()?
+                .compile_matcher();
+            let matched = members()
+                .filter(|id| {
+                    let dir = metadata[id]
+                        .manifest_path
+                        .parent()
+                        .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+                    let dir = crate::manifest::relative_path(&metadata.workspace_root, dir);
+                    matcher.is_match(dir.as_str())
+                })
+                .collect::<Vec<_>>();
+            ensure!(!matched.is_empty(), "`{}` did not match any members", glob);
+            selected.extend(matched);
+        }
+        for (specs, dependents) in [(dependents_of, true), (dependencies_of, false)] {
+            for spec in specs {
+                let seeds = metadata.match_members(spec)?;
+                let closure = metadata.member_closure(seeds.iter().copied(), dependents)?;
+                selected.extend(closure.into_iter().filter(|id| !seeds.contains(id)));
+            }
+        }
+        if let Some(since) = changed_since {
+            let changed = metadata.changed_members(since, false)?;
+            selected.extend(changed.into_iter().map(|(p, _)| &p.id));
+        }
+        selected.retain(|id| {
+            has_feature
+                .iter()
+                .all(|feature| metadata[id].features.contains_key(feature))
+        });
+        for spec in exclude {
+            for id in metadata.match_members(spec)? {
+                selected.remove(id);
+            }
+        }
+
+        let mut selected = selected
+            .into_iter()
+            .map(|id| &metadata[id])
+            .collect::<Vec<_>>();
+        selected.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let only_exact_specs = specs.iter().all(|s| !is_glob(s))
+            && !*all
+            && exclude.is_empty()
+            && paths.is_empty()
+            && dependents_of.is_empty()
+            && dependencies_of.is_empty()
+            && changed_since.is_none()
+            && has_feature.is_empty();
+        if !only_exact_specs {
+            if selected.is_empty() {
+                stderr.warn("no members are selected")?;
+            } else {
+                stderr.status("Selected", selected.iter().map(|p| &p.name).format(", "))?;
+            }
+        }
+        Ok(selected)
     }
 }
 
-impl IntoIterator for Args {
-    type Item = OsString;
-    type IntoIter = vec::IntoIter<OsString>;
-
-    fn into_iter(self) -> vec::IntoIter<OsString> {
-        self.0.into_iter()
+/// Extends `ids` with every package reachable from them through `edges`.
+fn closure<'a>(
+    edges: &HashMap<&'a PackageId, Vec<&'a PackageId>>,
+    ids: impl IntoIterator<Item = &'a PackageId>,
+) -> BTreeSet<&'a PackageId> {
+    let mut closure = ids.into_iter().collect::<BTreeSet<_>>();
+    let mut queue = closure.iter().copied().collect::<VecDeque<_>>();
+    while let Some(id) = queue.pop_front() {
+        for &next in edges.get(id).into_iter().flatten() {
+            if closure.insert(next) {
+                queue.push_back(next);
+            }
+        }
     }
+    closure
+}
+
+fn is_glob(spec: &str) -> bool {
+    spec.contains(['*', '?', '['])
 }
 
 #[derive(Debug)]
-pub struct Cp<W> {
-    src: anyhow::Result<PathBuf>,
-    dst: anyhow::Result<PathBuf>,
-    dry_run: bool,
-    no_rename: bool,
-    stderr: W,
+pub struct Exec<'a, O, E> {
+    metadata: &'a Metadata,
+    program: OsString,
+    args: Vec<OsString>,
+    selection: Selection,
+    with_dependencies: bool,
+    with_dependents: bool,
+    jobs: usize,
+    fail_fast: bool,
+    stdout: O,
+    stderr: E,
 }
 
-impl Cp<NoColor<Sink>> {
-    pub fn new(src: &Path, dst: &Path) -> Self {
+impl<'a> Exec<'a, Sink, NoColor<Sink>> {
+    pub fn from_metadata<Ss: IntoIterator<Item = S>, S: AsRef<OsStr>>(
+        metadata: &'a Metadata,
+        program: impl AsRef<OsStr>,
+        args: Ss,
+    ) -> Self {
         Self {
-            src: ensure_absolute(src),
-            dst: ensure_absolute(dst),
-            dry_run: false,
-            no_rename: false,
+            metadata,
+            program: program.as_ref().to_owned(),
+            args: args.into_iter().map(|s| s.as_ref().to_owned()).collect(),
+            selection: Selection::new(),
+            with_dependencies: false,
+            with_dependents: false,
+            jobs: 1,
+            fail_fast: false,
+            stdout: io::sink(),
             stderr: NoColor::new(io::sink()),
         }
     }
+}
 
-    pub fn from_metadata(metadata: &Metadata, src: &str, dst: &Path) -> Self {
+impl<'a, O: io::Write, E: WriteColor> Exec<'a, O, E> {
+    /// Selects the members to run the command in. Every member is a candidate if it only
+    /// consists of `exclude` and `has_feature`, or is empty.
+    pub fn selection(self, selection: Selection) -> Self {
+        Self { selection, ..self }
+    }
+
+    pub fn with_dependencies(self, with_dependencies: bool) -> Self {
         Self {
-            src: metadata.query_for_member(Some(src)).map(|member| {
-                member
-                    .manifest_path
-                    .parent()
-                    .expect(r#"`manifest_path` should end with "Cargo.toml""#)
-                    .to_owned()
-            }),
-            dst: ensure_absolute(dst),
-            dry_run: false,
-            no_rename: false,
+            with_dependencies,
+            ..self
+        }
+    }
+
+    pub fn with_dependents(self, with_dependents: bool) -> Self {
+        Self {
+            with_dependents,
+            ..self
+        }
+    }
+
+    pub fn jobs(self, jobs: usize) -> Self {
+        Self { jobs, ..self }
+    }
+
+    pub fn fail_fast(self, fail_fast: bool) -> Self {
+        Self { fail_fast, ..self }
+    }
+
+    pub fn stdout<O2: io::Write>(self, stdout: O2) -> Exec<'a, O2, E> {
+        Exec {
+            stdout,
+            metadata: self.metadata,
+            program: self.program,
+            args: self.args,
+            selection: self.selection,
+            with_dependencies: self.with_dependencies,
+            with_dependents: self.with_dependents,
+            jobs: self.jobs,
+            fail_fast: self.fail_fast,
+            stderr: self.stderr,
+        }
+    }
+
+    pub fn stderr<E2: WriteColor>(self, stderr: E2) -> Exec<'a, O, E2> {
+        Exec {
+            stderr,
+            metadata: self.metadata,
+            program: self.program,
+            args: self.args,
+            selection: self.selection,
+            with_dependencies: self.with_dependencies,
+            with_dependents: self.with_dependents,
+            jobs: self.jobs,
+            fail_fast: self.fail_fast,
+            stdout: self.stdout,
+        }
+    }
+
+    /// Runs the command in the directory of each selected member. A member waits for the
+    /// selected members it depends on, and up to `jobs` members run at once. Each line of the
+    /// output is prefixed with the name of the member.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            metadata,
+            program,
+            args,
+            selection,
+            with_dependencies,
+            with_dependents,
+            jobs,
+            fail_fast,
+            mut stdout,
+            mut stderr,
+        } = self;
+
+        ensure!(jobs > 0, "`jobs` must be at least 1");
+
+        let selection = if !selection.has_sources() {
+            selection.all(true)
+        } else {
+            selection
+        };
+        let mut selected = selection
+            .select(metadata, &mut stderr)?
+            .into_iter()
+            .map(|p| &p.id)
+            .collect::<BTreeSet<_>>();
+        if with_dependencies {
+            selected = metadata.member_closure(selected, false)?;
+        }
+        if with_dependents {
+            selected = metadata.member_closure(selected, true)?;
+        }
+
+        let edges = metadata.member_edges(false)?;
+        let mut waiting_for = HashMap::new();
+        for &id in &selected {
+            let deps = closure(&edges, [id])
+                .into_iter()
+                .filter(|dep| *dep != id && selected.contains(dep))
+                .collect::<HashSet<_>>();
+            waiting_for.insert(id, deps);
+        }
+
+        let command = iter::once(&program)
+            .chain(&args)
+            .map(|s| shell_escape::escape(s.to_string_lossy()))
+            .join(" ");
+
+        let mut pending = selected
+            .iter()
+            .copied()
+            .sorted_by_key(|id| &metadata[id].name)
+            .collect::<Vec<_>>();
+        let mut results = HashMap::new();
+        let mut finished = HashSet::new();
+        let mut aborted = false;
+
+        thread::scope(|scope| -> anyhow::Result<()> {
+            let (tx, rx) = mpsc::channel();
+            let mut running = 0;
+            loop {
+                while running < jobs && !aborted {
+                    let Some(i) = pending
+                        .iter()
+                        .position(|id| waiting_for[id].is_subset(&finished))
+                    else {
+                        break;
+                    };
+                    let id = pending.remove(i);
+                    let package = &metadata[id];
+                    let dir = package
+                        .manifest_path
+                        .parent()
+                        .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+
+                    stderr.status("Running", format!("`{}` in {}", command, dir))?;
+
+                    let (tx, program, args) = (tx.clone(), &program, &args);
+                    scope.spawn(move || {
+                        let start = Instant::now();
+                        let result = run_forwarding(program, args, dir, |stream, line| {
+                            let _ = tx.send(ExecEvent::Line(id, stream, line));
+                        });
+                        let _ = tx.send(ExecEvent::Finished(id, result, start.elapsed()));
+                    });
+                    running += 1;
+                }
+                if running == 0 {
+                    break;
+                }
+                match rx.recv()? {
+                    ExecEvent::Line(id, stream, line) => {
+                        let prefix = format!("[{}] ", metadata[id].name);
+                        let to: &mut dyn io::Write = match stream {
+                            ExecStream::Stdout => &mut stdout,
+                            ExecStream::Stderr => &mut stderr,
+                        };
+                        to.write_all(prefix.as_bytes())?;
+                        to.write_all(&line)?;
+                        to.flush()?;
+                    }
+                    ExecEvent::Finished(id, result, elapsed) => {
+                        running -= 1;
+                        if fail_fast && !result.as_ref().is_ok_and(ExitStatus::success) {
+                            aborted = true;
+                        }
+                        finished.insert(id);
+                        results.insert(id, (result, elapsed));
+                    }
+                }
+            }
+            Ok(())
+        })?;
+
+        let num_failed = results
+            .values()
+            .filter(|(r, _)| !r.as_ref().is_ok_and(ExitStatus::success))
+            .count();
+        stderr.status(
+            "Summary",
+            format!(
+                "{} passed, {} failed, {} skipped",
+                results.len() - num_failed,
+                num_failed,
+                pending.len(),
+            ),
+        )?;
+        for id in selected.iter().sorted_by_key(|id| &metadata[id].name) {
+            let name = &metadata[id].name;
+            match results.get(id) {
+                Some((Ok(status), elapsed)) if status.success() => stderr.status_with_color(
+                    "ok",
+                    format!("{} ({:.2}s)", name, elapsed.as_secs_f64()),
+                    termcolor::Color::Green,
+                )?,
+                Some((result, elapsed)) => stderr.status_with_color(
+                    "FAILED",
+                    format!(
+                        "{} ({}, {:.2}s)",
+                        name,
+                        match result {
+                            Ok(status) => status.to_string(),
+                            Err(err) => err.to_string(),
+                        },
+                        elapsed.as_secs_f64(),
+                    ),
+                    termcolor::Color::Red,
+                )?,
+                None => stderr.status_with_color("skipped", name, termcolor::Color::Yellow)?,
+            }
+        }
+
+        ensure!(
+            num_failed == 0,
+            "`{}` failed in {} member{}",
+            command,
+            num_failed,
+            if num_failed == 1 { "" } else { "s" },
+        );
+        Ok(())
+    }
+}
+
+enum ExecEvent<'a> {
+    Line(&'a PackageId, ExecStream, Vec<u8>),
+    Finished(&'a PackageId, io::Result<ExitStatus>, Duration),
+}
+
+#[derive(Clone, Copy)]
+enum ExecStream {
+    Stdout,
+    Stderr,
+}
+
+/// Runs a command, passing each line of its stdout and stderr to `on_line`.
+fn run_forwarding(
+    program: &OsStr,
+    args: &[OsString],
+    dir: &Path,
+    on_line: impl Fn(ExecStream, Vec<u8>) + Sync,
+) -> io::Result<ExitStatus> {
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let child_stdout = child.stdout.take().expect("should be piped");
+    let child_stderr = child.stderr.take().expect("should be piped");
+
+    let on_line = &on_line;
+    thread::scope(|scope| {
+        let forwarding = scope.spawn(move || forward(child_stdout, ExecStream::Stdout, on_line));
+        forward(child_stderr, ExecStream::Stderr, on_line)?;
+        forwarding.join().expect("should not panic")
+    })?;
+    return child.wait();
+
+    fn forward(
+        from: impl io::Read,
+        stream: ExecStream,
+        on_line: &impl Fn(ExecStream, Vec<u8>),
+    ) -> io::Result<()> {
+        let mut from = io::BufReader::new(from);
+        loop {
+            let mut line = vec![];
+            if from.read_until(b'\n', &mut line)? == 0 {
+                break Ok(());
+            }
+            if !line.ends_with(b"\n") {
+                line.push(b'\n');
+            }
+            on_line(stream, line);
+        }
+    }
+}
+
+/// Output format of [`Graph`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+#[derive(Debug)]
+pub struct Graph<'a, O, E> {
+    metadata: &'a Metadata,
+    format: GraphFormat,
+    focus: Option<String>,
+    depth: usize,
+    all: bool,
+    stdout: O,
+    stderr: E,
+}
+
+impl<'a> Graph<'a, Sink, NoColor<Sink>> {
+    pub fn from_metadata(metadata: &'a Metadata) -> Self {
+        Self {
+            metadata,
+            format: GraphFormat::Dot,
+            focus: None,
+            depth: 1,
+            all: false,
+            stdout: io::sink(),
             stderr: NoColor::new(io::sink()),
         }
     }
 }
 
-impl<W: WriteColor> Cp<W> {
-    pub fn dry_run(self, dry_run: bool) -> Self {
-        Self { dry_run, ..self }
+impl<'a, O: io::Write, E: WriteColor> Graph<'a, O, E> {
+    pub fn format(self, format: GraphFormat) -> Self {
+        Self { format, ..self }
     }
 
-    pub fn no_rename(self, no_rename: bool) -> Self {
-        Self { no_rename, ..self }
+    /// Shows only the packages within `depth` edges of the member.
+    pub fn focus(self, focus: Option<&str>) -> Self {
+        let focus = focus.map(ToOwned::to_owned);
+        Self { focus, ..self }
     }
 
-    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Cp<W2> {
-        Cp {
-            src: self.src,
-            dst: self.dst,
-            dry_run: self.dry_run,
-            no_rename: self.no_rename,
+    pub fn depth(self, depth: usize) -> Self {
+        Self { depth, ..self }
+    }
+
+    /// Also shows the excluded and inactive packages under the workspace root.
+    pub fn all(self, all: bool) -> Self {
+        Self { all, ..self }
+    }
+
+    pub fn stdout<O2: io::Write>(self, stdout: O2) -> Graph<'a, O2, E> {
+        Graph {
+            stdout,
+            metadata: self.metadata,
+            format: self.format,
+            focus: self.focus,
+            depth: self.depth,
+            all: self.all,
+            stderr: self.stderr,
+        }
+    }
+
+    pub fn stderr<E2: WriteColor>(self, stderr: E2) -> Graph<'a, O, E2> {
+        Graph {
             stderr,
+            metadata: self.metadata,
+            format: self.format,
+            focus: self.focus,
+            depth: self.depth,
+            all: self.all,
+            stdout: self.stdout,
         }
     }
 
+    /// Prints the dependency graph of the members. The edges of the members are taken from
+    /// `resolve`, and the ones of the packages only found on disk are taken from their
+    /// manifests.
     pub fn exec(self) -> anyhow::Result<()> {
         let Self {
+            metadata,
+            format,
+            focus,
+            depth,
+            all,
+            mut stdout,
             mut stderr,
-            src,
-            dst,
-            dry_run,
-            no_rename,
         } = self;
 
-        let (src, dst) = (src?, dst?);
+        let workspace_root = &metadata.workspace_root;
+        let resolve = metadata.resolve.as_ref().context("`resolve` is missing")?;
+        let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
 
-        let dst = if dst.exists() {
-            dst.join(src.file_name().expect("should be absolute"))
-        } else {
-            dst
+        let excluded_dirs = excluded_dirs(workspace_root, &root_cargo_toml);
+        let status = |dir: &Path| {
+            if excluded_dirs.iter().any(|d| dir.starts_with(d)) {
+                PackageStatus::Excluded
+            } else {
+                PackageStatus::Inactive
+            }
         };
 
-        ensure!(!dst.exists(), "`{}` exists", dst);
+        let mut nodes = BTreeMap::<PathBuf, GraphNode>::new();
+        let mut edges = BTreeSet::<(PathBuf, PathBuf, EdgeKind)>::new();
 
-        let mut cargo_toml = crate::fs::read_toml_edit(src.join("Cargo.toml"))
-            .with_context(|| format!("`{}` does not seem to be a package", src))?;
-        if let Some(package) = cargo_toml["package"].as_table_mut() {
-            package.remove("workspace");
-            if !no_rename {
-                let file_name = dst.file_name().expect("should exist");
-                package["name"] = toml_edit::value(file_name);
+        let mut dirs = HashMap::new();
+        for package in &metadata.packages {
+            let is_member = metadata.workspace_members.contains(&package.id);
+            if package.source.is_some() || !(is_member || all) {
+                continue;
+            }
+            let dir = package
+                .manifest_path
+                .parent()
+                .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+            nodes.insert(
+                dir.to_owned(),
+                GraphNode {
+                    name: package.name.clone(),
+                    version: Some(package.version.to_string()),
+                    manifest_path: package.manifest_path.clone(),
+                    status: if is_member {
+                        PackageStatus::Member
+                    } else {
+                        status(dir)
+                    },
+                },
+            );
+            dirs.insert(&package.id, dir);
+        }
+        for node in &resolve.nodes {
+            let Some(&from) = dirs.get(&node.id) else {
+                continue;
+            };
+            for dep in &node.deps {
+                let Some(&to) = dirs.get(&dep.pkg) else {
+                    continue;
+                };
+                for dep_kind in &dep.dep_kinds {
+                    let kind = match dep_kind.kind {
+                        DependencyKind::Normal => EdgeKind::Normal,
+                        DependencyKind::Development => EdgeKind::Dev,
+                        DependencyKind::Build => EdgeKind::Build,
+                        _ => continue,
+                    };
+                    edges.insert((from.to_owned(), to.to_owned(), kind));
+                }
             }
         }
 
-        stderr.status("Copying", format!("`{}` to `{}`", src, dst))?;
-
-        let src_root = src;
-        for src in WalkBuilder::new(&src_root).hidden(false).build() {
-            match src {
-                Ok(src) => {
-                    let src = Path::from_path(src.path()).expect("not a valid utf-8 path");
-                    if !(src.is_dir()
-                        || src == src_root.join("Cargo.toml")
-                        || src.starts_with(src_root.join(".git")))
-                    {
-                        let dst = dst.join(src.strip_prefix(&src_root)?);
-                        if let Some(parent) = dst.parent() {
-                            if !parent.exists() {
-                                crate::fs::create_dir_all(parent, dry_run)?;
+        if all {
+            let target_directory = metadata.target_directory.clone();
+            let walk = WalkBuilder::new(workspace_root)
+                .filter_entry(move |e| e.path() != target_directory)
+                .build();
+            let mut manifests = vec![];
+            for entry in walk {
+                match entry {
+                    Ok(entry) => {
+                        if entry.path().ends_with("Cargo.toml") {
+                            let manifest_path = <&Path>::try_from(entry.path())?;
+                            let dir = manifest_path.parent().expect("should not empty");
+                            if !nodes.contains_key(dir) {
+                                manifests.push(manifest_path.to_owned());
                             }
                         }
-                        crate::fs::copy(src, dst, dry_run)?;
                     }
+                    Err(err) => stderr.warn(err)?,
                 }
-                Err(err) => stderr.warn(err)?,
             }
-        }
 
-        crate::fs::write(dst.join("Cargo.toml"), cargo_toml.to_string(), dry_run)?;
+            let mut cargo_tomls = vec![];
+            for manifest_path in manifests {
+                let cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+                let name = cargo_toml
+                    .get("package")
+                    .and_then(|p| p.get("name"))
+                    .and_then(toml_edit::Item::as_str);
+                let Some(name) = name else {
+                    continue;
+                };
+                let dir = manifest_path.parent().expect("should not empty").to_owned();
+                nodes.insert(
+                    dir.clone(),
+                    GraphNode {
+                        name: name.to_owned(),
+                        version: crate::manifest::package_version(&cargo_toml, &root_cargo_toml),
+                        status: status(&dir),
+                        manifest_path,
+                    },
+                );
+                cargo_tomls.push((dir, cargo_toml));
+            }
 
-        if let [_, dst_workspace_root] = &*dst
-            .ancestors()
-            .filter(|d| d.join("Cargo.toml").exists())
-            .collect::<Vec<_>>()
-        {
-            stderr.status_with_color(
-                "Found",
-                format!("workspace at {}", dst_workspace_root),
-                termcolor::Color::Cyan,
-            )?;
+            let workspace_dependencies =
+                crate::manifest::workspace_table(&root_cargo_toml, "dependencies");
+            for (dir, cargo_toml) in &cargo_tomls {
+                crate::manifest::for_each_dependency(cargo_toml, |kind, name, dep| {
+                    let path = if crate::manifest::is_inherited(dep) {
+                        workspace_dependencies
+                            .and_then(|deps| deps.get(name)?.get("path")?.as_str())
+                            .map(|path| workspace_root.join(path))
+                    } else {
+                        dep.get("path")
+                            .and_then(toml_edit::Item::as_str)
+                            .map(|path| dir.join(path))
+                    };
+                    let Some(path) = path.map(|p| crate::manifest::normalize(&p)) else {
+                        return;
+                    };
+                    if nodes.contains_key(&path) {
+                        let kind = match kind {
+                            "dev-dependencies" => EdgeKind::Dev,
+                            "build-dependencies" => EdgeKind::Build,
+                            _ => EdgeKind::Normal,
+                        };
+                        edges.insert((dir.clone(), path, kind));
+                    }
+                });
+            }
+        }
 
-            modify_members(
-                dst_workspace_root,
-                &[&dst],
-                &[],
-                &[],
-                &[&dst],
-                dry_run,
-                &mut stderr,
-            )?;
+        if let Some(focus) = &focus {
+            let package = if all {
+                metadata
+                    .query_for_package(focus, |p| p.source.is_none())?
+                    .with_context(|| format!("package `{}` is not a path package", focus))?
+            } else {
+                metadata.query_for_member(focus)?
+            };
+            let start = package
+                .manifest_path
+                .parent()
+                .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+            let mut distances = HashMap::from([(start.to_owned(), 0)]);
+            let mut queue = VecDeque::from([start.to_owned()]);
+            while let Some(dir) = queue.pop_front() {
+                let distance = distances[&dir];
+                if distance == depth {
+                    continue;
+                }
+                let neighbors = edges.iter().flat_map(|(from, to, _)| {
+                    if *from == dir {
+                        Some(to)
+                    } else if *to == dir {
+                        Some(from)
+                    } else {
+                        None
+                    }
+                });
+                for neighbor in neighbors {
+                    if !distances.contains_key(neighbor) {
+                        distances.insert(neighbor.clone(), distance + 1);
+                        queue.push_back(neighbor.clone());
+                    }
+                }
+            }
+            nodes.retain(|dir, _| distances.contains_key(dir));
+            edges.retain(|(from, to, _)| nodes.contains_key(from) && nodes.contains_key(to));
         }
 
-        if dry_run {
-            stderr.warn("not copying due to dry run")?;
+        let mut nodes = nodes.into_iter().collect::<Vec<_>>();
+        nodes.sort_by(|(_, a), (_, b)| {
+            (&a.name, &a.manifest_path).cmp(&(&b.name, &b.manifest_path))
+        });
+        let indices = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, (dir, _))| (dir, i))
+            .collect::<HashMap<_, _>>();
+        let nodes = nodes.iter().map(|(_, node)| node).collect::<Vec<_>>();
+        let edges = edges
+            .iter()
+            .map(|(from, to, kind)| (indices[from], indices[to], *kind))
+            .sorted()
+            .collect::<Vec<_>>();
+
+        match format {
+            GraphFormat::Dot => {
+                writeln!(stdout, "digraph {{")?;
+                for (i, node) in nodes.iter().enumerate() {
+                    write!(stdout, "    n{} [label={:?}", i, node.name)?;
+                    match node.status {
+                        PackageStatus::Excluded => write!(stdout, ", style=dashed")?,
+                        PackageStatus::Inactive => write!(stdout, ", style=dotted")?,
+                        _ => {}
+                    }
+                    writeln!(stdout, "];")?;
+                }
+                for (from, to, kind) in &edges {
+                    write!(stdout, "    n{} -> n{}", from, to)?;
+                    let style = match kind {
+                        EdgeKind::Normal => None,
+                        EdgeKind::Build => Some("bold"),
+                        EdgeKind::Dev => Some("dashed"),
+                    };
+                    if let Some(style) = style {
+                        let label = <&str>::from(*kind);
+                        write!(stdout, r#" [style={}, label="{}"]"#, style, label)?;
+                    }
+                    writeln!(stdout, ";")?;
+                }
+                writeln!(stdout, "}}")?;
+            }
+            GraphFormat::Mermaid => {
+                writeln!(stdout, "flowchart TD")?;
+                for (i, node) in nodes.iter().enumerate() {
+                    write!(stdout, "    n{}[{:?}]", i, node.name)?;
+                    if node.status != PackageStatus::Member {
+                        write!(stdout, ":::{}", <&str>::from(node.status))?;
+                    }
+                    writeln!(stdout)?;
+                }
+                for (from, to, kind) in &edges {
+                    let arrow = match *kind {
+                        EdgeKind::Dev => "-.->|dev|",
+                        EdgeKind::Build => "==>|build|",
+                        EdgeKind::Normal => "-->",
+                    };
+                    writeln!(stdout, "    n{} {} n{}", from, arrow, to)?;
+                }
+                for (status, style) in [
+                    (PackageStatus::Excluded, "stroke-dasharray: 5 5"),
+                    (PackageStatus::Inactive, "stroke-dasharray: 2 2"),
+                ] {
+                    if nodes.iter().any(|n| n.status == status) {
+                        writeln!(stdout, "    classDef {} {}", <&str>::from(status), style)?;
+                    }
+                }
+            }
+            GraphFormat::Json => {
+                let nodes = nodes
+                    .iter()
+                    .map(|node| {
+                        serde_json::json!({
+                            "name": node.name,
+                            "version": node.version,
+                            "manifest_path": node.manifest_path,
+                            "status": <&str>::from(node.status),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let edges = edges
+                    .iter()
+                    .map(|(from, to, kind)| {
+                        let kind = <&str>::from(kind);
+                        serde_json::json!({ "from": from, "to": to, "kind": kind })
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::to_writer(
+                    &mut stdout,
+                    &serde_json::json!({ "nodes": nodes, "edges": edges }),
+                )?;
+                writeln!(stdout)?;
+            }
         }
+        stdout.flush()?;
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct Rm<W> {
-    workspace_root: anyhow::Result<PathBuf>,
-    paths: anyhow::Result<Vec<PathBuf>>,
-    force: bool,
+struct GraphNode {
+    name: String,
+    version: Option<String>,
+    manifest_path: PathBuf,
+    status: PackageStatus,
+}
+
+/// How a package relates to the workspace, as printed by [`Graph`] and [`Which`].
+#[derive(IntoStaticStr, Clone, Copy, PartialEq, Eq, Debug)]
+#[strum(serialize_all = "kebab-case")]
+enum PackageStatus {
+    Member,
+    Root,
+    Excluded,
+    Inactive,
+}
+
+/// Kind of a dependency edge in [`Graph`], in the order the edges are printed.
+#[derive(IntoStaticStr, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[strum(serialize_all = "kebab-case")]
+enum EdgeKind {
+    Normal,
+    Build,
+    Dev,
+}
+
+/// How to change a version.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Bump {
+    Major,
+    Minor,
+    Patch,
+    Exact(semver::Version),
+}
+
+impl Bump {
+    fn apply(&self, version: &semver::Version) -> semver::Version {
+        match self {
+            Self::Major => semver::Version::new(version.major + 1, 0, 0),
+            Self::Minor => semver::Version::new(version.major, version.minor + 1, 0),
+            Self::Patch if version.pre.is_empty() => {
+                semver::Version::new(version.major, version.minor, version.patch + 1)
+            }
+            Self::Patch => semver::Version::new(version.major, version.minor, version.patch),
+            Self::Exact(version) => version.clone(),
+        }
+    }
+}
+
+impl str::FromStr for Bump {
+    type Err = semver::Error;
+
+    fn from_str(s: &str) -> Result<Self, semver::Error> {
+        match s {
+            "major" => Ok(Self::Major),
+            "minor" => Ok(Self::Minor),
+            "patch" => Ok(Self::Patch),
+            s => s.parse().map(Self::Exact),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Version<'a, W> {
+    metadata: &'a Metadata,
+    specs: Vec<String>,
+    ids: Vec<PackageId>,
+    all: bool,
+    bump: Bump,
+    cascade: bool,
     dry_run: bool,
     stderr: W,
 }
 
-impl Rm<NoColor<Sink>> {
-    pub fn new<Ps: IntoIterator<Item = P>, P: AsRef<Path>>(
-        workspace_root: &Path,
-        paths: Ps,
+impl<'a> Version<'a, NoColor<Sink>> {
+    pub fn from_metadata<Ss: IntoIterator<Item = S>, S: AsRef<str>>(
+        metadata: &'a Metadata,
+        specs: Ss,
+        bump: Bump,
     ) -> Self {
         Self {
-            workspace_root: ensure_absolute(workspace_root),
-            paths: paths.into_iter().map(ensure_absolute).collect(),
-            force: false,
+            metadata,
+            specs: specs.into_iter().map(|s| s.as_ref().to_owned()).collect(),
+            ids: vec![],
+            all: false,
+            bump,
+            cascade: false,
             dry_run: false,
             stderr: NoColor::new(io::sink()),
         }
     }
 
-    pub fn from_metadata<
-        Ps: IntoIterator<Item = P>,
-        P: AsRef<Path>,
-        Ss: IntoIterator<Item = S>,
-        S: AsRef<str>,
-    >(
-        metadata: &Metadata,
-        paths: Ps,
-        specs: Ss,
+    /// Bumps the members by their IDs, such as the ones resolved by a [`Selection`].
+    pub fn from_ids<'b>(
+        metadata: &'a Metadata,
+        ids: impl IntoIterator<Item = &'b PackageId>,
+        bump: Bump,
     ) -> Self {
         Self {
-            workspace_root: Ok(metadata.workspace_root.clone()),
-            paths: paths
-                .into_iter()
-                .map(ensure_absolute)
-                .chain(specs.into_iter().map(|spec| {
-                    let member = metadata.query_for_member(Some(spec.as_ref()))?;
-                    Ok(member
-                        .manifest_path
-                        .parent()
-                        .expect(r#"`manifest_path` should end with "Cargo.toml""#)
-                        .to_owned())
-                }))
-                .collect(),
-            force: false,
+            metadata,
+            specs: vec![],
+            ids: ids.into_iter().cloned().collect(),
+            all: false,
+            bump,
+            cascade: false,
             dry_run: false,
             stderr: NoColor::new(io::sink()),
         }
     }
 }
 
-impl<W: WriteColor> Rm<W> {
-    pub fn force(self, force: bool) -> Self {
-        Self { force, ..self }
+impl<'a, W: WriteColor> Version<'a, W> {
+    pub fn all(self, all: bool) -> Self {
+        Self { all, ..self }
+    }
+
+    /// Also bumps the members that depend on the bumped ones with normal or build dependencies,
+    /// by the same level. Bumps them by a patch level if the new version is given explicitly.
+    pub fn cascade(self, cascade: bool) -> Self {
+        Self { cascade, ..self }
     }
 
     pub fn dry_run(self, dry_run: bool) -> Self {
         Self { dry_run, ..self }
     }
 
-    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Rm<W2> {
-        Rm {
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Version<'a, W2> {
+        Version {
             stderr,
-            workspace_root: self.workspace_root,
-            paths: self.paths,
-            force: self.force,
+            metadata: self.metadata,
+            specs: self.specs,
+            ids: self.ids,
+            all: self.all,
+            bump: self.bump,
+            cascade: self.cascade,
             dry_run: self.dry_run,
         }
     }
@@ -888,147 +6100,426 @@ impl<W: WriteColor> Rm<W> {
     pub fn exec(self) -> anyhow::Result<()> {
         let Self {
             mut stderr,
-            workspace_root,
-            paths,
-            force,
+            metadata,
+            specs,
+            ids,
+            all,
+            bump,
+            cascade,
             dry_run,
         } = self;
 
-        let (workspace_root, paths) = (workspace_root?, paths?);
+        let workspace_root = &metadata.workspace_root;
+        let root_manifest_path = workspace_root.join("Cargo.toml");
 
-        let modified = paths.iter().try_fold(false, |acc, path| {
-            if !(force || path.join("Cargo.toml").exists()) {
-                return Err(
-                    anyhow!("`{}` does not exist", path.join("Cargo.toml")).context(format!(
-                        "`{}` does not seem to be a package. enable `--force` to remove",
-                        path,
-                    )),
+        let members = metadata
+            .packages
+            .iter()
+            .filter(|p| metadata.workspace_members.contains(&p.id))
+            .collect::<Vec<_>>();
+        let mut targets = if all {
+            members.clone()
+        } else {
+            ensure!(
+                !specs.is_empty() || !ids.is_empty(),
+                "no packages are specified",
+            );
+            let mut targets = specs
+                .iter()
+                .map(|spec| metadata.query_for_member(spec))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            for id in &ids {
+                ensure!(
+                    metadata.workspace_members.contains(id),
+                    "`{}` is not a member of the workspace",
+                    id,
                 );
+                targets.push(&metadata[id]);
             }
-            stderr.status_with_color(
-                "Removing",
-                format!("directory `{}`", path),
-                termcolor::Color::Red,
+            targets
+        };
+
+        let mut docs = iter::once(root_manifest_path.clone())
+            .chain(members.iter().map(|p| p.manifest_path.clone()))
+            .unique()
+            .map(|path| {
+                let doc = crate::fs::read_toml_edit(&path)?;
+                Ok((path, (doc.to_string(), doc)))
+            })
+            .collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+
+        let inheriting = members
+            .iter()
+            .filter(|p| {
+                docs[&p.manifest_path]
+                    .1
+                    .get("package")
+                    .and_then(|p| p.get("version"))
+                    .is_some_and(crate::manifest::is_inherited)
+            })
+            .map(|p| &p.id)
+            .collect::<HashSet<_>>();
+        let inherits_version = |package: &Package| inheriting.contains(&package.id);
+
+        // `(package, bump)`s. The packages that inherit `workspace.package.version` are bumped
+        // together.
+        let mut bumped = Vec::<(&Package, Bump)>::new();
+        let add = |package: &'a Package, bump: Bump, bumped: &mut Vec<(&'a Package, Bump)>| {
+            if bumped.iter().any(|(p, _)| p.id == package.id) {
+                return;
+            }
+            if inherits_version(package) {
+                for &member in &members {
+                    if inherits_version(member) && !bumped.iter().any(|(p, _)| p.id == member.id) {
+                        bumped.push((member, bump.clone()));
+                    }
+                }
+            } else {
+                bumped.push((package, bump));
+            }
+        };
+        for target in targets.drain(..) {
+            add(target, bump.clone(), &mut bumped);
+        }
+
+        if cascade {
+            let cascaded_bump = match &bump {
+                Bump::Exact(_) => Bump::Patch,
+                bump => bump.clone(),
+            };
+            loop {
+                let dirs = bumped
+                    .iter()
+                    .flat_map(|(p, _)| p.manifest_path.parent())
+                    .collect::<HashSet<_>>();
+                let dependent = members.iter().copied().find(|member| {
+                    !bumped.iter().any(|(p, _)| p.id == member.id)
+                        && member.dependencies.iter().any(|d| {
+                            d.kind != DependencyKind::Development
+                                && d.path.as_deref().is_some_and(|p| dirs.contains(p))
+                        })
+                });
+                match dependent {
+                    Some(dependent) => add(dependent, cascaded_bump.clone(), &mut bumped),
+                    None => break,
+                }
+            }
+        }
+
+        let mut new_versions = HashMap::new();
+        let mut workspace_version = None;
+        for (package, bump) in &bumped {
+            let new_version = bump.apply(&package.version);
+            stderr.status(
+                "Bumping",
+                format!(
+                    "`{}` from {} to {}",
+                    package.name, package.version, new_version,
+                ),
             )?;
-            crate::fs::remove_dir_all(path, dry_run)?;
-            modify_members(
-                &workspace_root,
-                &[],
-                &[],
-                &[path],
-                &[path],
-                dry_run,
-                &mut stderr,
-            )
-            .map(|p| acc | p)
-        })?;
+            if inherits_version(package) {
+                workspace_version = Some(new_version.clone());
+            } else {
+                let doc = &mut docs.get_mut(&package.manifest_path).expect("loaded").1;
+                set_version_str(&mut doc["package"]["version"], &new_version.to_string());
+            }
+            let dir = package
+                .manifest_path
+                .parent()
+                .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                .to_owned();
+            new_versions.insert(dir, (package.name.clone(), new_version));
+        }
+        if let Some(workspace_version) = workspace_version {
+            let root = &mut docs.get_mut(&root_manifest_path).expect("loaded").1;
+            set_version_str(
+                &mut root["workspace"]["package"]["version"],
+                &workspace_version.to_string(),
+            );
+        }
 
-        if !modified {
-            stderr.warn("`workspace` unchanged")?;
+        for (manifest_path, (_, doc)) in &mut docs {
+            let dir = manifest_path.parent().expect("should end with Cargo.toml");
+            let mut update = |name: &str, dep: &mut toml_edit::Item, base: &Path| {
+                if crate::manifest::is_inherited(dep) {
+                    return Ok(());
+                }
+                let Some(path) = dep.get("path").and_then(toml_edit::Item::as_str) else {
+                    return Ok(());
+                };
+                let Some((_, new_version)) =
+                    new_versions.get(&crate::manifest::normalize(&base.join(path)))
+                else {
+                    return Ok(());
+                };
+                let Some(req) = dep.get("version").and_then(toml_edit::Item::as_str) else {
+                    return Ok(());
+                };
+                let new_req = bump_requirement(req, new_version);
+                if new_req != req {
+                    stderr.status(
+                        "Updating",
+                        format!(
+                            "requirement on {:?} from {:?} to {:?} in {}",
+                            name, req, new_req, manifest_path,
+                        ),
+                    )?;
+                    set_version_str(&mut dep["version"], &new_req);
+                }
+                anyhow::Ok(())
+            };
+            crate::manifest::for_each_dependency_mut(doc, |name, dep| update(name, dep, dir))?;
+            if let Some(deps) = doc
+                .get_mut("workspace")
+                .and_then(|w| w.get_mut("dependencies"))
+                .and_then(toml_edit::Item::as_table_like_mut)
+            {
+                for (name, dep) in deps.iter_mut() {
+                    update(name.get(), dep, dir)?;
+                }
+            }
+        }
+
+        for (manifest_path, (orig, doc)) in &docs {
+            let doc = doc.to_string();
+            if doc != *orig {
+                crate::fs::write(manifest_path, doc, dry_run)?;
+            }
         }
 
         if dry_run {
-            stderr.warn("not modifying the manifest due to dry run")?;
-        } else if !is_empty_workspace(&workspace_root.join("Cargo.toml"))? {
+            stderr.warn("not bumping due to dry run")?;
+        } else {
             stderr.status("Updating", workspace_root.join("Cargo.lock"))?;
-
             cargo_metadata(
-                Some(&workspace_root.join("Cargo.toml")),
-                false,
+                Some(&root_manifest_path),
                 false,
                 false,
-                &workspace_root,
+                true,
+                workspace_root,
             )?;
         }
-        Ok(())
-    }
-}
-
-#[derive(Debug)]
-pub struct Mv<W> {
-    workspace_root: anyhow::Result<PathBuf>,
-    src: anyhow::Result<PathBuf>,
-    dst: anyhow::Result<PathBuf>,
-    dry_run: bool,
-    no_rename: bool,
-    stderr: W,
-}
-
-impl Mv<NoColor<Sink>> {
-    pub fn new(workspace_root: &Path, src: &Path, dst: &Path) -> Self {
-        Self {
-            workspace_root: ensure_absolute(workspace_root),
-            src: ensure_absolute(src),
-            dst: ensure_absolute(dst),
-            dry_run: false,
-            no_rename: false,
-            stderr: NoColor::new(io::sink()),
-        }
-    }
-
-    pub fn from_metadata(metadata: &Metadata, src: &str, dst: &Path) -> Self {
-        Self {
-            workspace_root: Ok(metadata.workspace_root.clone()),
-            src: metadata.query_for_member(Some(src)).map(|member| {
-                member
-                    .manifest_path
-                    .parent()
-                    .expect(r#"`manifest_path` should end with "Cargo.toml""#)
-                    .to_owned()
-            }),
-            dst: ensure_absolute(dst),
-            dry_run: false,
-            no_rename: false,
-            stderr: NoColor::new(io::sink()),
+        return Ok(());
+
+        fn set_version_str(item: &mut toml_edit::Item, version: &str) {
+            let decor = item.as_value().map(|v| v.decor().clone());
+            *item = toml_edit::value(version);
+            if let (Some(decor), Some(value)) = (decor, item.as_value_mut()) {
+                *value.decor_mut() = decor;
+            }
+        }
+
+        /// Keeps the operator and the precision of a simple requirement such as `"0.3"` or
+        /// `"=0.3.1"`. Replaces the others with the new version.
+        fn bump_requirement(req: &str, new_version: &semver::Version) -> String {
+            let op_len = req
+                .find(|c: char| c.is_ascii_digit())
+                .filter(|_| !req.contains(','))
+                .unwrap_or(0);
+            let (op, version) = req.split_at(op_len);
+            if !["", "^", "=", "~", ">="].contains(&op.trim())
+                || !version.chars().all(|c| c.is_ascii_digit() || c == '.')
+            {
+                return new_version.to_string();
+            }
+            match version.split('.').count() {
+                1 => format!("{}{}", op, new_version.major),
+                2 => format!("{}{}.{}", op, new_version.major, new_version.minor),
+                _ => format!("{}{}", op, new_version),
+            }
         }
     }
 }
 
-impl<W: WriteColor> Mv<W> {
-    pub fn dry_run(self, dry_run: bool) -> Self {
-        Self { dry_run, ..self }
+fn dependency_table_name(kind: DependencyKind) -> anyhow::Result<&'static str> {
+    match kind {
+        DependencyKind::Normal => Ok("dependencies"),
+        DependencyKind::Development => Ok("dev-dependencies"),
+        DependencyKind::Build => Ok("build-dependencies"),
+        DependencyKind::Unknown => bail!("unknown dependency kind"),
     }
+}
 
-    pub fn no_rename(self, no_rename: bool) -> Self {
-        Self { no_rename, ..self }
+/// Removes `patch.<source>.<name>` from the root manifest, dropping the tables left empty.
+/// Looks through every source if `source` is `None`.
+fn remove_patch(
+    root_cargo_toml: &mut toml_edit::DocumentMut,
+    name: &str,
+    source: Option<&str>,
+) -> Option<(String, toml_edit::Item)> {
+    let patch = root_cargo_toml
+        .get_mut("patch")
+        .and_then(toml_edit::Item::as_table_like_mut)?;
+    let key = patch
+        .iter()
+        .map(|(key, _)| key.to_owned())
+        .filter(|key| source.is_none_or(|s| s == key))
+        .find(|key| patch.get(key).and_then(|s| s.get(name)).is_some())?;
+    let table = patch
+        .get_mut(&key)
+        .and_then(toml_edit::Item::as_table_like_mut)?;
+    let entry = table.remove(name)?;
+    if table.is_empty() {
+        patch.remove(&key);
     }
+    if patch.is_empty() {
+        root_cargo_toml.remove("patch");
+    }
+    Some((key, entry))
+}
 
-    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Mv<W2> {
-        Mv {
-            stderr,
-            workspace_root: self.workspace_root,
-            src: self.src,
-            dst: self.dst,
-            dry_run: self.dry_run,
-            no_rename: self.no_rename,
-        }
+/// The key of `[patch]` for a registry source.
+fn patch_key(source: &cargo_metadata::Source) -> String {
+    if source.is_crates_io() || source.repr == "sparse+https://index.crates.io/" {
+        "crates-io".to_owned()
+    } else {
+        source.repr.trim_start_matches("registry+").to_owned()
     }
+}
 
-    pub fn exec(self) -> anyhow::Result<()> {
-        let Self {
-            mut stderr,
-            workspace_root,
-            src,
-            dst,
-            dry_run,
-            no_rename,
-        } = self;
+/// The source of the packages from the registry named `name`, as written in `Cargo.lock`.
+///
+/// The index is looked up the way Cargo does: `$CARGO_REGISTRIES_<NAME>_INDEX`, then
+/// `registries.<name>.index` in the `.cargo/config.toml` files from `cwd` upwards and in
+/// `$CARGO_HOME`.
+fn registry_source(cwd: &Path, name: &str) -> anyhow::Result<String> {
+    let env_key = format!(
+        "CARGO_REGISTRIES_{}_INDEX",
+        name.to_uppercase().replace('-', "_"),
+    );
+    let index = match env::var(&env_key) {
+        Ok(index) => index,
+        Err(_) => {
+            let cargo_home = env::var("CARGO_HOME").map(PathBuf::from).ok().or_else(|| {
+                env::var("HOME")
+                    .ok()
+                    .map(|home| Path::new(&home).join(".cargo"))
+            });
+            let mut index = None;
+            for dir in cwd.ancestors().map(|d| d.join(".cargo")).chain(cargo_home) {
+                // Cargo prefers `config` when both exist.
+                let Some(path) = ["config", "config.toml"]
+                    .iter()
+                    .map(|file_name| dir.join(file_name))
+                    .find(|path| path.is_file())
+                else {
+                    continue;
+                };
+                let config = crate::fs::read_toml::<toml::Table, _>(&path)?;
+                if let Some(found) = config
+                    .get("registries")
+                    .and_then(|r| r.get(name))
+                    .and_then(|r| r.get("index"))
+                    .and_then(toml::Value::as_str)
+                {
+                    index = Some(found.to_owned());
+                    break;
+                }
+            }
+            index.with_context(|| {
+                format!(
+                    "could not find the index of registry `{}`; set `registries.{}.index` in \
+                     `.cargo/config.toml`",
+                    name, name,
+                )
+            })?
+        }
+    };
+    Ok(if index.starts_with("sparse+") {
+        index
+    } else {
+        format!("registry+{}", index)
+    })
+}
 
-        let (workspace_root, src, dst) = (workspace_root?, src?, dst?);
+/// Rewrites the non-inherited path dependencies of a package moved from `src` to `dst`.
+/// `map_path` tells where the other moved packages go.
+fn remap_path_dependencies(
+    cargo_toml: &mut toml_edit::DocumentMut,
+    src: &Path,
+    dst: &Path,
+    map_path: impl Fn(&Path) -> PathBuf,
+) -> anyhow::Result<()> {
+    crate::manifest::for_each_dependency_mut(cargo_toml, |_, dep| {
+        if crate::manifest::is_inherited(dep) {
+            return Ok(());
+        }
+        if let Some(path) = dep.get("path").and_then(toml_edit::Item::as_str) {
+            let target = map_path(&crate::manifest::normalize(&src.join(path)));
+            let path = crate::manifest::relative_path(dst, &target);
+            if let Some(dep) = dep.as_table_like_mut() {
+                if dep.get("path").and_then(toml_edit::Item::as_str) != Some(path.as_str()) {
+                    dep.insert(
+                        "path",
+                        toml_edit::value(crate::manifest::to_manifest_str(&path)),
+                    );
+                }
+            }
+        }
+        Ok(())
+    })
+}
 
-        Cp::new(&src, &dst)
-            .dry_run(dry_run)
-            .no_rename(no_rename)
-            .stderr(&mut stderr)
-            .exec()?;
+/// Copies the files of a package except `.git` and `skip`, which are relative to `src_root`.
+fn copy_package(
+    src_root: &Path,
+    dst: &Path,
+    skip: &[&str],
+    dry_run: bool,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    for src in WalkBuilder::new(src_root).hidden(false).build() {
+        match src {
+            Ok(src) => {
+                let src = Path::from_path(src.path()).expect("not a valid utf-8 path");
+                if !(src.is_dir()
+                    || skip.iter().any(|&s| src == src_root.join(s))
+                    || src.starts_with(src_root.join(".git")))
+                {
+                    let dst = dst.join(src.strip_prefix(src_root)?);
+                    if let Some(parent) = dst.parent() {
+                        if !parent.exists() {
+                            crate::fs::create_dir_all(parent, dry_run)?;
+                        }
+                    }
+                    crate::fs::copy(src, dst, dry_run)?;
+                }
+            }
+            Err(err) => stderr.warn(err)?,
+        }
+    }
+    Ok(())
+}
 
-        Rm::new(&workspace_root, [src])
-            .dry_run(dry_run)
-            .stderr(stderr)
-            .exec()
+/// Lists `path` and its ancestors that contain a `Cargo.toml`, nearest first.
+/// Prepends `prefix` to a package name derived from a directory name, unless it already starts
+/// with it.
+fn prefixed_name(prefix: Option<&str>, name: &str) -> String {
+    match prefix {
+        Some(prefix) if !name.starts_with(prefix) => format!("{}{}", prefix, name),
+        _ => name.to_owned(),
     }
 }
 
+fn manifest_dirs(path: &Path) -> impl Iterator<Item = &Path> {
+    path.ancestors().filter(|d| d.join("Cargo.toml").exists())
+}
+
+fn excluded_dirs(workspace_root: &Path, root_cargo_toml: &toml_edit::DocumentMut) -> Vec<PathBuf> {
+    root_cargo_toml
+        .get("workspace")
+        .and_then(|w| w.get("exclude"))
+        .and_then(toml_edit::Item::as_array)
+        .map(|exclude| {
+            exclude
+                .iter()
+                .flat_map(|s| s.as_str())
+                .map(|s| crate::manifest::normalize(&workspace_root.join(s)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 fn ensure_absolute(path: impl AsRef<Path>) -> anyhow::Result<PathBuf> {
     let path = path.as_ref();
     ensure!(path.is_absolute(), "must be absolute: {}", path);
@@ -1038,7 +6529,7 @@ fn ensure_absolute(path: impl AsRef<Path>) -> anyhow::Result<PathBuf> {
 fn is_empty_workspace(manifest_path: &Path) -> anyhow::Result<bool> {
     return {
         let CargoToml { workspace, package } = crate::fs::read_toml(manifest_path)?;
-        Ok(!workspace.members.is_empty() || package.is_some())
+        Ok(workspace.members.is_empty() && package.is_none())
     };
 
     #[derive(Deserialize)]
@@ -1082,6 +6573,280 @@ fn cargo_metadata(
     Ok(metadata)
 }
 
+fn update_lockfile(
+    workspace_root: &Path,
+    lock_mode: LockMode,
+    offline: bool,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    if lock_mode == LockMode::Skip {
+        return Ok(());
+    }
+
+    let manifest_path = workspace_root.join("Cargo.toml");
+    let lock_path = workspace_root.join("Cargo.lock");
+    stderr.status("Updating", &lock_path)?;
+
+    if lock_mode == LockMode::Edit && lock_path.exists() {
+        let orig = crate::fs::read_to_string(&lock_path)?;
+        let edit = || -> anyhow::Result<()> {
+            let members = MetadataCommand::new()
+                .manifest_path(&manifest_path)
+                .no_deps()
+                .other_options(vec!["--offline".to_owned()])
+                .current_dir(workspace_root)
+                .exec()?
+                .workspace_packages()
+                .into_iter()
+                .cloned()
+                .collect::<Vec<_>>();
+            let lock = crate::lockfile::edit(&orig, &members)?;
+            crate::fs::write(&lock_path, lock, false)?;
+            cargo_metadata(Some(&manifest_path), false, true, true, workspace_root)?;
+            Ok(())
+        };
+        match edit() {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                stderr.warn(format!(
+                    "could not edit {} in place: {:#}. resolving the dependencies instead",
+                    lock_path, err,
+                ))?;
+                crate::fs::write(&lock_path, orig, false)?;
+            }
+        }
+    }
+
+    cargo_metadata(Some(&manifest_path), false, false, offline, workspace_root)?;
+    Ok(())
+}
+
+/// Lists the files in the working tree that differ from `since`, including the untracked ones.
+/// Only the files under `workspace_root` are listed.
+fn git_changed_files(workspace_root: &Path, since: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let git = |args: &[&str]| -> anyhow::Result<Vec<PathBuf>> {
+        let output = duct::cmd("git", args)
+            .dir(workspace_root)
+            .stdout_capture()
+            .stderr_capture()
+            .unchecked()
+            .run()
+            .with_context(|| "failed to run `git`")?;
+        let stderr = str::from_utf8(&output.stderr)?.trim_end();
+        if !output.status.success() {
+            bail!("{}", stderr.trim_start_matches("fatal: "));
+        }
+        Ok(str::from_utf8(&output.stdout)?
+            .split('\0')
+            .filter(|s| !s.is_empty())
+            .map(|s| workspace_root.join(s))
+            .collect())
+    };
+
+    let mut files = git(&[
+        "diff",
+        "--name-only",
+        "--no-renames",
+        "--relative",
+        "-z",
+        since,
+        "--",
+    ])?;
+    files.extend(git(&["ls-files", "--others", "--exclude-standard", "-z"])?);
+    Ok(files)
+}
+
+/// Reads a package with `cargo metadata --no-deps`, which resolves workspace inheritance.
+fn read_package(manifest_path: &Path) -> anyhow::Result<Package> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .map_err(|err| match err {
+            cargo_metadata::Error::CargoMetadata { stderr } => anyhow!("{}", stderr.trim_end()),
+            err => err.into(),
+        })?;
+    metadata
+        .packages
+        .into_iter()
+        .find(|p| p.manifest_path == manifest_path)
+        .with_context(|| format!("`{}` is not a package", manifest_path))
+}
+
+#[derive(Debug)]
+struct AbsorbedWorkspace {
+    is_package: bool,
+    members: Vec<PathBuf>,
+    exclude: Vec<PathBuf>,
+}
+
+fn absorb_workspace(
+    root_manifest: &mut RootManifest,
+    path: &Path,
+    dry_run: bool,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<Option<AbsorbedWorkspace>> {
+    let manifest_path = path.join("Cargo.toml");
+    let mut cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+
+    let workspace = match cargo_toml.remove("workspace") {
+        Some(toml_edit::Item::Table(workspace)) => workspace,
+        Some(_) => bail!("`workspace` in {} must be a table", manifest_path),
+        None => return Ok(None),
+    };
+
+    stderr.status_with_color(
+        "Absorbing",
+        format!("workspace at {}", path),
+        termcolor::Color::Cyan,
+    )?;
+
+    let paths = |field: &str| -> anyhow::Result<Vec<PathBuf>> {
+        let array = match workspace.get(field) {
+            Some(array) => array
+                .as_array()
+                .with_context(|| format!("`workspace.{}` must be an array", field))?,
+            None => return Ok(vec![]),
+        };
+        array
+            .iter()
+            .map(|p| {
+                let p = p.as_str().with_context(|| {
+                    format!("`workspace.{}` must be an array of strings", field)
+                })?;
+                Ok(crate::manifest::normalize(&path.join(p)))
+            })
+            .filter(|p| p.as_ref().map_or(true, |p| p != path))
+            .collect()
+    };
+    let members = paths("members")?;
+    let exclude = paths("exclude")?;
+
+    if workspace.contains_key("default-members") {
+        stderr.warn(format!(
+            "ignoring `workspace.default-members` in {}",
+            manifest_path,
+        ))?;
+    }
+
+    let workspace_root = &*root_manifest.possibly_empty_workspace_root;
+    merge_workspace_tables(
+        &mut root_manifest.cargo_toml,
+        &workspace_root.join("Cargo.toml"),
+        &workspace,
+        |p| crate::manifest::rebase(path, workspace_root, p),
+        &mut stderr,
+    )?;
+
+    let is_package = cargo_toml.contains_key("package");
+    if is_package {
+        stderr.status_with_color(
+            "Removing",
+            format!("`workspace` from {}", manifest_path),
+            termcolor::Color::Red,
+        )?;
+        crate::fs::write(&manifest_path, cargo_toml.to_string(), dry_run)?;
+    } else {
+        for (key, _) in cargo_toml.iter() {
+            stderr.warn(format!("dropping `{}` in {}", key, manifest_path))?;
+        }
+        stderr.status_with_color(
+            "Removing",
+            format!("virtual manifest {}", manifest_path),
+            termcolor::Color::Red,
+        )?;
+        crate::fs::remove_file(&manifest_path, dry_run)?;
+    }
+
+    let lock_path = path.join("Cargo.lock");
+    if lock_path.exists() {
+        stderr.status_with_color("Removing", &lock_path, termcolor::Color::Red)?;
+        crate::fs::remove_file(&lock_path, dry_run)?;
+    }
+
+    Ok(Some(AbsorbedWorkspace {
+        is_package,
+        members,
+        exclude,
+    }))
+}
+
+#[derive(Default, Debug)]
+struct WorkspaceConflicts {
+    package: Vec<String>,
+    dependencies: Vec<String>,
+    lints: bool,
+}
+
+/// Merges `workspace.{package, dependencies, lints}` of another workspace into the root manifest.
+/// `map_path` converts a path relative to the other root into one relative to this root.
+fn merge_workspace_tables(
+    root_cargo_toml: &mut toml_edit::DocumentMut,
+    root_manifest_path: &Path,
+    workspace: &toml_edit::Table,
+    map_path: impl Fn(&str) -> String,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<WorkspaceConflicts> {
+    let mut merge = |field: String, src: &toml_edit::Table, dst: &mut toml_edit::Item| {
+        let dst = dst
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+            .with_context(|| format!("`workspace.{}` must be a table", field))?;
+        let merged = crate::manifest::merge_table(dst, src);
+        for key in &merged.added {
+            stderr.status("Merging", format!("{:?} into `workspace.{}`", key, field))?;
+        }
+        for key in &merged.conflicts {
+            stderr.warn(format!(
+                "`workspace.{}.{}` conflicts. keeping the one in {}",
+                field, key, root_manifest_path,
+            ))?;
+        }
+        anyhow::Ok(merged.conflicts)
+    };
+
+    let mut conflicts = WorkspaceConflicts::default();
+    for field in ["package", "dependencies", "lints"] {
+        let src = match workspace.get(field) {
+            Some(src) => src
+                .as_table()
+                .with_context(|| format!("`workspace.{}` must be a table", field))?,
+            None => continue,
+        };
+        let dst = &mut root_cargo_toml["workspace"][field];
+        match field {
+            "package" => {
+                let mut src = src.clone();
+                for key in ["readme", "license-file"] {
+                    if let Some(s) = src.get(key).and_then(toml_edit::Item::as_str) {
+                        src[key] = toml_edit::value(map_path(s));
+                    }
+                }
+                conflicts.package = merge(field.to_owned(), &src, dst)?;
+            }
+            "dependencies" => {
+                let mut src = src.clone();
+                for (_, dep) in src.iter_mut() {
+                    if let Some(path) = dep.get("path").and_then(toml_edit::Item::as_str) {
+                        dep["path"] = toml_edit::value(map_path(path));
+                    }
+                }
+                conflicts.dependencies = merge(field.to_owned(), &src, dst)?;
+            }
+            _ => {
+                for (tool, src) in src.iter() {
+                    let src = src
+                        .as_table()
+                        .with_context(|| format!("`workspace.lints.{}` must be a table", tool))?;
+                    let tool_conflicts = merge(format!("lints.{}", tool), src, &mut dst[tool])?;
+                    conflicts.lints |= !tool_conflicts.is_empty();
+                }
+            }
+        }
+    }
+    Ok(conflicts)
+}
+
 fn modify_members<'a>(
     possibly_empty_workspace_root: &Path,
     add_to_workspace_members: &[&'a Path],
@@ -1089,68 +6854,139 @@ fn modify_members<'a>(
     rm_from_workspace_members: &[&'a Path],
     rm_from_workspace_exclude: &[&'a Path],
     dry_run: bool,
-    mut stderr: impl WriteColor,
+    stderr: impl WriteColor,
 ) -> anyhow::Result<bool> {
-    if [
+    let mut root_manifest = RootManifest::read(possibly_empty_workspace_root)?;
+    root_manifest.modify_members(
         add_to_workspace_members,
         add_to_workspace_exclude,
         rm_from_workspace_members,
         rm_from_workspace_exclude,
-    ]
-    .iter()
-    .copied()
-    .flatten()
-    .any(|&p| p == possibly_empty_workspace_root)
-    {
-        bail!("`{}` is the workspace root", possibly_empty_workspace_root);
+        dry_run,
+        stderr,
+    )?;
+    root_manifest.write(false, None, dry_run)
+}
+
+/// The root `Cargo.toml` kept in memory so that a batch of edits is written at once.
+#[derive(Debug)]
+struct RootManifest {
+    possibly_empty_workspace_root: PathBuf,
+    cargo_toml: toml_edit::DocumentMut,
+    orig: String,
+}
+
+impl RootManifest {
+    fn read(possibly_empty_workspace_root: &Path) -> anyhow::Result<Self> {
+        let cargo_toml =
+            crate::fs::read_toml_edit(possibly_empty_workspace_root.join("Cargo.toml"))?;
+        let orig = cargo_toml.to_string();
+        Ok(Self {
+            possibly_empty_workspace_root: possibly_empty_workspace_root.to_owned(),
+            cargo_toml,
+            orig,
+        })
     }
 
-    let manifest_path = possibly_empty_workspace_root.join("Cargo.toml");
-    let mut cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
-    let orig = cargo_toml.to_string();
+    fn modify_members<'a>(
+        &mut self,
+        add_to_workspace_members: &[&'a Path],
+        add_to_workspace_exclude: &[&'a Path],
+        rm_from_workspace_members: &[&'a Path],
+        rm_from_workspace_exclude: &[&'a Path],
+        dry_run: bool,
+        mut stderr: impl WriteColor,
+    ) -> anyhow::Result<()> {
+        let possibly_empty_workspace_root = &*self.possibly_empty_workspace_root;
+
+        if [
+            add_to_workspace_members,
+            add_to_workspace_exclude,
+            rm_from_workspace_members,
+            rm_from_workspace_exclude,
+        ]
+        .iter()
+        .copied()
+        .flatten()
+        .any(|&p| p == possibly_empty_workspace_root)
+        {
+            bail!("`{}` is the workspace root", possibly_empty_workspace_root);
+        }
 
-    for (field, add, rm) in &[
-        (
+        self.modify_array(
             "members",
             add_to_workspace_members,
             rm_from_workspace_members,
-        ),
-        (
+            dry_run,
+            &mut stderr,
+        )?;
+        self.modify_array(
             "exclude",
             add_to_workspace_exclude,
             rm_from_workspace_exclude,
-        ),
-    ] {
-        let relative_to_root = |path: &'a Path| -> _ {
+            dry_run,
+            &mut stderr,
+        )
+    }
+
+    fn modify_array(
+        &mut self,
+        field: &str,
+        add: &[&Path],
+        rm: &[&Path],
+        dry_run: bool,
+        mut stderr: impl WriteColor,
+    ) -> anyhow::Result<()> {
+        if add.is_empty() && rm.is_empty() {
+            return Ok(());
+        }
+
+        let possibly_empty_workspace_root = &*self.possibly_empty_workspace_root;
+
+        let relative_to_root = |path: &'_ Path| -> PathBuf {
             path.strip_prefix(possibly_empty_workspace_root)
                 .unwrap_or(path)
+                .to_owned()
         };
 
+        #[allow(clippy::unnecessary_map_or)]
         let same_paths = |value: &toml_edit::Value, target: &str| -> _ {
             value.as_str().map_or(false, |s| {
                 possibly_empty_workspace_root.join(s) == possibly_empty_workspace_root.join(target)
             })
         };
 
-        let array = cargo_toml["workspace"][field]
-            .or_insert(toml_edit::value(toml_edit::Array::default()))
+        let workspace = self
+            .cargo_toml
+            .entry("workspace")
+            .or_insert(toml_edit::table());
+        if let Some(workspace) = workspace.as_inline_table_mut() {
+            if !workspace.contains_key(field) {
+                crate::manifest::insert_inline(workspace, field, toml_edit::Array::new());
+            }
+        }
+        let array = workspace
+            .as_table_like_mut()
+            .context("`workspace` must be a table")?
+            .entry(field)
+            .or_insert(toml_edit::value(toml_edit::Array::new()))
             .as_array_mut()
             .with_context(|| format!("`workspace.{}` must be an array", field))?;
-        for add in *add {
-            let add = relative_to_root(add).as_str();
-            if array.iter().all(|m| !same_paths(m, add)) {
+        for add in add {
+            let add = relative_to_root(add);
+            if array.iter().all(|m| !same_paths(m, add.as_str())) {
                 if !dry_run {
-                    array.push(add);
+                    crate::manifest::push_entry(array, add.as_str());
                 }
                 stderr.status("Adding", format!("{:?} to `workspace.{}`", add, field))?;
             }
         }
-        for rm in *rm {
-            let rm = relative_to_root(rm).as_str();
-            let i = array.iter().position(|m| same_paths(m, rm));
+        for rm in rm {
+            let rm = relative_to_root(rm);
+            let i = array.iter().position(|m| same_paths(m, rm.as_str()));
             if let Some(i) = i {
                 if !dry_run {
-                    array.remove(i);
+                    crate::manifest::remove_entry(array, i);
                 }
                 stderr.status_with_color(
                     "Removing",
@@ -1159,16 +6995,55 @@ fn modify_members<'a>(
                 )?;
             }
         }
+        Ok(())
+    }
+
+    /// Sorts, deduplicates, and lays out `workspace.{members, exclude, default-members}`. Returns
+    /// the fields that changed.
+    fn tidy(&mut self, multiline_threshold: Option<usize>) -> Vec<&'static str> {
+        MEMBER_ARRAYS
+            .iter()
+            .copied()
+            .filter(|field| {
+                self.cargo_toml
+                    .get_mut("workspace")
+                    .and_then(|w| w.get_mut(field))
+                    .and_then(toml_edit::Item::as_array_mut)
+                    .is_some_and(|array| {
+                        crate::manifest::tidy_path_array(array, multiline_threshold)
+                    })
+            })
+            .collect()
     }
 
-    let cargo_toml = cargo_toml.to_string();
-    let modified = cargo_toml != orig;
-    if modified {
-        crate::fs::write(manifest_path, cargo_toml, dry_run)?;
+    /// Writes the manifest if any edit changed it. Returns whether it did.
+    ///
+    /// With `sort_members`, the member arrays are tidied as well. See [`Self::tidy`].
+    fn write(
+        mut self,
+        sort_members: bool,
+        multiline_threshold: Option<usize>,
+        dry_run: bool,
+    ) -> anyhow::Result<bool> {
+        let mut cargo_toml = self.cargo_toml.to_string();
+        let modified = cargo_toml != self.orig;
+        if modified && sort_members {
+            self.tidy(multiline_threshold);
+            cargo_toml = self.cargo_toml.to_string();
+        }
+        if modified {
+            crate::fs::write(
+                self.possibly_empty_workspace_root.join("Cargo.toml"),
+                cargo_toml,
+                dry_run,
+            )?;
+        }
+        Ok(modified)
     }
-    Ok(modified)
 }
 
+const MEMBER_ARRAYS: [&str; 3] = ["members", "exclude", "default-members"];
+
 trait WriteColorExt: WriteColor {
     fn warn(&mut self, message: impl Display) -> io::Result<()> {
         self.set_color(
@@ -1210,54 +7085,334 @@ impl<W: WriteColor> WriteColorExt for W {}
 
 #[ext(MetadataExt)]
 impl Metadata {
-    fn query_for_member<'a>(&'a self, spec: Option<&str>) -> anyhow::Result<&'a Package> {
-        let cargo_exe = env::var_os("CARGO").with_context(|| "`$CARGO` should be present")?;
-
-        let manifest_path = self
-            .resolve
-            .as_ref()
-            .and_then(|Resolve { root, .. }| root.as_ref())
-            .map(|id| self[id].manifest_path.clone())
-            .unwrap_or_else(|| self.workspace_root.join("Cargo.toml"));
-
-        let args = [
-            Some("pkgid".as_ref()),
-            Some("--manifest-path".as_ref()),
-            Some(manifest_path.as_ref()),
-            spec.map(OsStr::new),
+    fn is_publishable(&self, package: &Package) -> bool {
+        self.workspace_members.contains(&package.id)
+            && package.publish.as_ref().is_none_or(|r| !r.is_empty())
+    }
+
+    /// Groups the publishable members into levels with the normal and build dependencies in
+    /// `resolve`. Optional dependencies are taken from the manifests since `resolve` only has
+    /// the activated ones, while crates.io requires all of them to exist.
+    fn publish_levels(&self, mut stderr: impl WriteColor) -> anyhow::Result<Vec<Vec<&Package>>> {
+        let resolve = self.resolve.as_ref().context("`resolve` is missing")?;
+        let by_id = self
+            .packages
+            .iter()
+            .map(|p| (&p.id, p))
+            .collect::<HashMap<_, _>>();
+        let by_dir = self
+            .packages
+            .iter()
+            .flat_map(|p| Some((p.manifest_path.parent()?, &p.id)))
+            .collect::<HashMap<_, _>>();
+        let publishable = self
+            .packages
+            .iter()
+            .filter(|p| self.is_publishable(p))
+            .map(|p| &p.id)
+            .collect::<BTreeSet<_>>();
+
+        let mut deps = publishable
+            .iter()
+            .map(|&id| (id, BTreeSet::new()))
+            .collect::<BTreeMap<_, _>>();
+        for node in &resolve.nodes {
+            let Some(node_deps) = deps.get_mut(&node.id) else {
+                continue;
+            };
+            for dep in &node.deps {
+                let non_dev = dep
+                    .dep_kinds
+                    .iter()
+                    .any(|k| k.kind != DependencyKind::Development);
+                if non_dev && self.workspace_members.contains(&dep.pkg) {
+                    node_deps.insert(&dep.pkg);
+                }
+            }
+        }
+        for &id in &publishable {
+            let package = by_id[id];
+            for dep in &package.dependencies {
+                if dep.kind == DependencyKind::Development {
+                    continue;
+                }
+                let Some(path) = &dep.path else {
+                    continue;
+                };
+                if dep.req == semver::VersionReq::STAR {
+                    stderr.warn(format!(
+                        "`{}` has a path dependency on `{}` without `version`, which blocks \
+                         publishing",
+                        package.name, dep.name,
+                    ))?;
+                }
+                if let Some(&dep_id) = by_dir.get(&**path) {
+                    if dep.optional && self.workspace_members.contains(dep_id) {
+                        deps.get_mut(id).expect("inserted").insert(dep_id);
+                    }
+                }
+            }
+        }
+        for (&id, node_deps) in &mut deps {
+            for dep in node_deps.iter().filter(|dep| !publishable.contains(*dep)) {
+                stderr.warn(format!(
+                    "`{}` depends on `{}`, which is not published",
+                    by_id[id].name, by_id[dep].name,
+                ))?;
+            }
+            node_deps.retain(|dep| publishable.contains(dep));
+        }
+
+        let mut levels = vec![];
+        while !deps.is_empty() {
+            let mut level = deps
+                .iter()
+                .filter(|(_, node_deps)| node_deps.is_empty())
+                .map(|(&id, _)| by_id[id])
+                .collect::<Vec<_>>();
+            if level.is_empty() {
+                bail!(
+                    "found a dependency cycle among {}",
+                    deps.keys()
+                        .map(|id| format!("`{}`", by_id[id].name))
+                        .format(", "),
+                );
+            }
+            level.sort_by(|a, b| a.name.cmp(&b.name));
+            for package in &level {
+                deps.remove(&package.id);
+            }
+            for node_deps in deps.values_mut() {
+                node_deps.retain(|dep| level.iter().all(|p| p.id != **dep));
+            }
+            levels.push(level);
+        }
+        Ok(levels)
+    }
+
+    /// Finds the members affected by the changes since `since`, which are the ones owning the
+    /// changed files and the ones depending on them. Dev-dependencies are only followed one step
+    /// with `dev` since they do not propagate. A change to the root `Cargo.toml` or `Cargo.lock`
+    /// affects every member.
+    fn changed_members(
+        &self,
+        since: &str,
+        dev: bool,
+    ) -> anyhow::Result<Vec<(&Package, &'static str)>> {
+        let resolve = self.resolve.as_ref().context("`resolve` is missing")?;
+        let workspace_root = &self.workspace_root;
+        let by_dir = self
+            .packages
+            .iter()
+            .filter(|p| self.workspace_members.contains(&p.id))
+            .flat_map(|p| Some((p.manifest_path.parent()?, &p.id)))
+            .collect::<HashMap<_, _>>();
+
+        let mut reasons = BTreeMap::<&PackageId, &'static str>::new();
+        let root_files = [
+            workspace_root.join("Cargo.toml"),
+            workspace_root.join("Cargo.lock"),
         ];
-        let args = args.iter().flatten();
-        let output = duct::cmd(cargo_exe, args)
-            .dir(&self.workspace_root)
-            .stdout_capture()
-            .stderr_capture()
-            .unchecked()
-            .run()?;
-        let stdout = str::from_utf8(&output.stdout)?.trim_end();
-        let stderr = str::from_utf8(&output.stderr)?.trim_end();
-        if !output.status.success() {
-            bail!("{}", stderr.trim_start_matches("error: "));
-        }
-
-        let url = stdout.parse::<Url>()?;
-        let fragment = url.fragment().expect("the URL should contain fragment");
-        let spec_name = match *fragment.splitn(2, ':').collect::<Vec<_>>() {
-            [name, _] => name,
-            [_] => url
-                .path_segments()
-                .and_then(Iterator::last)
-                .expect("should contain name"),
-            _ => unreachable!(),
-        };
+        for file in git_changed_files(workspace_root, since)? {
+            if root_files.contains(&file) {
+                for id in by_dir.values() {
+                    reasons.insert(id, "workspace");
+                }
+                break;
+            }
+            // Files under a non-member `Cargo.toml`, such as a test fixture, belong to the member
+            // enclosing it.
+            if let Some(&id) = file.ancestors().find_map(|d| by_dir.get(d)) {
+                reasons.insert(id, "changed");
+            }
+        }
+
+        let mut dependents = HashMap::<&PackageId, Vec<(&PackageId, bool)>>::new();
+        for node in &resolve.nodes {
+            if !self.workspace_members.contains(&node.id) {
+                continue;
+            }
+            for dep in &node.deps {
+                if self.workspace_members.contains(&dep.pkg) {
+                    let non_dev = dep
+                        .dep_kinds
+                        .iter()
+                        .any(|k| k.kind != DependencyKind::Development);
+                    dependents
+                        .entry(&dep.pkg)
+                        .or_default()
+                        .push((&node.id, non_dev));
+                }
+            }
+        }
+        let mut queue = reasons.keys().copied().collect::<VecDeque<_>>();
+        while let Some(id) = queue.pop_front() {
+            for &(dependent, non_dev) in dependents.get(id).into_iter().flatten() {
+                if non_dev && !reasons.contains_key(dependent) {
+                    reasons.insert(dependent, "dependent");
+                    queue.push_back(dependent);
+                }
+            }
+        }
+        if dev {
+            for id in reasons.keys().copied().collect::<Vec<_>>() {
+                for &(dependent, _) in dependents.get(id).into_iter().flatten() {
+                    reasons.entry(dependent).or_insert("dependent");
+                }
+            }
+        }
+
+        let mut changed = reasons
+            .into_iter()
+            .map(|(id, reason)| (&self[id], reason))
+            .collect::<Vec<_>>();
+        changed.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+        Ok(changed)
+    }
 
-        self.packages
+    /// Finds the members matching a package ID specification or a glob on the names.
+    fn match_members(&self, spec: &str) -> anyhow::Result<Vec<&PackageId>> {
+        if !is_glob(spec) {
+            return Ok(vec![&self.query_for_member(spec)?.id]);
+        }
+        let glob = globset::Glob::new(spec)?.compile_matcher();
+        let matched = self
+            .packages
             .iter()
-            .find(|Package { id, name, .. }| {
-                self.workspace_members.contains(id) && name == spec_name
-            })
-            .with_context(|| {
-                let spec = spec.expect("should be present here");
-                format!("package `{}` is not a member of the workspace", spec)
-            })
+            .filter(|p| self.workspace_members.contains(&p.id) && glob.is_match(&*p.name))
+            .map(|p| &p.id)
+            .collect::<Vec<_>>();
+        ensure!(!matched.is_empty(), "`{}` did not match any members", spec);
+        Ok(matched)
+    }
+
+    /// Extends `ids` with the members they depend on, or the members depending on them with
+    /// `dependents`, following normal and build dependencies in `resolve`.
+    fn member_closure<'a>(
+        &'a self,
+        ids: impl IntoIterator<Item = &'a PackageId>,
+        dependents: bool,
+    ) -> anyhow::Result<BTreeSet<&'a PackageId>> {
+        let edges = self.member_edges(dependents)?;
+        Ok(closure(&edges, ids))
+    }
+
+    /// Maps each member to the members it depends on, or to the members depending on it with
+    /// `dependents`, following normal and build dependencies in `resolve`.
+    fn member_edges(
+        &self,
+        dependents: bool,
+    ) -> anyhow::Result<HashMap<&PackageId, Vec<&PackageId>>> {
+        let resolve = self.resolve.as_ref().context("`resolve` is missing")?;
+        let mut edges = HashMap::<&PackageId, Vec<&PackageId>>::new();
+        for node in &resolve.nodes {
+            if !self.workspace_members.contains(&node.id) {
+                continue;
+            }
+            for dep in &node.deps {
+                let non_dev = dep
+                    .dep_kinds
+                    .iter()
+                    .any(|k| k.kind != DependencyKind::Development);
+                if non_dev && self.workspace_members.contains(&dep.pkg) {
+                    if dependents {
+                        edges.entry(&dep.pkg).or_default().push(&node.id);
+                    } else {
+                        edges.entry(&node.id).or_default().push(&dep.pkg);
+                    }
+                }
+            }
+        }
+        Ok(edges)
+    }
+
+    /// Finds a path from `from` to `to` following declared normal and build path dependencies,
+    /// including optional ones.
+    fn find_non_dev_path(&self, from: &PackageId, to: &PackageId) -> Option<Vec<PackageId>> {
+        let by_dir = self
+            .packages
+            .iter()
+            .filter(|p| p.source.is_none())
+            .flat_map(|p| Some((p.manifest_path.parent()?, &p.id)))
+            .collect::<HashMap<_, _>>();
+        let by_id = self
+            .packages
+            .iter()
+            .map(|p| (&p.id, p))
+            .collect::<HashMap<_, _>>();
+
+        let mut parents = HashMap::<&PackageId, &PackageId>::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(id) = queue.pop_front() {
+            if id == to {
+                let mut path = vec![to.clone()];
+                let mut id = to;
+                while let Some(&parent) = parents.get(id) {
+                    path.push(parent.clone());
+                    id = parent;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let deps = by_id[id]
+                .dependencies
+                .iter()
+                .filter(|d| d.kind != DependencyKind::Development)
+                .flat_map(|d| by_dir.get(&**d.path.as_ref()?));
+            for &dep in deps {
+                if dep != from && !parents.contains_key(dep) {
+                    parents.insert(dep, id);
+                    queue.push_back(dep);
+                }
+            }
+        }
+        None
+    }
+
+    fn query_for_registry_package<'a>(&'a self, spec: &str) -> anyhow::Result<&'a Package> {
+        self.query_for_package(spec, |p| {
+            p.source
+                .as_ref()
+                .is_some_and(|s| s.repr.starts_with("registry+") || s.repr.starts_with("sparse+"))
+        })?
+        .with_context(|| {
+            format!(
+                "package `{}` is not a registry dependency of the workspace",
+                spec
+            )
+        })
+    }
+
+    fn query_for_member<'a>(&'a self, spec: &str) -> anyhow::Result<&'a Package> {
+        self.query_for_package(spec, |p| self.workspace_members.contains(&p.id))?
+            .with_context(|| format!("package `{}` is not a member of the workspace", spec))
+    }
+
+    /// Resolves a package ID specification against every package in the dependency graph.
+    ///
+    /// Like `cargo pkgid`, a specification matching several packages is an error even if `filter`
+    /// would narrow them down to one. Returns `Ok(None)` if the match is rejected by `filter`.
+    fn query_for_package<'a>(
+        &'a self,
+        spec: &str,
+        filter: impl Fn(&Package) -> bool,
+    ) -> anyhow::Result<Option<&'a Package>> {
+        let parsed = spec.parse::<pkgid::PackageIdSpec>()?;
+        let matches = self
+            .packages
+            .iter()
+            .filter(|p| parsed.matches(p))
+            .collect::<Vec<_>>();
+        match *matches {
+            [] => bail!(
+                "package ID specification `{}` did not match any packages",
+                spec
+            ),
+            [package] => Ok(Some(package).filter(|p| filter(p))),
+            _ => {
+                debug!("`{}` matched {} packages", parsed.name(), matches.len());
+                Err(pkgid::ambiguity_error(spec, &matches))
+            }
+        }
     }
 }
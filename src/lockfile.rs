@@ -0,0 +1,372 @@
+use anyhow::{bail, ensure, Context as _};
+use cargo_metadata::{semver, Package};
+use itertools::Itertools as _;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub(crate) struct LockedPackage {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) source: Option<String>,
+}
+
+impl LockedPackage {
+    pub(crate) fn from_table(table: &toml_edit::Table) -> Option<Self> {
+        Some(Self {
+            name: table.get("name")?.as_str()?.to_owned(),
+            version: table.get("version")?.as_str()?.to_owned(),
+            source: table
+                .get("source")
+                .and_then(toml_edit::Item::as_str)
+                .map(ToOwned::to_owned),
+        })
+    }
+}
+
+impl From<&'_ Package> for LockedPackage {
+    fn from(package: &'_ Package) -> Self {
+        Self {
+            name: package.name.clone(),
+            version: package.version.to_string(),
+            source: package.source.as_ref().map(|s| s.repr.clone()),
+        }
+    }
+}
+
+pub(crate) fn retain_packages(
+    lock: &mut toml_edit::DocumentMut,
+    mut f: impl FnMut(&LockedPackage) -> bool,
+) {
+    if let Some(packages) = lock
+        .get_mut("package")
+        .and_then(toml_edit::Item::as_array_of_tables_mut)
+    {
+        packages.retain(|table| LockedPackage::from_table(table).is_none_or(|p| f(&p)));
+    }
+}
+
+/// Adds the packages in `other` that are missing in `lock`. Cargo keeps them where they are
+/// still compatible when it refreshes the lockfile.
+pub(crate) fn merge(lock: &mut toml_edit::DocumentMut, other: &toml_edit::DocumentMut) {
+    let Some(other_packages) = other
+        .get("package")
+        .and_then(toml_edit::Item::as_array_of_tables)
+    else {
+        return;
+    };
+    let packages = lock["package"]
+        .or_insert(toml_edit::Item::ArrayOfTables(Default::default()))
+        .as_array_of_tables_mut()
+        .expect("`package` should be an array of tables");
+    let existing = packages
+        .iter()
+        .flat_map(LockedPackage::from_table)
+        .collect::<HashSet<_>>();
+    for table in other_packages {
+        if LockedPackage::from_table(table).is_some_and(|p| !existing.contains(&p)) {
+            packages.push(table.clone());
+        }
+    }
+}
+
+/// Removes the entries of `name` from the sources matching `is_source`, including the ones in
+/// `patch.unused`, so that Cargo resolves it again.
+pub(crate) fn forget(
+    lock: &mut toml_edit::DocumentMut,
+    name: &str,
+    mut is_source: impl FnMut(&str) -> bool,
+) {
+    retain_packages(lock, |p| {
+        !(p.name == name && p.source.as_deref().is_some_and(&mut is_source))
+    });
+    if let Some(unused) = lock
+        .get_mut("patch")
+        .and_then(|p| p.get_mut("unused"))
+        .and_then(toml_edit::Item::as_array_of_tables_mut)
+    {
+        unused.retain(|t| t.get("name").and_then(toml_edit::Item::as_str) != Some(name));
+        if unused.is_empty() {
+            lock.remove("patch");
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    version: semver::Version,
+    source: Option<String>,
+    checksum: Option<String>,
+    dependencies: BTreeSet<usize>,
+}
+
+impl Entry {
+    fn to_locked_package(&self) -> LockedPackage {
+        LockedPackage {
+            name: self.name.clone(),
+            version: self.version.to_string(),
+            source: self.source.clone(),
+        }
+    }
+}
+
+/// Collects the packages reachable from `roots` through `dependencies` in `lock`. Unlike the
+/// resolve graph of `cargo metadata`, this includes the optional dependencies of every feature.
+pub(crate) fn reachable(
+    lock: &toml_edit::DocumentMut,
+    roots: &[LockedPackage],
+) -> anyhow::Result<HashSet<LockedPackage>> {
+    let entries = entries(lock)?;
+    let mut visited = HashSet::new();
+    let mut stack = entries
+        .iter()
+        .positions(|e| roots.contains(&e.to_locked_package()))
+        .collect::<Vec<_>>();
+    while let Some(i) = stack.pop() {
+        if visited.insert(i) {
+            stack.extend(&entries[i].dependencies);
+        }
+    }
+    Ok(visited
+        .into_iter()
+        .map(|i| entries[i].to_locked_package())
+        .collect())
+}
+
+/// Rewrites the path packages in `lock` for `members`, the members after a change of the
+/// workspace, and drops the packages no longer reachable from them. Other packages are kept as
+/// they are, so this fails if a member depends on something missing in `lock`.
+///
+/// The output follows the format of Cargo so that `--locked` accepts it.
+pub(crate) fn edit(lock: &str, members: &[Package]) -> anyhow::Result<String> {
+    let doc = lock.parse::<toml_edit::DocumentMut>()?;
+    let format_version = doc.get("version").and_then(toml_edit::Item::as_integer);
+    ensure!(
+        matches!(format_version, Some(3 | 4)),
+        "unsupported lockfile version",
+    );
+
+    let mut entries = entries(&doc)?;
+
+    let member_indices = members
+        .iter()
+        .map(|member| {
+            let existing = entries.iter().position(|e| {
+                e.name == member.name && e.version == member.version && e.source.is_none()
+            });
+            existing.unwrap_or_else(|| {
+                entries.push(Entry {
+                    name: member.name.clone(),
+                    version: member.version.clone(),
+                    source: None,
+                    checksum: None,
+                    dependencies: BTreeSet::new(),
+                });
+                entries.len() - 1
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for (member, &i) in members.iter().zip(&member_indices) {
+        let dependencies = member
+            .dependencies
+            .iter()
+            .map(|dep| {
+                let found = if let Some(path) = &dep.path {
+                    members
+                        .iter()
+                        .position(|m| m.manifest_path.parent() == Some(path))
+                        .map(|k| member_indices[k])
+                        .or_else(|| best(&entries, |e| e.source.is_none() && e.name == dep.name))
+                } else {
+                    best(&entries, |e| {
+                        e.name == dep.name
+                            && dep.req.matches(&e.version)
+                            && e.source
+                                .as_deref()
+                                .is_none_or(|s| dep.source.as_deref() == Some(without_precise(s)))
+                    })
+                };
+                found.with_context(|| {
+                    format!(
+                        "`{}` (dependency of `{}`) is not locked",
+                        dep.name, member.name,
+                    )
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        entries[i].dependencies = dependencies;
+    }
+
+    let mut reachable = vec![false; entries.len()];
+    let mut stack = member_indices;
+    while let Some(i) = stack.pop() {
+        if !reachable[i] {
+            reachable[i] = true;
+            stack.extend(entries[i].dependencies.iter().copied());
+        }
+    }
+
+    let mut kept = (0..entries.len())
+        .filter(|&i| reachable[i])
+        .collect::<Vec<_>>();
+    kept.sort_by(|&i, &j| {
+        let key = |e: &Entry| (e.name.clone(), e.version.clone(), e.source.clone());
+        key(&entries[i]).cmp(&key(&entries[j]))
+    });
+
+    let mut counts = HashMap::<_, HashMap<_, usize>>::new();
+    for &i in &kept {
+        *counts
+            .entry(&*entries[i].name)
+            .or_default()
+            .entry(&entries[i].version)
+            .or_default() += 1;
+    }
+    let encode = |e: &Entry| {
+        let versions = &counts[&*e.name];
+        if versions[&e.version] > 1 {
+            let source = e.source.as_deref().map(without_precise).unwrap_or_default();
+            format!("{} {} ({})", e.name, e.version, source)
+        } else if versions.len() > 1 {
+            format!("{} {}", e.name, e.version)
+        } else {
+            e.name.clone()
+        }
+    };
+
+    let packages = kept
+        .iter()
+        .map(|&i| {
+            let entry = &entries[i];
+            let mut package = format!(
+                "[[package]]\nname = {}\nversion = {}\n",
+                quote(&entry.name),
+                quote(&entry.version.to_string()),
+            );
+            if let Some(source) = &entry.source {
+                package += &format!("source = {}\n", quote(source));
+            }
+            if let Some(checksum) = &entry.checksum {
+                package += &format!("checksum = {}\n", quote(checksum));
+            }
+            if !entry.dependencies.is_empty() {
+                package += "dependencies = [\n";
+                for dep in entry
+                    .dependencies
+                    .iter()
+                    .map(|&j| encode(&entries[j]))
+                    .sorted()
+                {
+                    package += &format!(" {},\n", quote(&dep));
+                }
+                package += "]\n";
+            }
+            package
+        })
+        .join("\n");
+
+    let header_end = lock.find("[[package]]").unwrap_or(lock.len());
+    let trailer = lock
+        .match_indices('\n')
+        .map(|(i, _)| i + 1)
+        .filter(|&i| i >= header_end)
+        .find(|&i| lock[i..].starts_with('[') && !lock[i..].starts_with("[[package]]"))
+        .map(|i| &lock[i..]);
+
+    let mut output = lock[..header_end].to_owned();
+    output += &packages;
+    if let Some(trailer) = trailer {
+        output += "\n";
+        output += trailer;
+    }
+    Ok(output)
+}
+
+/// Reads the `package` entries of `lock` with their `dependencies` resolved to indices.
+fn entries(lock: &toml_edit::DocumentMut) -> anyhow::Result<Vec<Entry>> {
+    let tables = lock
+        .get("package")
+        .and_then(toml_edit::Item::as_array_of_tables)
+        .map(|packages| packages.iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let mut entries = tables
+        .iter()
+        .map(|table| {
+            let str_field = |key: &str| table.get(key).and_then(toml_edit::Item::as_str);
+            let name = str_field("name").context("missing `name`")?;
+            let version = str_field("version").context("missing `version`")?;
+            Ok(Entry {
+                name: name.to_owned(),
+                version: version.parse()?,
+                source: str_field("source").map(ToOwned::to_owned),
+                checksum: str_field("checksum").map(ToOwned::to_owned),
+                dependencies: BTreeSet::new(),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let dependencies = tables
+        .iter()
+        .map(|table| {
+            table
+                .get("dependencies")
+                .and_then(toml_edit::Item::as_array)
+                .into_iter()
+                .flatten()
+                .map(|dep| {
+                    let dep = dep.as_str().context("`dependencies` must be strings")?;
+                    find_encoded(&entries, dep)
+                })
+                .collect::<anyhow::Result<BTreeSet<_>>>()
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    for (entry, dependencies) in entries.iter_mut().zip(dependencies) {
+        entry.dependencies = dependencies;
+    }
+    Ok(entries)
+}
+
+/// Finds the package referred by an entry in `dependencies`: `name`, `name version`, or
+/// `name version (source)`.
+fn find_encoded(entries: &[Entry], dep: &str) -> anyhow::Result<usize> {
+    let (dep_without_source, source) = match dep.split_once(" (") {
+        Some((dep, source)) => (dep, Some(source.trim_end_matches(')'))),
+        None => (dep, None),
+    };
+    let (name, version) = match dep_without_source.split_once(' ') {
+        Some((name, version)) => (name, Some(version)),
+        None => (dep_without_source, None),
+    };
+    match *entries
+        .iter()
+        .positions(|e| {
+            e.name == name
+                && version.is_none_or(|v| e.version.to_string() == v)
+                && source.is_none_or(|s| e.source.as_deref().map(without_precise) == Some(s))
+        })
+        .collect::<Vec<_>>()
+    {
+        [i] => Ok(i),
+        [] => bail!("`{}` is not found in `package`", dep),
+        _ => bail!("`{}` is ambiguous", dep),
+    }
+}
+
+fn best(entries: &[Entry], f: impl Fn(&Entry) -> bool) -> Option<usize> {
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| f(e))
+        .max_by_key(|(_, e)| (e.source.is_none(), &e.version))
+        .map(|(i, _)| i)
+}
+
+fn without_precise(source: &str) -> &str {
+    source.split('#').next().expect("should not be empty")
+}
+
+fn quote(s: &str) -> String {
+    toml_edit::Value::from(s).to_string()
+}
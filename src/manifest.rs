@@ -0,0 +1,793 @@
+use anyhow::{bail, Context as _};
+use camino::{Utf8Component, Utf8Path as Path, Utf8PathBuf as PathBuf};
+use std::iter;
+
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut acc = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Utf8Component::CurDir => {}
+            Utf8Component::ParentDir => {
+                acc.pop();
+            }
+            component => acc.push(component),
+        }
+    }
+    acc
+}
+
+pub(crate) fn relative_path(base: &Path, path: &Path) -> PathBuf {
+    let (base, path) = (normalize(base), normalize(path));
+    let common = base
+        .components()
+        .zip(path.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let rel = iter::repeat_n("..", base.components().count() - common)
+        .chain(path.components().skip(common).map(|c| c.as_str()))
+        .collect::<PathBuf>();
+    if rel.as_str().is_empty() {
+        ".".into()
+    } else {
+        rel
+    }
+}
+
+pub(crate) fn to_manifest_str(path: &Path) -> String {
+    path.as_str().replace('\\', "/")
+}
+
+pub(crate) fn rebase(old_base: &Path, new_base: &Path, path: &str) -> String {
+    to_manifest_str(&relative_path(new_base, &old_base.join(path)))
+}
+
+pub(crate) fn rebase_dependency(item: &mut toml_edit::Item, old_base: &Path, new_base: &Path) {
+    if let Some(dep) = item.as_table_like_mut() {
+        if let Some(path) = dep.get_mut("path") {
+            if let Some(s) = path.as_str() {
+                let rebased = rebase(old_base, new_base, s);
+                *path = toml_edit::value(rebased);
+            }
+        }
+    }
+}
+
+/// Converts an item into a plain TOML value so that two items can be compared regardless of
+/// their formatting. Bare version strings are normalized into `{ version = "..." }`.
+pub(crate) fn to_plain(item: &toml_edit::Item) -> Option<toml::Value> {
+    let value = item.clone().into_value().ok()?;
+    let toml::Value::Table(mut table) =
+        toml::from_str::<toml::Value>(&format!("v = {}", value)).ok()?
+    else {
+        return None;
+    };
+    let value = table.remove("v")?;
+    Some(match value {
+        toml::Value::String(version) => {
+            toml::Value::Table(iter::once(("version".to_owned(), version.into())).collect())
+        }
+        value => value,
+    })
+}
+
+#[derive(Default, Debug)]
+pub(crate) struct Merged {
+    pub(crate) added: Vec<String>,
+    pub(crate) conflicts: Vec<String>,
+}
+
+/// Adds the entries of `src` that are missing in `dst`. Entries present in both with different
+/// values are left as is in `dst` and reported as conflicts.
+pub(crate) fn merge_table(dst: &mut toml_edit::Table, src: &toml_edit::Table) -> Merged {
+    let mut merged = Merged::default();
+    for (key, src_item) in src.iter() {
+        match dst.get(key) {
+            None => {
+                dst.insert(key, src_item.clone());
+                merged.added.push(key.to_owned());
+            }
+            Some(dst_item) => {
+                if to_plain(dst_item) != to_plain(src_item) {
+                    merged.conflicts.push(key.to_owned());
+                }
+            }
+        }
+    }
+    merged
+}
+
+pub(crate) const DEPENDENCY_KINDS: [&str; 3] =
+    ["dependencies", "dev-dependencies", "build-dependencies"];
+
+pub(crate) fn for_each_dependency_mut(
+    cargo_toml: &mut toml_edit::DocumentMut,
+    mut f: impl FnMut(&str, &mut toml_edit::Item) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut visit = |deps: &mut toml_edit::Item| -> anyhow::Result<()> {
+        if let Some(deps) = deps.as_table_like_mut() {
+            for (name, dep) in deps.iter_mut() {
+                f(name.get(), dep)?;
+            }
+        }
+        Ok(())
+    };
+
+    for (key, item) in cargo_toml.as_table_mut().iter_mut() {
+        if DEPENDENCY_KINDS.contains(&key.get()) {
+            visit(item)?;
+        } else if key.get() == "target" {
+            if let Some(targets) = item.as_table_like_mut() {
+                for (_, target) in targets.iter_mut() {
+                    if let Some(target) = target.as_table_like_mut() {
+                        for (key, item) in target.iter_mut() {
+                            if DEPENDENCY_KINDS.contains(&key.get()) {
+                                visit(item)?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Visits the dependencies in the top-level and `target.*` tables along with the name of the
+/// table (`dependencies`, `dev-dependencies`, or `build-dependencies`) they are in.
+pub(crate) fn for_each_dependency(
+    cargo_toml: &toml_edit::DocumentMut,
+    mut f: impl FnMut(&str, &str, &toml_edit::Item),
+) {
+    let mut visit = |kind: &str, deps: &toml_edit::Item| {
+        if let Some(deps) = deps.as_table_like() {
+            for (name, dep) in deps.iter() {
+                f(kind, name, dep);
+            }
+        }
+    };
+
+    for (key, item) in cargo_toml.as_table().iter() {
+        if DEPENDENCY_KINDS.contains(&key) {
+            visit(key, item);
+        } else if key == "target" {
+            if let Some(targets) = item.as_table_like() {
+                for (_, target) in targets.iter() {
+                    if let Some(target) = target.as_table_like() {
+                        for (key, item) in target.iter() {
+                            if DEPENDENCY_KINDS.contains(&key) {
+                                visit(key, item);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn workspace_table<'a>(
+    root_cargo_toml: &'a toml_edit::DocumentMut,
+    field: &str,
+) -> Option<&'a toml_edit::Table> {
+    root_cargo_toml.get("workspace")?.get(field)?.as_table()
+}
+
+pub(crate) fn is_inherited(item: &toml_edit::Item) -> bool {
+    item.as_table_like()
+        .and_then(|t| t.get("workspace"))
+        .and_then(toml_edit::Item::as_bool)
+        == Some(true)
+}
+
+/// Clones an item, dropping the document positions of the tables so that they are placed
+/// right after their parents when inserted into another document.
+pub(crate) fn detach(item: &toml_edit::Item) -> toml_edit::Item {
+    match item {
+        toml_edit::Item::Table(table) => {
+            let mut detached = toml_edit::Table::new();
+            detached.set_implicit(table.is_implicit());
+            detached.set_dotted(table.is_dotted());
+            for (key, item) in table.iter() {
+                detached.insert(key, detach(item));
+            }
+            toml_edit::Item::Table(detached)
+        }
+        item => item.clone(),
+    }
+}
+
+/// Turns an inline table that only contains `version` into a bare version string.
+pub(crate) fn simplify_dependency(dep: &mut toml_edit::Item) {
+    let version = dep.as_table_like().and_then(|t| {
+        let mut keys = t.iter().map(|(k, _)| k);
+        match (keys.next(), keys.next()) {
+            (Some("version"), None) => t.get("version")?.as_str().map(ToOwned::to_owned),
+            _ => None,
+        }
+    });
+    if let Some(version) = version {
+        let decor = dep.as_value().map(|v| v.decor().clone());
+        *dep = toml_edit::value(version);
+        if let (Some(decor), Some(value)) = (decor, dep.as_value_mut()) {
+            *value.decor_mut() = decor;
+        }
+    }
+}
+
+pub(crate) fn package_version(
+    cargo_toml: &toml_edit::DocumentMut,
+    root_cargo_toml: &toml_edit::DocumentMut,
+) -> Option<String> {
+    let version = cargo_toml.get("package")?.get("version")?;
+    let version = if is_inherited(version) {
+        workspace_table(root_cargo_toml, "package")?.get("version")?
+    } else {
+        version
+    };
+    version.as_str().map(ToOwned::to_owned)
+}
+
+/// Replaces every `*.workspace = true` in a member manifest with the concrete value from the
+/// root manifest. Paths taken from the root are rebased onto `package_dir`.
+pub(crate) fn resolve_inheritance(
+    cargo_toml: &mut toml_edit::DocumentMut,
+    root_cargo_toml: &toml_edit::DocumentMut,
+    root_dir: &Path,
+    package_dir: &Path,
+) -> anyhow::Result<()> {
+    if let Some(package) = cargo_toml
+        .get_mut("package")
+        .and_then(toml_edit::Item::as_table_like_mut)
+    {
+        for (key, item) in package.iter_mut() {
+            if is_inherited(item) {
+                let mut value = workspace_table(root_cargo_toml, "package")
+                    .and_then(|t| t.get(key.get()))
+                    .with_context(|| format!("`workspace.package.{}` is not found", key.get()))?
+                    .clone();
+                if ["readme", "license-file"].contains(&key.get()) {
+                    if let Some(s) = value.as_str() {
+                        value = toml_edit::value(rebase(root_dir, package_dir, s));
+                    }
+                }
+                *item = value;
+            }
+        }
+    }
+
+    let workspace_deps = workspace_table(root_cargo_toml, "dependencies");
+    for_each_dependency_mut(cargo_toml, |name, dep| {
+        if !is_inherited(dep) {
+            return Ok(());
+        }
+        let base = workspace_deps
+            .and_then(|t| t.get(name))
+            .with_context(|| format!("`workspace.dependencies.{}` is not found", name))?;
+        let mut resolved = match base.clone().into_value() {
+            Ok(toml_edit::Value::String(version)) => {
+                let mut table = toml_edit::InlineTable::new();
+                table.insert("version", toml_edit::Value::String(version));
+                table
+            }
+            Ok(toml_edit::Value::InlineTable(table)) => table,
+            _ => bail!(
+                "`workspace.dependencies.{}` must be a string or a table",
+                name
+            ),
+        };
+        if let Some(path) = resolved.get("path").and_then(toml_edit::Value::as_str) {
+            let rebased = rebase(root_dir, package_dir, path);
+            resolved.insert("path", rebased.into());
+        }
+        for (key, value) in dep.as_table_like().expect("checked").iter() {
+            let value = match value.as_value() {
+                Some(value) => value,
+                None => continue,
+            };
+            match key {
+                "workspace" => {}
+                "features" => {
+                    let features = resolved
+                        .entry("features")
+                        .or_insert(toml_edit::Array::new().into());
+                    if let (Some(features), Some(additional)) =
+                        (features.as_array_mut(), value.as_array())
+                    {
+                        for feature in additional {
+                            if !features.iter().any(|f| f.as_str() == feature.as_str()) {
+                                features.push(feature.clone());
+                            }
+                        }
+                    }
+                }
+                key => {
+                    resolved.insert(key, value.clone());
+                }
+            }
+        }
+        resolved.fmt();
+        *dep = match dep {
+            toml_edit::Item::Table(table) if !table.is_dotted() => {
+                toml_edit::Item::Table(resolved.into_table())
+            }
+            _ => {
+                let decor = dep.as_value().map(|v| v.decor().clone());
+                let mut resolved = toml_edit::Value::InlineTable(resolved);
+                if let Some(decor) = decor {
+                    *resolved.decor_mut() = decor;
+                }
+                toml_edit::Item::Value(resolved)
+            }
+        };
+        simplify_dependency(dep);
+        Ok(())
+    })?;
+
+    if cargo_toml.get("lints").is_some_and(is_inherited) {
+        let lints = workspace_table(root_cargo_toml, "lints")
+            .with_context(|| "`workspace.lints` is not found")?;
+        let mut lints = detach(&toml_edit::Item::Table(lints.clone()));
+        if let (Some(lints), Some(position)) = (
+            lints.as_table_mut(),
+            cargo_toml["lints"]
+                .as_table()
+                .and_then(toml_edit::Table::position),
+        ) {
+            lints.set_position(position);
+        }
+        cargo_toml["lints"] = lints;
+    }
+    Ok(())
+}
+
+const INHERITABLE_PACKAGE_FIELDS: [&str; 17] = [
+    "version",
+    "authors",
+    "description",
+    "documentation",
+    "readme",
+    "homepage",
+    "repository",
+    "license",
+    "license-file",
+    "keywords",
+    "categories",
+    "publish",
+    "edition",
+    "rust-version",
+    "exclude",
+    "include",
+    "badges",
+];
+
+pub(crate) fn inherited() -> toml_edit::Item {
+    let mut table = toml_edit::InlineTable::new();
+    table.insert("workspace", true.into());
+    table.set_dotted(true);
+    toml_edit::value(table)
+}
+
+/// Absolutizes the `path` of a plain dependency so that dependencies written relative to
+/// different directories can be compared.
+fn absolutize_dependency(dep: &mut toml::Value, base: &Path) {
+    if let Some(path) = dep.get_mut("path") {
+        if let Some(s) = path.as_str() {
+            *path = normalize(&base.join(s)).into_string().into();
+        }
+    }
+}
+
+/// The inverse of [`resolve_inheritance`]. Replaces values that are equal to the ones in the
+/// root manifest with `*.workspace = true`.
+pub(crate) fn reinherit(
+    cargo_toml: &mut toml_edit::DocumentMut,
+    root_cargo_toml: &toml_edit::DocumentMut,
+    root_dir: &Path,
+    package_dir: &Path,
+) -> anyhow::Result<()> {
+    if let (Some(package), Some(workspace_package)) = (
+        cargo_toml
+            .get_mut("package")
+            .and_then(toml_edit::Item::as_table_like_mut),
+        workspace_table(root_cargo_toml, "package"),
+    ) {
+        for (key, item) in package.iter_mut() {
+            let key = key.get();
+            if !INHERITABLE_PACKAGE_FIELDS.contains(&key) || is_inherited(item) {
+                continue;
+            }
+            let Some(workspace_item) = workspace_package.get(key) else {
+                continue;
+            };
+            let same = if ["readme", "license-file"].contains(&key) {
+                match (item.as_str(), workspace_item.as_str()) {
+                    (Some(path), Some(workspace_path)) => {
+                        normalize(&package_dir.join(path))
+                            == normalize(&root_dir.join(workspace_path))
+                    }
+                    _ => to_plain(item) == to_plain(workspace_item),
+                }
+            } else {
+                to_plain(item) == to_plain(workspace_item)
+            };
+            if same {
+                *item = inherited();
+            }
+        }
+    }
+
+    if let Some(workspace_deps) = workspace_table(root_cargo_toml, "dependencies") {
+        for_each_dependency_mut(cargo_toml, |name, dep| {
+            if is_inherited(dep) {
+                return Ok(());
+            }
+            let (Some(mut member), Some(mut workspace)) =
+                (to_plain(dep), workspace_deps.get(name).and_then(to_plain))
+            else {
+                return Ok(());
+            };
+            absolutize_dependency(&mut member, package_dir);
+            absolutize_dependency(&mut workspace, root_dir);
+
+            let (Some(member), Some(workspace)) = (member.as_table_mut(), workspace.as_table_mut())
+            else {
+                return Ok(());
+            };
+            let features = |table: &mut toml::Table| -> Vec<String> {
+                table
+                    .remove("features")
+                    .and_then(|f| f.try_into().ok())
+                    .unwrap_or_default()
+            };
+            let member_features = features(member);
+            let workspace_features = features(workspace);
+            let optional = member.remove("optional");
+            if member != workspace
+                || !workspace_features
+                    .iter()
+                    .all(|f| member_features.contains(f))
+            {
+                return Ok(());
+            }
+
+            let mut inherited = toml_edit::InlineTable::new();
+            inherited.insert("workspace", true.into());
+            let features = member_features
+                .iter()
+                .filter(|f| !workspace_features.contains(f))
+                .collect::<toml_edit::Array>();
+            if !features.is_empty() {
+                inherited.insert("features", features.into());
+            }
+            if let Some(optional) = optional.as_ref().and_then(toml::Value::as_bool) {
+                inherited.insert("optional", optional.into());
+            }
+            *dep = match dep {
+                toml_edit::Item::Table(table) if !table.is_dotted() => {
+                    toml_edit::Item::Table(inherited.into_table())
+                }
+                _ if inherited.len() == 1 => self::inherited(),
+                _ => toml_edit::value(inherited),
+            };
+            Ok(())
+        })?;
+    }
+
+    if let (Some(lints), Some(workspace_lints)) = (
+        cargo_toml.get("lints"),
+        workspace_table(root_cargo_toml, "lints"),
+    ) {
+        if !is_inherited(lints)
+            && to_plain(lints) == to_plain(&toml_edit::Item::Table(workspace_lints.clone()))
+        {
+            let mut table = toml_edit::Table::new();
+            table.insert("workspace", toml_edit::value(true));
+            if let Some(position) = cargo_toml["lints"]
+                .as_table()
+                .and_then(toml_edit::Table::position)
+            {
+                table.set_position(position);
+            }
+            cargo_toml["lints"] = toml_edit::Item::Table(table);
+        }
+    }
+    Ok(())
+}
+
+/// Sorts and deduplicates a path array such as `workspace.members`, keeping the comments with the
+/// entries they are attached to.
+///
+/// The array is laid out one entry per line, with a trailing comma, when it has more than
+/// `multiline_threshold` entries or contains a comment. Without a threshold, the current layout
+/// is kept. Returns whether the array changed.
+pub(crate) fn tidy_path_array(
+    array: &mut toml_edit::Array,
+    multiline_threshold: Option<usize>,
+) -> bool {
+    let orig = array.to_string();
+    let ArrayEntries {
+        mut entries,
+        header,
+        dangling,
+        indent,
+        closing_indent,
+    } = ArrayEntries::new(array);
+
+    entries.sort_by_key(Entry::key);
+    entries.dedup_by(|dup, kept| {
+        if dup.key() != kept.key() {
+            return false;
+        }
+        kept.leading.append(&mut dup.leading);
+        match (&kept.trailing, dup.trailing.take()) {
+            (None, trailing) => kept.trailing = trailing,
+            (Some(_), Some(trailing)) => kept.leading.push(trailing),
+            (Some(_), None) => {}
+        }
+        true
+    });
+
+    let has_comments = header.is_some()
+        || !dangling.is_empty()
+        || entries
+            .iter()
+            .any(|e| !e.leading.is_empty() || e.trailing.is_some());
+    let multiline = has_comments
+        || match multiline_threshold {
+            Some(threshold) => entries.len() > threshold,
+            None => is_multiline(array),
+        };
+
+    array.clear();
+    if multiline && !entries.is_empty() {
+        let mut prev_trailing = header;
+        for Entry {
+            mut value,
+            leading,
+            trailing,
+        } in entries
+        {
+            let mut prefix = comment_suffix(prev_trailing.as_deref());
+            prefix += "\n";
+            for comment in leading {
+                prefix += &format!("{}{}\n", indent, comment);
+            }
+            prefix += &indent;
+            value.decor_mut().set_prefix(prefix);
+            value.decor_mut().set_suffix("");
+            array.push_formatted(value);
+            prev_trailing = trailing;
+        }
+        let mut trailing = comment_suffix(prev_trailing.as_deref());
+        trailing += "\n";
+        for comment in dangling {
+            trailing += &format!("{}{}\n", indent, comment);
+        }
+        trailing += &closing_indent;
+        array.set_trailing(trailing);
+        array.set_trailing_comma(true);
+    } else {
+        for (i, Entry { mut value, .. }) in entries.into_iter().enumerate() {
+            value.decor_mut().set_prefix(if i == 0 { "" } else { " " });
+            value.decor_mut().set_suffix("");
+            array.push_formatted(value);
+        }
+        array.set_trailing("");
+        array.set_trailing_comma(false);
+    }
+    array.to_string() != orig
+}
+
+/// Appends a string to an array, following its layout. In a multi-line array the new entry goes
+/// on its own line, below the comment on the line of the former last entry.
+pub(crate) fn push_entry(array: &mut toml_edit::Array, value: &str) {
+    if !is_multiline(array) || array.is_empty() {
+        array.push(value);
+        return;
+    }
+    let indent = array
+        .iter()
+        .map(|v| raw_prefix(v.decor()))
+        .filter(|p| p.contains('\n'))
+        .last()
+        .map(|p| p[p.rfind('\n').expect("checked") + 1..].to_owned())
+        .filter(|s| s.chars().all(char::is_whitespace))
+        .unwrap_or_else(|| "    ".to_owned());
+    let trailing = array.trailing().as_str().unwrap_or_default().to_owned();
+    let (line_end, rest) = split_first_line(&trailing);
+    let mut value = toml_edit::Value::from(value);
+    value
+        .decor_mut()
+        .set_prefix(format!("{}\n{}", line_end, indent));
+    array.push_formatted(value);
+    array.set_trailing(format!("\n{}", rest));
+    array.set_trailing_comma(true);
+}
+
+/// Removes the `i`th entry of an array along with the comment lines above it and the comment on
+/// its line, leaving the rest as it is.
+pub(crate) fn remove_entry(array: &mut toml_edit::Array, i: usize) {
+    let removed = raw_prefix(array.get(i).expect("out of bounds").decor()).to_owned();
+    // The first line of a prefix belongs to the previous entry, or `[`.
+    let (prev_line_end, _) = split_first_line(&removed);
+    let is_last = i + 1 == array.len();
+    let next = if is_last {
+        array.trailing().as_str().unwrap_or_default().to_owned()
+    } else {
+        raw_prefix(array.get(i + 1).expect("checked").decor()).to_owned()
+    };
+    let next = match next.split_once('\n') {
+        Some((_, rest)) => format!("{}\n{}", prev_line_end, rest),
+        None if removed.contains('\n') && is_last => format!("{}\n{}", prev_line_end, next),
+        None if removed.contains('\n') => format!(
+            "{}\n{}",
+            prev_line_end,
+            &removed[removed.rfind('\n').expect("checked") + 1..],
+        ),
+        None if is_last => next,
+        None => removed,
+    };
+
+    array.remove(i);
+    if is_last {
+        if array.is_empty() && next.trim().is_empty() {
+            array.set_trailing("");
+            array.set_trailing_comma(false);
+        } else {
+            array.set_trailing(next);
+        }
+    } else {
+        array
+            .get_mut(i)
+            .expect("checked")
+            .decor_mut()
+            .set_prefix(next);
+    }
+}
+
+/// Inserts a key into an inline table, keeping the space before `}` at the end.
+pub(crate) fn insert_inline(
+    table: &mut toml_edit::InlineTable,
+    key: &str,
+    value: impl Into<toml_edit::Value>,
+) {
+    let padding = table.iter_mut().last().map(|(_, last)| {
+        let padding = raw_suffix(last.decor()).to_owned();
+        last.decor_mut().set_suffix("");
+        padding
+    });
+    table.insert(key, value.into());
+    if let Some(padding) = padding {
+        table
+            .get_mut(key)
+            .expect("just inserted")
+            .decor_mut()
+            .set_suffix(padding);
+    }
+}
+
+/// Whether the array spans several lines.
+pub(crate) fn is_multiline(array: &toml_edit::Array) -> bool {
+    array
+        .iter()
+        .map(|v| raw_prefix(v.decor()))
+        .chain([array.trailing().as_str().unwrap_or_default()])
+        .any(|s| s.contains('\n'))
+}
+
+struct ArrayEntries {
+    entries: Vec<Entry>,
+    /// A comment right after `[`.
+    header: Option<String>,
+    /// Comment lines between the last entry and `]`.
+    dangling: Vec<String>,
+    indent: String,
+    closing_indent: String,
+}
+
+struct Entry {
+    value: toml_edit::Value,
+    /// Comment lines above the entry.
+    leading: Vec<String>,
+    /// A comment on the same line as the entry.
+    trailing: Option<String>,
+}
+
+impl Entry {
+    fn key(&self) -> (bool, String) {
+        match self.value.as_str() {
+            Some(path) => (
+                false,
+                path.trim_start_matches("./")
+                    .trim_end_matches('/')
+                    .to_owned(),
+            ),
+            None => (true, self.value.to_string().trim().to_owned()),
+        }
+    }
+}
+
+impl ArrayEntries {
+    fn new(array: &toml_edit::Array) -> Self {
+        let mut entries = Vec::<Entry>::new();
+        let mut header = None;
+        let mut indent = None;
+
+        for (i, value) in array.iter().enumerate() {
+            let prefix = raw_prefix(value.decor());
+            let (first_line, rest) = split_first_line(prefix);
+            let same_line = comments(first_line).next();
+            match entries.last_mut() {
+                Some(prev) if same_line.is_some() => prev.trailing = same_line,
+                _ => header = header.or(same_line),
+            }
+            if i == 0 && prefix.contains('\n') {
+                let last_line = &prefix[prefix.rfind('\n').expect("checked") + 1..];
+                if last_line.chars().all(char::is_whitespace) {
+                    indent = Some(last_line.to_owned());
+                }
+            }
+            let mut value = value.clone();
+            let suffix_comment = comments(raw_suffix(value.decor())).next();
+            value.decor_mut().clear();
+            entries.push(Entry {
+                value,
+                leading: comments(rest).collect(),
+                trailing: suffix_comment,
+            });
+        }
+
+        let trailing = array.trailing().as_str().unwrap_or_default();
+        let (first_line, rest) = split_first_line(trailing);
+        let mut dangling = comments(rest).collect::<Vec<_>>();
+        if let Some(comment) = comments(first_line).next() {
+            match entries.last_mut() {
+                Some(last) => last.trailing = Some(comment),
+                None => dangling.insert(0, comment),
+            }
+        }
+        let closing_indent = trailing
+            .rfind('\n')
+            .map(|i| &trailing[i + 1..])
+            .filter(|s| s.chars().all(char::is_whitespace))
+            .unwrap_or_default()
+            .to_owned();
+
+        Self {
+            entries,
+            header,
+            dangling,
+            indent: indent.unwrap_or_else(|| "    ".to_owned()),
+            closing_indent,
+        }
+    }
+}
+
+fn raw_prefix(decor: &toml_edit::Decor) -> &str {
+    decor
+        .prefix()
+        .and_then(toml_edit::RawString::as_str)
+        .unwrap_or_default()
+}
+
+fn raw_suffix(decor: &toml_edit::Decor) -> &str {
+    decor
+        .suffix()
+        .and_then(toml_edit::RawString::as_str)
+        .unwrap_or_default()
+}
+
+fn split_first_line(s: &str) -> (&str, &str) {
+    s.split_once('\n').unwrap_or((s, ""))
+}
+
+fn comments(s: &str) -> impl Iterator<Item = String> + '_ {
+    s.lines()
+        .map(str::trim)
+        .filter(|l| l.starts_with('#'))
+        .map(ToOwned::to_owned)
+}
+
+fn comment_suffix(comment: Option<&str>) -> String {
+    comment.map(|c| format!(" {}", c)).unwrap_or_default()
+}
@@ -0,0 +1,209 @@
+//! Package ID specifications, resolved against `cargo metadata` without spawning `cargo pkgid`.
+//!
+//! <https://doc.rust-lang.org/cargo/reference/pkgid-spec.html>
+
+use anyhow::anyhow;
+use cargo_metadata::{semver, Package};
+use itertools::Itertools as _;
+use std::str::FromStr;
+use url::Url;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PackageIdSpec {
+    name: String,
+    version: Option<PartialVersion>,
+    url: Option<Url>,
+    kind: Option<String>,
+}
+
+impl PackageIdSpec {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn matches(&self, package: &Package) -> bool {
+        if package.name != self.name {
+            return false;
+        }
+        if let Some(version) = &self.version {
+            if !version.matches(&package.version) {
+                return false;
+            }
+        }
+        if self.url.is_none() && self.kind.is_none() {
+            return true;
+        }
+        let Some((kind, url)) = source_url(package) else {
+            return false;
+        };
+        if self.kind.as_ref().is_some_and(|k| *k != kind) {
+            return false;
+        }
+        self.url
+            .as_ref()
+            .is_none_or(|u| u.as_str().trim_end_matches('/') == url.as_str().trim_end_matches('/'))
+    }
+}
+
+impl FromStr for PackageIdSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> anyhow::Result<Self> {
+        let invalid = || anyhow!("invalid package ID specification: `{}`", spec);
+
+        if !spec.contains("://") {
+            let (name, version) = match spec.split_once(['@', ':']) {
+                Some((name, version)) => (name, Some(version.parse().map_err(|_| invalid())?)),
+                None => (spec, None),
+            };
+            if !is_valid_name(name) {
+                return Err(invalid());
+            }
+            return Ok(Self {
+                name: name.to_owned(),
+                version,
+                url: None,
+                kind: None,
+            });
+        }
+
+        let (kind, url) = match spec.split_once("://") {
+            Some((scheme, _)) if scheme.contains('+') => {
+                let (kind, url) = spec.split_once('+').expect("should contain `+`");
+                (Some(kind.to_owned()), url)
+            }
+            _ => (None, spec),
+        };
+        let mut url = url.parse::<Url>().map_err(|_| invalid())?;
+        let fragment = url.fragment().map(ToOwned::to_owned);
+        url.set_fragment(None);
+        url.set_query(None);
+
+        let last_segment = || {
+            url.path_segments()
+                .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+                .map(ToOwned::to_owned)
+                .ok_or_else(invalid)
+        };
+        let (name, version) = match fragment.as_deref() {
+            None => (last_segment()?, None),
+            Some(fragment) => match fragment.split_once(['@', ':']) {
+                Some((name, version)) => (name.to_owned(), Some(version)),
+                None if fragment.starts_with(|c: char| c.is_ascii_digit()) => {
+                    (last_segment()?, Some(fragment))
+                }
+                None => (fragment.to_owned(), None),
+            },
+        };
+        if !is_valid_name(&name) {
+            return Err(invalid());
+        }
+        let version = version.map(str::parse).transpose().map_err(|_| invalid())?;
+        Ok(Self {
+            name,
+            version,
+            url: Some(url),
+            kind,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PartialVersion {
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Option<semver::Prerelease>,
+}
+
+impl PartialVersion {
+    fn matches(&self, version: &semver::Version) -> bool {
+        self.major == version.major
+            && self.minor.is_none_or(|minor| minor == version.minor)
+            && self.patch.is_none_or(|patch| patch == version.patch)
+            && self.pre.as_ref().is_none_or(|pre| *pre == version.pre)
+    }
+}
+
+impl FromStr for PartialVersion {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        if let Ok(version) = s.parse::<semver::Version>() {
+            return Ok(Self {
+                major: version.major,
+                minor: Some(version.minor),
+                patch: Some(version.patch),
+                pre: Some(version.pre),
+            });
+        }
+        let mut parts = s.split('.').map(|p| {
+            p.parse::<u64>()
+                .ok()
+                .filter(|_| p.chars().all(|c| c.is_ascii_digit()))
+                .ok_or(())
+        });
+        let major = parts.next().ok_or(())??;
+        let minor = parts.next().transpose()?;
+        if parts.next().is_some() {
+            return Err(());
+        }
+        Ok(Self {
+            major,
+            minor,
+            patch: None,
+            pre: None,
+        })
+    }
+}
+
+/// Builds the error `cargo` reports when a specification matches more than one package.
+pub(crate) fn ambiguity_error(spec: &str, candidates: &[&Package]) -> anyhow::Error {
+    let versions_are_unique = candidates.iter().map(|p| &p.version).all_unique();
+    let suggestions = candidates
+        .iter()
+        .map(|package| {
+            if versions_are_unique {
+                format!("{}@{}", package.name, package.version)
+            } else {
+                let (kind, url) = source_url(package).expect("should be valid");
+                format!("{}+{}#{}@{}", kind, url, package.name, package.version)
+            }
+        })
+        .sorted()
+        .map(|s| format!("  {}", s))
+        .join("\n");
+    anyhow!(
+        "There are multiple `{}` packages in your project, and the specification `{}` is \
+         ambiguous.\n\
+         Please re-run this command with one of the following specifications:\n\
+         {}",
+        candidates[0].name,
+        spec,
+        suggestions,
+    )
+}
+
+fn source_url(package: &Package) -> Option<(String, Url)> {
+    match &package.source {
+        None => {
+            let dir = package.manifest_path.parent()?;
+            let url = Url::from_directory_path(dir).ok()?;
+            Some(("path".to_owned(), url))
+        }
+        Some(source) => {
+            let (kind, url) = source.repr.split_once('+')?;
+            let mut url = url.parse::<Url>().ok()?;
+            url.set_fragment(None);
+            url.set_query(None);
+            Some((kind.to_owned(), url))
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
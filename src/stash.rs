@@ -0,0 +1,135 @@
+//! Membership snapshots saved by `stash push`, kept in `target/cargo-member/stash.toml` with the
+//! newest first.
+
+use anyhow::{anyhow, bail};
+use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
+use itertools::Itertools as _;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct Stash {
+    #[serde(default, rename = "stash")]
+    pub(crate) snapshots: Vec<Snapshot>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Snapshot {
+    pub(crate) name: Option<String>,
+    pub(crate) members: Vec<String>,
+    pub(crate) exclude: Vec<String>,
+    pub(crate) default_members: Option<Vec<String>>,
+}
+
+impl Stash {
+    pub(crate) fn path(target_dir: &Path) -> PathBuf {
+        target_dir.join("cargo-member").join("stash.toml")
+    }
+
+    pub(crate) fn read(target_dir: &Path) -> anyhow::Result<Self> {
+        let path = Self::path(target_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        crate::fs::read_toml(path)
+    }
+
+    pub(crate) fn write(&self, target_dir: &Path, dry_run: bool) -> anyhow::Result<()> {
+        let path = Self::path(target_dir);
+        crate::fs::create_dir_all(path.parent().expect("should not be root"), dry_run)?;
+        crate::fs::write(path, toml::to_string(self)?, dry_run)
+    }
+
+    /// Finds a snapshot by its index or name. The newest one if `stash` is `None`.
+    pub(crate) fn position(&self, stash: Option<&str>) -> anyhow::Result<usize> {
+        let Some(stash) = stash else {
+            if self.snapshots.is_empty() {
+                bail!("no stash entries found");
+            }
+            return Ok(0);
+        };
+        self.snapshots
+            .iter()
+            .position(|s| s.name.as_deref() == Some(stash))
+            .or_else(|| stash.parse().ok().filter(|&i| i < self.snapshots.len()))
+            .ok_or_else(|| anyhow!("stash `{}` does not exist", stash))
+    }
+}
+
+impl Snapshot {
+    pub(crate) fn take(name: Option<String>, cargo_toml: &toml_edit::DocumentMut) -> Self {
+        let strings = |field| -> Option<Vec<String>> {
+            let array = cargo_toml.get("workspace")?.get(field)?.as_array()?;
+            Some(
+                array
+                    .iter()
+                    .flat_map(|v| v.as_str())
+                    .map(Into::into)
+                    .collect(),
+            )
+        };
+        Self {
+            name,
+            members: strings("members").unwrap_or_default(),
+            exclude: strings("exclude").unwrap_or_default(),
+            default_members: strings("default-members"),
+        }
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &str> {
+        self.members
+            .iter()
+            .chain(&self.exclude)
+            .chain(self.default_members.iter().flatten())
+            .map(String::as_str)
+    }
+
+    /// Merges the snapshot into `current`, an array in the manifest now. Entries the snapshot
+    /// knows go back where they were, and entries added since are left as they are.
+    ///
+    /// Returns the entries to add and to remove.
+    pub(crate) fn merge<'a>(
+        &'a self,
+        saved: &'a [String],
+        current: &'a [String],
+        same_paths: impl Fn(&str, &str) -> bool,
+    ) -> (Vec<&'a str>, Vec<&'a str>) {
+        let add = saved
+            .iter()
+            .filter(|s| !current.iter().any(|c| same_paths(s, c)))
+            .map(String::as_str)
+            .collect();
+        let rm = current
+            .iter()
+            .filter(|c| self.entries().any(|e| same_paths(e, c)))
+            .filter(|c| !saved.iter().any(|s| same_paths(s, c)))
+            .map(String::as_str)
+            .collect();
+        (add, rm)
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let array = |entries: &[String]| {
+            format!(
+                "[{}]",
+                entries.iter().map(|e| format!("{:?}", e)).join(", ")
+            )
+        };
+        if let Some(name) = &self.name {
+            write!(f, "{}: ", name)?;
+        }
+        write!(
+            f,
+            "members = {}, exclude = {}",
+            array(&self.members),
+            array(&self.exclude),
+        )?;
+        if let Some(default_members) = &self.default_members {
+            write!(f, ", default-members = {}", array(default_members))?;
+        }
+        Ok(())
+    }
+}
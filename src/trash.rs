@@ -0,0 +1,133 @@
+//! Removed packages kept in `target/cargo-member/trash/<date>/<name>`, with where they came from
+//! in `target/cargo-member/trash/index.toml`, newest first.
+
+use anyhow::{anyhow, bail};
+use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct Trash {
+    #[serde(default, rename = "item")]
+    pub(crate) items: Vec<Item>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct Item {
+    pub(crate) path: PathBuf,
+    pub(crate) trash: PathBuf,
+}
+
+impl Trash {
+    fn path(target_dir: &Path) -> PathBuf {
+        dir(target_dir).join("index.toml")
+    }
+
+    /// Reads the index, leaving out the items taken out of the trash by other means.
+    pub(crate) fn read(target_dir: &Path) -> anyhow::Result<Self> {
+        let path = Self::path(target_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let mut trash = crate::fs::read_toml::<Self, _>(path)?;
+        trash.items.retain(|item| item.trash.exists());
+        Ok(trash)
+    }
+
+    pub(crate) fn write(&self, target_dir: &Path, dry_run: bool) -> anyhow::Result<()> {
+        let path = Self::path(target_dir);
+        crate::fs::create_dir_all(path.parent().expect("should not be root"), dry_run)?;
+        crate::fs::write(path, toml::to_string(self)?, dry_run)
+    }
+
+    /// Finds an item by its index or directory name. The newest one if `item` is `None`.
+    pub(crate) fn position(&self, item: Option<&str>) -> anyhow::Result<usize> {
+        let Some(item) = item else {
+            if self.items.is_empty() {
+                bail!("the trash is empty");
+            }
+            return Ok(0);
+        };
+        self.items
+            .iter()
+            .position(|i| i.path.file_name() == Some(item))
+            .or_else(|| item.parse().ok().filter(|&i| i < self.items.len()))
+            .ok_or_else(|| anyhow!("`{}` is not in the trash", item))
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let date = self
+            .trash
+            .parent()
+            .and_then(Path::file_name)
+            .unwrap_or_default();
+        write!(f, "{} {}", date, self.path)
+    }
+}
+
+pub(crate) fn dir(target_dir: &Path) -> PathBuf {
+    target_dir.join("cargo-member").join("trash")
+}
+
+/// Moves `path` into the trash. Returns where it went.
+pub(crate) fn put(target_dir: &Path, path: &Path) -> anyhow::Result<PathBuf> {
+    let date_dir = dir(target_dir).join(timestamp(SystemTime::now()));
+    let name = path.file_name().unwrap_or("root");
+    let mut dst = date_dir.join(name);
+    for i in 1.. {
+        if !dst.exists() {
+            break;
+        }
+        dst = date_dir.join(format!("{}.{}", name, i));
+    }
+    crate::fs::create_dir_all(&date_dir, false)?;
+    crate::fs::rename(path, &dst, false)?;
+    debug!("Trashed {} into {}", path, dst);
+
+    let mut trash = Trash::read(target_dir)?;
+    trash.items.insert(
+        0,
+        Item {
+            path: path.to_owned(),
+            trash: dst.clone(),
+        },
+    );
+    trash.write(target_dir, false)?;
+    Ok(dst)
+}
+
+/// `YYYY-MM-DDTHH-MM-SSZ`, which is valid as a file name everywhere.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}-{:02}-{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+    )
+}
@@ -0,0 +1,163 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_member::ChangedFormat;
+use cargo_metadata::{Metadata, MetadataCommand};
+use std::{fs, io};
+use tempdir::TempDir;
+
+#[test]
+fn changed() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-changed")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+    git(tempdir_path, &["init", "-q"])?;
+    git(tempdir_path, &["add", "-A"])?;
+    git(tempdir_path, &["commit", "-qm", "Initial commit"])?;
+
+    let changed = |dev, format| -> anyhow::Result<String> {
+        let mut stdout = vec![];
+        cargo_member::Changed::from_metadata(&metadata, "HEAD")
+            .dev(dev)
+            .format(format)
+            .stdout(&mut stdout)
+            .exec()?;
+        Ok(String::from_utf8(stdout)?)
+    };
+
+    assert_eq!("", changed(false, ChangedFormat::Names)?);
+
+    fs::write(tempdir_path.join("a").join("src").join("lib.rs"), "//\n")?;
+
+    assert_eq!("a\nb\n", changed(false, ChangedFormat::Names)?);
+    assert_eq!("a\nb\nc\n", changed(true, ChangedFormat::Names)?);
+    assert_eq!("-p a -p b -p c\n", changed(true, ChangedFormat::Flags)?);
+
+    fs::write(tempdir_path.join("d").join("src").join("new.rs"), "")?;
+
+    assert_eq!("a\nb\nd\n", changed(false, ChangedFormat::Paths)?);
+
+    let json = serde_json::from_str::<serde_json::Value>(&changed(false, ChangedFormat::Json)?)?;
+    assert_eq!(
+        vec![("a", "changed"), ("b", "dependent"), ("d", "changed")],
+        json.as_array()
+            .expect("should be an array")
+            .iter()
+            .map(|p| (
+                p["name"].as_str().expect("should be a string"),
+                p["reason"].as_str().expect("should be a string"),
+            ))
+            .collect::<Vec<_>>(),
+    );
+
+    git(tempdir_path, &["add", "-A"])?;
+    git(tempdir_path, &["commit", "-qm", "Modify"])?;
+    fs::write(
+        tempdir_path.join("Cargo.toml"),
+        ROOT.replace("resolver", "# comment\nresolver"),
+    )?;
+
+    assert_eq!("a\nb\nc\nd\n", changed(false, ChangedFormat::Names)?);
+    Ok(())
+}
+
+#[test]
+fn nested_fixture() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-changed-nested-fixture")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let fixture = tempdir_path.join("c").join("tests").join("fixture");
+    fs::create_dir_all(fixture.join("src"))?;
+    fs::write(
+        fixture.join("Cargo.toml"),
+        "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\n\n[workspace]\n",
+    )?;
+    fs::write(fixture.join("src").join("lib.rs"), "")?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+    git(tempdir_path, &["init", "-q"])?;
+    git(tempdir_path, &["add", "-A"])?;
+    git(tempdir_path, &["commit", "-qm", "Initial commit"])?;
+
+    fs::write(fixture.join("src").join("lib.rs"), "//\n")?;
+
+    let mut stdout = vec![];
+    cargo_member::Changed::from_metadata(&metadata, "HEAD")
+        .stdout(&mut stdout)
+        .exec()?;
+    assert_eq!("c\nd\n", String::from_utf8(stdout)?);
+    Ok(())
+}
+
+static ROOT: &str = r#"[workspace]
+members = ["a", "b", "c", "d"]
+resolver = "2"
+"#;
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    for (name, manifest) in [("a", A), ("b", B), ("c", C), ("d", D)] {
+        fs::create_dir_all(root.join(name).join("src"))?;
+        fs::write(root.join(name).join("Cargo.toml"), manifest)?;
+        fs::write(root.join(name).join("src").join("lib.rs"), "")?;
+    }
+    return Ok(());
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+a = { path = "../a" }
+"#;
+
+    static C: &str = r#"[package]
+name = "c"
+version = "0.1.0"
+edition = "2021"
+
+[dev-dependencies]
+a = { path = "../a" }
+"#;
+
+    static D: &str = r#"[package]
+name = "d"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+c = { path = "../c" }
+"#;
+}
+
+fn git(dir: &Path, args: &[&str]) -> io::Result<()> {
+    let status = std::process::Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()?;
+    assert!(status.success());
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
@@ -0,0 +1,128 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn check_publish() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-check-publish")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let (mut stdout, mut stderr) = (vec![], vec![]);
+
+    let err = cargo_member::CheckPublish::from_metadata(&metadata)
+        .stdout(&mut stdout)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()
+        .unwrap_err();
+
+    assert_eq!("found 4 problems", err.to_string());
+    assert_diff!(EXPECTED_STDOUT, str::from_utf8(&stdout)?, "\n", 0);
+    assert_stderr(&stderr, "     Checked 3 packages\n")?;
+
+    let mut stdout = vec![];
+
+    let _ = cargo_member::CheckPublish::from_metadata(&metadata)
+        .json(true)
+        .stdout(&mut stdout)
+        .exec();
+
+    let json = serde_json::from_slice::<serde_json::Value>(&stdout)?;
+    assert_eq!("d", json[2]["name"]);
+    assert_eq!(serde_json::json!([]), json[2]["problems"]);
+    return Ok(());
+
+    static EXPECTED_STDOUT: &str = r#"a: `src/lib.rs` for lib target `a` is dropped by `package.include`
+c: path dependency `b` has no `version`
+c: depends on `b`, which has `publish = false`
+c: `package.repository` is missing
+"#;
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    for (name, manifest) in [("a", A), ("b", B), ("c", C), ("d", D)] {
+        fs::create_dir_all(root.join(name).join("src"))?;
+        fs::write(root.join(name).join("Cargo.toml"), manifest)?;
+        fs::write(root.join(name).join("src").join("lib.rs"), "")?;
+    }
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b", "c", "d"]
+resolver = "2"
+
+[workspace.package]
+license = "MIT"
+repository = "https://example.com/ws"
+include = ["src/main.rs"]
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+description = "a"
+license.workspace = true
+repository.workspace = true
+include.workspace = true
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+publish = false
+"#;
+
+    static C: &str = r#"[package]
+name = "c"
+version = "0.1.0"
+edition = "2021"
+description = "c"
+license = "MIT"
+
+[dependencies]
+b = { path = "../b" }
+
+[dev-dependencies]
+d = { path = "../d" }
+"#;
+
+    static D: &str = r#"[package]
+name = "d"
+version = "0.1.0"
+edition = "2021"
+description = "d"
+license.workspace = true
+repository.workspace = true
+"#;
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
@@ -0,0 +1,164 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_member::cli::{Cargo, Context};
+use difference::assert_diff;
+use std::{fs, io};
+use structopt::StructOpt as _;
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn workspace_metadata() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-config-workspace-metadata")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let manifest_path = tempdir_path.join("Cargo.toml");
+
+    fs::write(&manifest_path, ORIGINAL)?;
+
+    run(&["new", "--manifest-path", manifest_path.as_ref(), "foo"])?;
+
+    let foo = tempdir_path.join("crates").join("foo");
+    assert!(foo.join("src").join("lib.rs").exists());
+    assert!(!foo.join(".git").exists());
+    assert!(fs::read_to_string(foo.join("Cargo.toml"))?.contains(r#"name = "acme-foo""#));
+
+    fs::create_dir_all(tempdir_path.join("bar").join("src"))?;
+    fs::write(tempdir_path.join("bar").join("Cargo.toml"), BAR)?;
+    fs::write(tempdir_path.join("bar").join("src").join("lib.rs"), "")?;
+
+    run(&[
+        "include",
+        "--manifest-path",
+        manifest_path.as_ref(),
+        tempdir_path.join("bar").as_ref(),
+    ])?;
+
+    assert_manifest(&manifest_path, EXPECTED_MANIFEST)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+resolver = "2"
+
+[workspace.metadata.cargo-member]
+sort-members = true
+default-dir = "crates"
+name-prefix = "acme-"
+new = { template = "lib", vcs = "none" }
+"#;
+
+    static BAR: &str = r#"[package]
+name = "bar"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["bar", "crates/foo"]
+exclude = []
+resolver = "2"
+
+[workspace.metadata.cargo-member]
+sort-members = true
+default-dir = "crates"
+name-prefix = "acme-"
+new = { template = "lib", vcs = "none" }
+"#;
+}
+
+#[test]
+fn file() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-config-file")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let manifest_path = tempdir_path.join("Cargo.toml");
+
+    fs::write(&manifest_path, "[workspace]\nmembers = []\n")?;
+    fs::write(
+        tempdir_path.join(".cargo-member.toml"),
+        "lock-mode = \"bogus\"\n",
+    )?;
+
+    let err = run(&["new", "--manifest-path", manifest_path.as_ref(), "foo"]).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains(".cargo-member.toml"),
+        "{:#}",
+        err,
+    );
+    assert!(!tempdir_path.join("foo").exists());
+    Ok(())
+}
+
+#[test]
+fn workspace_root_and_sort_override() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-config-workspace-root-and-sort-override")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let manifest_path = tempdir_path.join("Cargo.toml");
+
+    fs::write(&manifest_path, ORIGINAL)?;
+    for name in ["c", "b", "a"] {
+        fs::create_dir_all(tempdir_path.join(name).join("src"))?;
+        fs::write(tempdir_path.join(name).join("Cargo.toml"), package(name))?;
+        fs::write(tempdir_path.join(name).join("src").join("lib.rs"), "")?;
+    }
+    // Not the configuration of the workspace, which is found from its root.
+    fs::write(
+        tempdir_path.join("c").join(".cargo-member.toml"),
+        "lock-mode = \"bogus\"\n",
+    )?;
+
+    let member_manifest_path = tempdir_path.join("c").join("Cargo.toml");
+    run(&[
+        "deactivate",
+        "--manifest-path",
+        member_manifest_path.as_ref(),
+        "-p",
+        "a",
+    ])?;
+    run(&[
+        "include",
+        "--no-sort",
+        "--manifest-path",
+        manifest_path.as_ref(),
+        tempdir_path.join("a").as_ref(),
+    ])?;
+
+    assert_manifest(&manifest_path, EXPECTED_MANIFEST)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["c", "b", "a"]
+exclude = []
+
+[workspace.metadata.cargo-member]
+sort-members = true
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["b", "c", "a"]
+exclude = []
+
+[workspace.metadata.cargo-member]
+sort-members = true
+"#;
+
+    fn package(name: &str) -> String {
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+            name
+        )
+    }
+}
+
+fn run(args: &[&str]) -> anyhow::Result<()> {
+    let Cargo::Member(opt) =
+        Cargo::from_iter_safe(["cargo", "member"].iter().chain(args).chain(&["--offline"]))?;
+    cargo_member::cli::run(opt, Context::new(NoColor::new(vec![]))?)
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
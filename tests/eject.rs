@@ -0,0 +1,186 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn eject() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-eject")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    let expected_stderr = EXPECTED_STDERR
+        .replace("{{src}}", tempdir_path.join("ws").join("b").as_ref())
+        .replace("{{dst}}", tempdir_path.join("b").as_ref());
+
+    create_workspace(&tempdir_path.join("ws"))?;
+    let metadata = cargo_metadata(&tempdir_path.join("ws").join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Eject::from_metadata(&metadata, "b", &tempdir_path.join("b"))
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(
+        &tempdir_path.join("b").join("Cargo.toml"),
+        EXPECTED_MANIFEST,
+    )?;
+    assert_stderr(&stderr, &expected_stderr)?;
+    assert!(tempdir_path.join("b").join("src").join("lib.rs").exists());
+    assert!(tempdir_path.join("ws").join("b").exists());
+    return Ok(());
+
+    static EXPECTED_MANIFEST: &str = r#"[package]
+name = "b"
+version = "0.3.0"
+edition = "2021"
+license = "MIT"
+
+[dependencies]
+a = { version = "0.3.0", features = ["x"] }
+
+[lints.rust]
+unsafe_code = "forbid"
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Ejecting `{{src}}` to `{{dst}}`
+   Replacing path dependency "a" with version "0.3.0"
+"#;
+}
+
+#[test]
+fn keep_paths_and_remove_source() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-eject-keep-paths-and-remove-source")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    let expected_stderr = EXPECTED_STDERR
+        .replace("{{src}}", tempdir_path.join("ws").join("b").as_ref())
+        .replace("{{dst}}", tempdir_path.join("b").as_ref())
+        .replace(
+            "{{lock}}",
+            tempdir_path.join("ws").join("Cargo.lock").as_ref(),
+        );
+
+    create_workspace(&tempdir_path.join("ws"))?;
+    let metadata = cargo_metadata(&tempdir_path.join("ws").join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Eject::from_metadata(&metadata, "b", &tempdir_path.join("b"))
+        .keep_paths(true)
+        .remove_source(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(
+        &tempdir_path.join("b").join("Cargo.toml"),
+        EXPECTED_MANIFEST,
+    )?;
+    assert_stderr(&stderr, &expected_stderr)?;
+    assert!(!tempdir_path.join("ws").join("b").exists());
+    cargo_metadata(&tempdir_path.join("ws").join("Cargo.toml"), &[])?;
+    cargo_metadata(&tempdir_path.join("b").join("Cargo.toml"), &["--offline"])?;
+    return Ok(());
+
+    static EXPECTED_MANIFEST: &str = r#"[package]
+name = "b"
+version = "0.3.0"
+edition = "2021"
+license = "MIT"
+
+[dependencies]
+a = { path = "../ws/a", version = "0.3.0", features = ["x"] }
+
+[lints.rust]
+unsafe_code = "forbid"
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Ejecting `{{src}}` to `{{dst}}`
+    Removing directory `{{src}}`
+    Removing "b" from `workspace.members`
+    Updating {{lock}}
+"#;
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    for dir in [root.join("a").join("src"), root.join("b").join("src")] {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    fs::write(root.join("a").join("Cargo.toml"), A)?;
+    fs::write(root.join("a").join("src").join("lib.rs"), "")?;
+    fs::write(root.join("b").join("Cargo.toml"), B)?;
+    fs::write(root.join("b").join("src").join("lib.rs"), "")?;
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+
+[workspace.package]
+version = "0.3.0"
+edition = "2021"
+license = "MIT"
+
+[workspace.dependencies]
+a = { path = "a", version = "0.3.0" }
+
+[workspace.lints.rust]
+unsafe_code = "forbid"
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version.workspace = true
+edition.workspace = true
+
+[features]
+x = []
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version.workspace = true
+edition.workspace = true
+license.workspace = true
+
+[dependencies]
+a = { workspace = true, features = ["x"] }
+
+[lints]
+workspace = true
+"#;
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
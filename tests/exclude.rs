@@ -30,7 +30,10 @@ fn normal() -> anyhow::Result<()> {
         .exec()?;
 
     assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_MANIFEST)?;
-    assert_stderr(&stderr, EXPECTED_STDERR)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{}", tempdir_path.join("Cargo.lock").as_ref()),
+    )?;
     cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--locked"])?;
     return Ok(());
 
@@ -48,6 +51,7 @@ exclude = ["b", "c"]
       Adding "b" to `workspace.exclude`
     Removing "c" from `workspace.members`
       Adding "c" to `workspace.exclude`
+    Updating {}
 "#;
 }
 
@@ -88,6 +92,106 @@ warning: not modifying the manifest due to dry run
 "#;
 }
 
+#[test]
+fn all_or_nothing() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exclude-all-or-nothing")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    fs::write(tempdir_path.join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir_path.join("a"))?;
+    cargo_new(&tempdir_path.join("b"))?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &[])?;
+
+    let err = cargo_member::Exclude::from_metadata(
+        &metadata,
+        [tempdir_path.join("a"), tempdir_path.to_owned()],
+        None::<&str>,
+    )
+    .exec()
+    .unwrap_err();
+
+    assert_eq!(
+        format!("`{}` is the workspace root", tempdir_path),
+        err.to_string(),
+    );
+    assert_manifest(&tempdir_path.join("Cargo.toml"), MANIFEST)?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+}
+
+#[test]
+fn comments() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exclude-comments")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    for name in ["a", "b", "c", "d"] {
+        cargo_new(&tempdir_path.join(name))?;
+    }
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &[])?;
+
+    cargo_member::Exclude::from_metadata(&metadata, [tempdir_path.join("b")], ["d"])
+        .lock_mode(cargo_member::LockMode::Skip)
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = [
+    # owner: team-x
+    "a",
+    # owner: team-y
+    "b", # flaky
+    "c", # owner: team-z
+    "d",
+]
+exclude = [
+    "e", # legacy
+]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    # owner: team-x
+    "a",
+    "c", # owner: team-z
+]
+exclude = [
+    "e", # legacy
+    "b",
+    "d",
+]
+"#;
+}
+
+#[test]
+fn inline_workspace() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exclude-inline-workspace")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    for name in ["a", "b", "c"] {
+        cargo_new(&tempdir_path.join(name))?;
+    }
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &[])?;
+
+    cargo_member::Exclude::from_metadata(&metadata, [tempdir_path.join("a")], ["c"]).exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"workspace = { members = ["a", "b", "c"] }
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"workspace = { members = ["b"], exclude = ["a", "c"] }
+"#;
+}
+
 fn cargo_new(path: &Path) -> io::Result<()> {
     let cargo_exe = env::var("CARGO").unwrap();
     cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
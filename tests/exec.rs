@@ -0,0 +1,127 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_member::Selection;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    env, fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn exec() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exec")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+    let cargo_exe = env::var("CARGO")?;
+
+    let (mut stdout, mut stderr) = (vec![], vec![]);
+
+    cargo_member::Exec::from_metadata(
+        &metadata,
+        &cargo_exe,
+        ["locate-project", "--message-format", "plain"],
+    )
+    .stdout(&mut stdout)
+    .stderr(NoColor::new(&mut stderr))
+    .exec()?;
+
+    assert_diff!(
+        &EXPECTED_STDOUT.replace("{{root}}", tempdir_path.as_ref()),
+        str::from_utf8(&stdout)?,
+        "\n",
+        0
+    );
+
+    let mut stderr = vec![];
+
+    let err = cargo_member::Exec::from_metadata(&metadata, &cargo_exe, ["pkgid", "nonexistent"])
+        .selection(Selection::new().specs(["a"]))
+        .with_dependencies(true)
+        .fail_fast(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        format!(
+            "`{} pkgid nonexistent` failed in 1 member",
+            shell_escape::escape(cargo_exe.into()),
+        ),
+        err.to_string(),
+    );
+    let stderr = str::from_utf8(&stderr)?;
+    let summary = &stderr[stderr.find("     Summary").expect("should exist")..];
+    let (summary, failed) = summary.split_at(summary.find("      FAILED").expect("should exist"));
+    assert_stderr(summary.as_ref(), EXPECTED_SUMMARY)?;
+    assert!(failed.starts_with("      FAILED b ("));
+    return Ok(());
+
+    static EXPECTED_STDOUT: &str = r#"[b] {{root}}/b/Cargo.toml
+[a] {{root}}/a/Cargo.toml
+[c] {{root}}/c/Cargo.toml
+"#;
+
+    static EXPECTED_SUMMARY: &str = r#"     Summary 0 passed, 1 failed, 1 skipped
+     skipped a
+"#;
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    for (name, manifest) in [("a", A), ("b", B), ("c", C)] {
+        fs::create_dir_all(root.join(name).join("src"))?;
+        fs::write(root.join(name).join("Cargo.toml"), manifest)?;
+        fs::write(root.join(name).join("src").join("lib.rs"), "")?;
+    }
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b", "c"]
+resolver = "2"
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+b = { path = "../b" }
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static C: &str = r#"[package]
+name = "c"
+version = "0.1.0"
+edition = "2021"
+"#;
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
@@ -0,0 +1,235 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn fork_and_unfork() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-fork-and-unfork")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+    let fork_path = tempdir_path.join("patches").join("shell-escape");
+
+    let mut stderr = vec![];
+
+    cargo_member::Fork::from_metadata(&metadata, "shell-escape@0.1.5")
+        .dir("patches")
+        .offline(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_FORKED_ROOT)?;
+    assert_manifest(&fork_path.join("Cargo.toml"), EXPECTED_FORK)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_FORK_STDERR
+            .replace("{{fork}}", fork_path.as_ref())
+            .replace("{{lock}}", tempdir_path.join("Cargo.lock").as_ref()),
+    )?;
+    assert!(fork_path.join("src").join("lib.rs").exists());
+    assert!(!fork_path.join("Cargo.toml.orig").exists());
+    assert!(!fork_path.join(".cargo-ok").exists());
+
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+    let shell_escape = metadata
+        .packages
+        .iter()
+        .find(|p| p.name == "shell-escape")
+        .expect("should exist");
+    assert_eq!(None, shell_escape.source);
+
+    let mut stderr = vec![];
+
+    cargo_member::Unfork::new(tempdir_path, "shell-escape")
+        .offline(true)
+        .dry_run(false)
+        .journal(Some(&metadata.target_directory))
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_UNFORKED_ROOT)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_UNFORK_STDERR
+            .replace("{{fork}}", fork_path.as_ref())
+            .replace("{{lock}}", tempdir_path.join("Cargo.lock").as_ref()),
+    )?;
+    assert!(!fork_path.exists());
+
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+    let shell_escape = metadata
+        .packages
+        .iter()
+        .find(|p| p.name == "shell-escape")
+        .expect("should exist");
+    assert!(shell_escape.source.as_ref().is_some());
+
+    cargo_member::Undo::from_metadata(&metadata).exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_FORKED_ROOT)?;
+    assert_manifest(&fork_path.join("Cargo.toml"), EXPECTED_FORK)?;
+    return Ok(());
+
+    static EXPECTED_FORKED_ROOT: &str = r#"[workspace]
+members = ["a", "patches/shell-escape"]
+exclude = []
+resolver = "2"
+
+[patch.crates-io]
+shell-escape = { path = "patches/shell-escape" }
+"#;
+
+    static EXPECTED_FORK: &str = r#"[package]
+name = "shell-escape"
+version = "0.1.5"
+authors = ["Steven Fackler <sfackler@gmail.com>"]
+license = "MIT/Apache-2.0"
+repository = "https://github.com/sfackler/shell-escape"
+description = "Escape characters that may have a special meaning in a shell"
+
+[dependencies]
+"#;
+
+    static EXPECTED_FORK_STDERR: &str = r#"     Forking `shell-escape v0.1.5` to `{{fork}}`
+      Adding "patches/shell-escape" to `workspace.members`
+      Adding "shell-escape" to `patch.crates-io`
+    Updating {{lock}}
+"#;
+
+    static EXPECTED_UNFORKED_ROOT: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+resolver = "2"
+"#;
+
+    static EXPECTED_UNFORK_STDERR: &str = r#"    Removing "shell-escape" from `patch.crates-io`
+    Removing directory `{{fork}}`
+    Removing "patches/shell-escape" from `workspace.members`
+    Updating {{lock}}
+"#;
+}
+
+#[test]
+fn unfork_keeps_vendored() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-unfork-keeps-vendored")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let vendored = tempdir_path.join("vendor").join("shell-escape");
+
+    fs::create_dir_all(tempdir_path.join("a").join("src"))?;
+    fs::create_dir_all(vendored.join("src"))?;
+    fs::write(tempdir_path.join("Cargo.toml"), ROOT)?;
+    fs::write(tempdir_path.join("a").join("Cargo.toml"), A)?;
+    fs::write(tempdir_path.join("a").join("src").join("lib.rs"), "")?;
+    fs::write(vendored.join("Cargo.toml"), VENDORED)?;
+    fs::write(vendored.join("src").join("lib.rs"), "")?;
+    cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Unfork::new(tempdir_path, "shell-escape")
+        .offline(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_ROOT)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR
+            .replace("{{vendored}}", vendored.as_ref())
+            .replace("{{lock}}", tempdir_path.join("Cargo.lock").as_ref()),
+    )?;
+    assert!(vendored.join("Cargo.toml").exists());
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+resolver = "2"
+
+[patch.crates-io]
+shell-escape = { path = "vendor/shell-escape" }
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+shell-escape = "0.1.5"
+"#;
+
+    static VENDORED: &str = r#"[package]
+name = "shell-escape"
+version = "0.1.5"
+edition = "2015"
+"#;
+
+    static EXPECTED_ROOT: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+resolver = "2"
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Removing "shell-escape" from `patch.crates-io`
+warning: keeping `{{vendored}}` since `fork` did not create it. enable `--remove` to remove
+    Updating {{lock}}
+"#;
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::create_dir_all(root.join("a").join("src"))?;
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    fs::write(root.join("a").join("Cargo.toml"), A)?;
+    fs::write(root.join("a").join("src").join("lib.rs"), "")?;
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+resolver = "2"
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+shell-escape = "0.1.5"
+"#;
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
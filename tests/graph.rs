@@ -0,0 +1,162 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_member::GraphFormat;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{fs, io, str};
+use tempdir::TempDir;
+
+#[test]
+fn graph() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-graph")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let mut stdout = vec![];
+
+    cargo_member::Graph::from_metadata(&metadata)
+        .stdout(&mut stdout)
+        .exec()?;
+
+    assert_diff!(EXPECTED_DOT, str::from_utf8(&stdout)?, "\n", 0);
+
+    let mut stdout = vec![];
+
+    cargo_member::Graph::from_metadata(&metadata)
+        .format(GraphFormat::Mermaid)
+        .all(true)
+        .stdout(&mut stdout)
+        .exec()?;
+
+    assert_diff!(EXPECTED_MERMAID, str::from_utf8(&stdout)?, "\n", 0);
+
+    let mut stdout = vec![];
+
+    cargo_member::Graph::from_metadata(&metadata)
+        .format(GraphFormat::Json)
+        .focus(Some("b"))
+        .all(true)
+        .stdout(&mut stdout)
+        .exec()?;
+
+    let json = serde_json::from_slice::<serde_json::Value>(&stdout)?;
+    let names = json["nodes"]
+        .as_array()
+        .expect("should be an array")
+        .iter()
+        .map(|n| n["name"].as_str().expect("should be a string"))
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["a", "b", "c"], names);
+    assert_eq!(
+        serde_json::json!([
+            { "from": 1, "to": 0, "kind": "build" },
+            { "from": 1, "to": 2, "kind": "normal" },
+            { "from": 2, "to": 0, "kind": "dev" },
+        ]),
+        json["edges"],
+    );
+    return Ok(());
+
+    static EXPECTED_DOT: &str = r#"digraph {
+    n0 [label="a"];
+    n1 [label="b"];
+    n2 [label="c"];
+    n1 -> n0 [style=bold, label="build"];
+    n1 -> n2;
+    n2 -> n0 [style=dashed, label="dev"];
+}
+"#;
+
+    static EXPECTED_MERMAID: &str = r#"flowchart TD
+    n0["a"]
+    n1["b"]
+    n2["c"]
+    n3["x"]:::excluded
+    n4["y"]:::inactive
+    n1 ==>|build| n0
+    n1 --> n2
+    n2 -.->|dev| n0
+    n2 -.->|dev| n3
+    n4 --> n2
+    classDef excluded stroke-dasharray: 5 5
+    classDef inactive stroke-dasharray: 2 2
+"#;
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    for (name, manifest) in [("a", A), ("b", B), ("c", C), ("x", X), ("y", Y)] {
+        fs::create_dir_all(root.join(name).join("src"))?;
+        fs::write(root.join(name).join("Cargo.toml"), manifest)?;
+        fs::write(root.join(name).join("src").join("lib.rs"), "")?;
+    }
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b", "c"]
+exclude = ["x"]
+resolver = "2"
+
+[workspace.dependencies]
+c = { path = "c" }
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+c.workspace = true
+
+[build-dependencies]
+a = { path = "../a" }
+"#;
+
+    static C: &str = r#"[package]
+name = "c"
+version = "0.1.0"
+edition = "2021"
+
+[dev-dependencies]
+a = { path = "../a" }
+x = { path = "../x" }
+"#;
+
+    static X: &str = r#"[package]
+name = "x"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static Y: &str = r#"[package]
+name = "y"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+c.workspace = true
+"#;
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
@@ -120,6 +120,139 @@ warning: not modifying the manifest due to dry run
 "#;
 }
 
+#[test]
+fn absorb() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-absorb")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir_path.join("a"))?;
+    let nested = tempdir_path.join("nested");
+    fs::create_dir_all(nested.join("crates").join("b").join("src"))?;
+    fs::create_dir_all(nested.join("crates").join("c").join("src"))?;
+    fs::write(nested.join("Cargo.toml"), NESTED)?;
+    fs::write(nested.join("crates").join("b").join("Cargo.toml"), B)?;
+    fs::write(
+        nested.join("crates").join("b").join("src").join("lib.rs"),
+        "",
+    )?;
+    fs::write(nested.join("crates").join("c").join("Cargo.toml"), C)?;
+    fs::write(
+        nested.join("crates").join("c").join("src").join("lib.rs"),
+        "",
+    )?;
+    cargo_metadata(&nested.join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Include::new(tempdir_path, [&nested])
+        .absorb(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR
+            .replace("{{root}}", tempdir_path.as_ref())
+            .replace("{{nested}}", nested.as_ref()),
+    )?;
+    assert!(!nested.join("Cargo.toml").exists());
+    assert!(!nested.join("Cargo.lock").exists());
+    cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+
+[workspace.package]
+version = "0.1.0"
+"#;
+
+    static NESTED: &str = r#"[workspace]
+members = ["crates/*"]
+
+[workspace.package]
+version = "0.2.0"
+edition = "2021"
+
+[workspace.dependencies]
+c = { path = "crates/c" }
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version.workspace = true
+edition.workspace = true
+
+[dependencies]
+c.workspace = true
+"#;
+
+    static C: &str = r#"[package]
+name = "c"
+version.workspace = true
+edition.workspace = true
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a", "nested/crates/*"]
+exclude = []
+
+[workspace.package]
+version = "0.1.0"
+edition = "2021"
+
+[workspace.dependencies]
+c = { path = "nested/crates/c" }
+"#;
+
+    static EXPECTED_STDERR: &str = r#"   Absorbing workspace at {{nested}}
+     Merging "edition" into `workspace.package`
+warning: `workspace.package.version` conflicts. keeping the one in {{root}}/Cargo.toml
+     Merging "c" into `workspace.dependencies`
+    Removing virtual manifest {{nested}}/Cargo.toml
+    Removing {{nested}}/Cargo.lock
+      Adding "nested/crates/*" to `workspace.members`
+    Updating {{root}}/Cargo.lock
+"#;
+}
+
+#[test]
+fn nested_workspace_without_absorb() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-nested-workspace-without-absorb")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    cargo_new(&tempdir_path.join("nested"))?;
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    let nested_manifest = fs::read_to_string(tempdir_path.join("nested").join("Cargo.toml"))?;
+    fs::write(
+        tempdir_path.join("nested").join("Cargo.toml"),
+        nested_manifest + "\n[workspace]\n",
+    )?;
+
+    let err = cargo_member::Include::new(tempdir_path, [tempdir_path.join("nested")])
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "`{}` is a workspace root. enable `--absorb` to dissolve it",
+            tempdir_path.join("nested"),
+        ),
+    );
+    assert_manifest(&tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+"#;
+}
+
 fn cargo_new(path: &Path) -> io::Result<()> {
     let cargo_exe = env::var("CARGO").unwrap();
     cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
@@ -0,0 +1,228 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn link_and_unlink() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-link-and-unlink")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let lock_path = tempdir_path.join("Cargo.lock");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Link::from_metadata(&metadata, "a", "b")
+        .offline(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    cargo_member::Link::from_metadata(&metadata, "b", "c")
+        .optional(true)
+        .features(["x"])
+        .offline(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("a").join("Cargo.toml"), EXPECTED_A)?;
+    assert_manifest(
+        &tempdir_path.join("b").join("Cargo.toml"),
+        EXPECTED_LINKED_B,
+    )?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_LINK_STDERR
+            .replace("{{a}}", tempdir_path.join("a").join("Cargo.toml").as_ref())
+            .replace("{{b}}", tempdir_path.join("b").join("Cargo.toml").as_ref())
+            .replace("{{lock}}", lock_path.as_ref()),
+    )?;
+
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let err = cargo_member::Link::from_metadata(&metadata, "c", "a")
+        .offline(true)
+        .dry_run(false)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        "linking `c` to `a` creates a cycle: c -> a -> b -> c",
+        err.to_string(),
+    );
+
+    cargo_member::Link::from_metadata(&metadata, "c", "a")
+        .kind(DependencyKind::Development)
+        .offline(true)
+        .dry_run(false)
+        .exec()?;
+
+    fs::write(
+        tempdir_path.join("b").join("Cargo.toml"),
+        EXPECTED_LINKED_B.replace("x = []\n", "x = []\ny = [\"c/x\", \"x\"]\n")
+            + "\n[dev-dependencies]\n",
+    )?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Unlink::from_metadata(&metadata, "b", "c")
+        .offline(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(
+        &tempdir_path.join("b").join("Cargo.toml"),
+        EXPECTED_UNLINKED_B,
+    )?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_UNLINK_STDERR
+            .replace("{{b}}", tempdir_path.join("b").join("Cargo.toml").as_ref())
+            .replace("{{lock}}", lock_path.as_ref()),
+    )?;
+    return Ok(());
+
+    static EXPECTED_A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[features]
+x = []
+
+[dependencies]
+b = { path = "../b", version = "0.1.0" }
+"#;
+
+    static EXPECTED_LINKED_B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+
+[features]
+x = []
+
+[dependencies]
+c = { workspace = true, features = ["x"], optional = true }
+"#;
+
+    static EXPECTED_LINK_STDERR: &str = r#"      Adding "b" to `dependencies` of {{a}}
+    Updating {{lock}}
+      Adding "c" to `dependencies` of {{b}}
+    Updating {{lock}}
+"#;
+
+    static EXPECTED_UNLINKED_B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+
+[features]
+x = []
+y = ["x"]
+
+[dev-dependencies]
+"#;
+
+    static EXPECTED_UNLINK_STDERR: &str = r#"    Removing "c" from `dependencies` of {{b}}
+    Removing "c/x" from `features.y`
+    Updating {{lock}}
+"#;
+}
+
+#[test]
+fn version_req() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-link-version-req")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let err = cargo_member::Link::from_metadata(&metadata, "a", "b")
+        .version_req(Some("0.2"))
+        .offline(true)
+        .exec()
+        .unwrap_err();
+    assert_eq!("`^0.2` does not match `b v0.1.0`", err.to_string());
+
+    cargo_member::Link::from_metadata(&metadata, "a", "b")
+        .version_req(Some("0.1"))
+        .offline(true)
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("a").join("Cargo.toml"), EXPECTED_A)?;
+    return Ok(());
+
+    static EXPECTED_A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[features]
+x = []
+
+[dependencies]
+b = { path = "../b", version = "^0.1" }
+"#;
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    for name in ["a", "b", "c"] {
+        fs::create_dir_all(root.join(name).join("src"))?;
+        fs::write(
+            root.join(name).join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[features]\nx = []\n",
+                name,
+            ),
+        )?;
+        fs::write(root.join(name).join("src").join("lib.rs"), "")?;
+    }
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b", "c"]
+resolver = "2"
+
+[workspace.dependencies]
+c = { path = "c", version = "0.1.0" }
+"#;
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
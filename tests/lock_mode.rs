@@ -0,0 +1,186 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_member::LockMode;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{fs, io};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn edit() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-lock-mode-edit")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    cargo_member::Exclude::new(tempdir_path, [tempdir_path.join("c")])
+        .lock_mode(LockMode::Edit)
+        .exec()?;
+    assert_file(&tempdir_path.join("Cargo.lock"), EXPECTED_AFTER_EXCLUDE)?;
+    cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--locked", "--offline"])?;
+
+    write_package(&tempdir_path.join("d"), D)?;
+    cargo_member::Include::new(tempdir_path, [tempdir_path.join("d")])
+        .lock_mode(LockMode::Edit)
+        .exec()?;
+    assert_file(&tempdir_path.join("Cargo.lock"), EXPECTED_AFTER_INCLUDE)?;
+    cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--locked", "--offline"])?;
+    return Ok(());
+
+    static EXPECTED_AFTER_EXCLUDE: &str = r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 4
+
+[[package]]
+name = "a"
+version = "0.1.0"
+dependencies = [
+ "b",
+]
+
+[[package]]
+name = "b"
+version = "0.1.0"
+"#;
+
+    static EXPECTED_AFTER_INCLUDE: &str = r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 4
+
+[[package]]
+name = "a"
+version = "0.1.0"
+dependencies = [
+ "b",
+]
+
+[[package]]
+name = "b"
+version = "0.1.0"
+
+[[package]]
+name = "d"
+version = "0.2.0"
+dependencies = [
+ "a",
+ "b",
+]
+"#;
+
+    static D: &str = r#"[package]
+name = "d"
+version = "0.2.0"
+edition = "2021"
+
+[dependencies]
+a = { path = "../a" }
+
+[dev-dependencies]
+b = { path = "../b" }
+"#;
+}
+
+#[test]
+fn edit_fallback() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-lock-mode-edit-fallback")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let lock_path = tempdir_path.join("Cargo.lock");
+
+    create_workspace(tempdir_path)?;
+    cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+    let orig = fs::read_to_string(&lock_path)?;
+    fs::write(&lock_path, orig.replace("version = 4\n", ""))?;
+
+    let mut stderr = vec![];
+    cargo_member::Rm::new(tempdir_path, [tempdir_path.join("c")])
+        .lock_mode(LockMode::Edit)
+        .offline(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+    let expected = format!(
+        "warning: could not edit {} in place: unsupported lockfile version. resolving the \
+         dependencies instead\n",
+        lock_path,
+    );
+    assert!(String::from_utf8(stderr)?.contains(&expected));
+    cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--locked", "--offline"])?;
+    Ok(())
+}
+
+#[test]
+fn skip() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-lock-mode-skip")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+    let orig = fs::read_to_string(tempdir_path.join("Cargo.lock"))?;
+
+    cargo_member::Rm::new(tempdir_path, [tempdir_path.join("c")])
+        .lock_mode(LockMode::Skip)
+        .exec()?;
+    assert_file(&tempdir_path.join("Cargo.lock"), &orig)?;
+    Ok(())
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    for (name, manifest) in [("a", A), ("b", B), ("c", C)] {
+        write_package(&root.join(name), manifest)?;
+    }
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b", "c"]
+resolver = "2"
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+b = { path = "../b" }
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static C: &str = r#"[package]
+name = "c"
+version = "0.1.0"
+edition = "2021"
+"#;
+}
+
+fn write_package(dir: &Path, manifest: &str) -> io::Result<()> {
+    fs::create_dir_all(dir.join("src"))?;
+    fs::write(dir.join("Cargo.toml"), manifest)?;
+    fs::write(dir.join("src").join("lib.rs"), "")
+}
+
+fn assert_file(path: &Path, expected: &str) -> io::Result<()> {
+    let actual = fs::read_to_string(path)?;
+    assert_diff!(expected, &actual, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
@@ -0,0 +1,221 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn merge() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-merge")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let (ws, other) = (tempdir_path.join("ws"), tempdir_path.join("other"));
+
+    let expected_stderr = EXPECTED_STDERR
+        .replace("{{ws}}", ws.as_ref())
+        .replace("{{other}}", other.as_ref());
+
+    write_package(&ws, "", WS)?;
+    write_package(&ws, "a", A)?;
+    write_package(&other, "", OTHER)?;
+    write_package(&other, "crates/x", X)?;
+    write_package(&other, "crates/y", Y)?;
+    cargo_metadata(&ws.join("Cargo.toml"), &[])?;
+    cargo_metadata(&other.join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Merge::new(&ws, &other)
+        .prefix("other")
+        .remove_source(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&ws.join("Cargo.toml"), EXPECTED_WS)?;
+    assert_manifest(
+        &ws.join("other").join("crates").join("x").join("Cargo.toml"),
+        EXPECTED_X,
+    )?;
+    assert_stderr(&stderr, &expected_stderr)?;
+    assert!(!other.join("Cargo.toml").exists());
+    assert!(!other.join("crates").join("x").exists());
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &["--locked"])?;
+    assert_eq!(metadata.workspace_members.len(), 3);
+    return Ok(());
+
+    static WS: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+
+[workspace.package]
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version.workspace = true
+edition.workspace = true
+"#;
+
+    static OTHER: &str = r#"[workspace]
+members = ["crates/*"]
+
+[workspace.package]
+version = "0.2.0"
+edition = "2021"
+
+[workspace.dependencies]
+y = { path = "crates/y" }
+"#;
+
+    static X: &str = r#"[package]
+name = "x"
+version.workspace = true
+edition.workspace = true
+
+[dependencies]
+y.workspace = true
+"#;
+
+    static Y: &str = r#"[package]
+name = "y"
+version = "0.2.0"
+edition.workspace = true
+"#;
+
+    static EXPECTED_WS: &str = r#"[workspace]
+members = ["a", "other/crates/x", "other/crates/y"]
+exclude = []
+
+[workspace.package]
+version = "0.1.0"
+edition = "2021"
+
+[workspace.dependencies]
+y = { path = "other/crates/y" }
+"#;
+
+    static EXPECTED_X: &str = r#"[package]
+name = "x"
+version = "0.2.0"
+edition.workspace = true
+
+[dependencies]
+y.workspace = true
+"#;
+
+    static EXPECTED_STDERR: &str = r#"      Adding "other/crates/x" to `workspace.members`
+      Adding "other/crates/y" to `workspace.members`
+warning: `workspace.package.version` conflicts. keeping the one in {{ws}}/Cargo.toml
+     Merging "y" into `workspace.dependencies`
+   Resolving conflicting inherited fields in {{other}}/crates/x/Cargo.toml
+      Moving `{{other}}/crates/x` to `{{ws}}/other/crates/x`
+    Removing directory `{{other}}/crates/x`
+      Moving `{{other}}/crates/y` to `{{ws}}/other/crates/y`
+    Removing directory `{{other}}/crates/y`
+     Merging {{other}}/Cargo.lock into {{ws}}/Cargo.lock
+    Removing {{other}}/Cargo.toml
+    Removing {{other}}/Cargo.lock
+    Updating {{ws}}/Cargo.lock
+"#;
+}
+
+#[test]
+fn failure_keeps_root_manifest() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-merge-failure-keeps-root-manifest")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let (ws, other) = (tempdir_path.join("ws"), tempdir_path.join("other"));
+
+    write_package(&ws, "", WS)?;
+    write_package(&ws, "a", A)?;
+    write_package(&other, "", OTHER)?;
+    write_package(&other, "x", X)?;
+    write_package(&other, "vendor/shell-escape", SHELL_ESCAPE)?;
+    cargo_metadata(&ws.join("Cargo.toml"), &["--offline"])?;
+    cargo_metadata(&other.join("Cargo.toml"), &["--offline"])?;
+
+    let err = cargo_member::Merge::new(&ws, &other)
+        .offline(true)
+        .exec()
+        .unwrap_err();
+
+    assert_eq!("`patch.crates-io` must be a table", err.to_string());
+    assert_manifest(&ws.join("Cargo.toml"), WS)?;
+    assert!(!ws.join("x").exists());
+    return Ok(());
+
+    static WS: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+
+[patch]
+crates-io = {}
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static OTHER: &str = r#"[workspace]
+members = ["x"]
+
+[patch.crates-io]
+shell-escape = { path = "vendor/shell-escape" }
+"#;
+
+    static X: &str = r#"[package]
+name = "x"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static SHELL_ESCAPE: &str = r#"[package]
+name = "shell-escape"
+version = "0.1.5"
+edition = "2015"
+"#;
+}
+
+fn write_package(workspace_root: &Path, dir: &str, manifest: &str) -> io::Result<()> {
+    let dir = workspace_root.join(dir);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("Cargo.toml"), manifest)?;
+    if manifest.starts_with("[package]") {
+        fs::create_dir_all(dir.join("src"))?;
+        fs::write(dir.join("src").join("lib.rs"), "")?;
+    }
+    Ok(())
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
@@ -18,7 +18,11 @@ fn mv() -> anyhow::Result<()> {
 
     let expected_stderr = EXPECTED_STDERR
         .replace("{{src}}", tempdir_path.join("ws").join("b").as_ref())
-        .replace("{{dst}}", tempdir_path.join("b").as_ref());
+        .replace("{{dst}}", tempdir_path.join("b").as_ref())
+        .replace(
+            "{{lock}}",
+            tempdir_path.join("ws").join("Cargo.lock").as_ref(),
+        );
 
     fs::create_dir(tempdir_path.join("ws"))?;
     fs::write(tempdir_path.join("ws").join("Cargo.toml"), ORIGINAL)?;
@@ -56,9 +60,251 @@ exclude = []
     static EXPECTED_STDERR: &str = r#"     Copying `{{src}}` to `{{dst}}`
     Removing directory `{{src}}`
     Removing "b" from `workspace.members`
+    Updating {{lock}}
 "#;
 }
 
+#[test]
+fn to_workspace() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-to-workspace")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let (ws1, ws2) = (tempdir_path.join("ws1"), tempdir_path.join("ws2"));
+
+    let expected_stderr = EXPECTED_STDERR
+        .replace("{{ws1}}", ws1.as_ref())
+        .replace("{{ws2}}", ws2.as_ref());
+
+    write_package(&ws1, "", WS1)?;
+    write_package(&ws1, "a", A)?;
+    write_package(&ws1, "b", B)?;
+    write_package(&ws1, "c", C)?;
+    write_package(&ws2, "", WS2)?;
+    write_package(&ws2, "x", X)?;
+    let metadata = cargo_metadata(&ws1.join("Cargo.toml"), &[])?;
+    cargo_metadata(&ws2.join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Mv::from_metadata(&metadata, "b", &ws2.join("b"))
+        .to_workspace(Some(&ws2))
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&ws1.join("Cargo.toml"), EXPECTED_WS1)?;
+    assert_manifest(&ws2.join("Cargo.toml"), EXPECTED_WS2)?;
+    assert_manifest(&ws2.join("b").join("Cargo.toml"), EXPECTED_B)?;
+    assert_stderr(&stderr, &expected_stderr)?;
+    assert!(!ws1.join("b").exists());
+
+    let mut stdout = vec![];
+    cargo_member::TrashList::from_metadata(&metadata)
+        .stdout(&mut stdout)
+        .exec()?;
+    let stdout = str::from_utf8(&stdout)?;
+    assert!(
+        stdout.ends_with(&format!(" {}\n", ws1.join("b"))),
+        "{}",
+        stdout
+    );
+
+    cargo_metadata(&ws1.join("Cargo.toml"), &["--locked"])?;
+    cargo_metadata(&ws2.join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static WS1: &str = r#"[workspace]
+members = ["a", "b", "c"]
+exclude = []
+
+[workspace.package]
+version = "0.1.0"
+edition = "2021"
+
+[workspace.dependencies]
+b = { path = "b" }
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version.workspace = true
+edition.workspace = true
+
+[dependencies]
+b.workspace = true
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version.workspace = true
+edition.workspace = true
+
+[dependencies]
+c = { path = "../c" }
+"#;
+
+    static C: &str = r#"[package]
+name = "c"
+version.workspace = true
+edition.workspace = true
+"#;
+
+    static WS2: &str = r#"[workspace]
+members = ["x"]
+exclude = []
+
+[workspace.package]
+version = "0.1.0"
+edition = "2018"
+"#;
+
+    static X: &str = r#"[package]
+name = "x"
+version = "0.1.0"
+edition = "2018"
+"#;
+
+    static EXPECTED_WS1: &str = r#"[workspace]
+members = ["a", "c"]
+exclude = []
+
+[workspace.package]
+version = "0.1.0"
+edition = "2021"
+
+[workspace.dependencies]
+b = { path = "../ws2/b" }
+"#;
+
+    static EXPECTED_WS2: &str = r#"[workspace]
+members = ["x", "b"]
+exclude = []
+
+[workspace.package]
+version = "0.1.0"
+edition = "2018"
+
+[workspace.dependencies]
+b = { path = "b" }
+"#;
+
+    static EXPECTED_B: &str = r#"[package]
+name = "b"
+version.workspace = true
+edition = "2021"
+
+[dependencies]
+c = { path = "../../ws1/c" }
+"#;
+
+    static EXPECTED_STDERR: &str = r#"      Moving `{{ws1}}/b` to `{{ws2}}/b`
+    Removing directory `{{ws1}}/b`
+    Removing "b" from `workspace.members`
+      Adding "b" to `workspace.members`
+    Rebasing "b" in `workspace.dependencies` of {{ws1}}/Cargo.toml
+      Adding "b" to `workspace.dependencies` of {{ws2}}/Cargo.toml
+    Updating {{ws1}}/Cargo.lock
+    Updating {{ws2}}/Cargo.lock
+warning: could not edit {{ws2}}/Cargo.lock in place: `c` (dependency of `b`) is not locked. resolving the dependencies instead
+"#;
+}
+
+#[test]
+fn to_workspace_rename() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-to-workspace-rename")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let (ws1, ws2) = (tempdir_path.join("ws1"), tempdir_path.join("ws2"));
+
+    let expected_stderr = EXPECTED_STDERR
+        .replace("{{ws1}}", ws1.as_ref())
+        .replace("{{ws2}}", ws2.as_ref());
+
+    write_package(&ws1, "", WS1)?;
+    write_package(&ws1, "a", A)?;
+    write_package(&ws1, "b", B)?;
+    write_package(&ws2, "", WS2)?;
+    let metadata = cargo_metadata(&ws1.join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Mv::from_metadata(&metadata, "b", &ws1.join("..").join("ws2").join("bee"))
+        .to_workspace(Some(&ws1.join("..").join("ws2")))
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&ws1.join("Cargo.toml"), EXPECTED_WS1)?;
+    assert_manifest(&ws2.join("Cargo.toml"), EXPECTED_WS2)?;
+    assert_stderr(&stderr, &expected_stderr)?;
+    cargo_metadata(&ws1.join("Cargo.toml"), &["--locked"])?;
+    cargo_metadata(&ws2.join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static WS1: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+
+[workspace.dependencies]
+b = { path = "b" }
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+b.workspace = true
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static WS2: &str = r#"[workspace]
+members = []
+exclude = []
+"#;
+
+    static EXPECTED_WS1: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+
+[workspace.dependencies]
+b = { path = "../ws2/bee", package = "bee" }
+"#;
+
+    static EXPECTED_WS2: &str = r#"[workspace]
+members = ["bee"]
+exclude = []
+
+[workspace.dependencies]
+bee = { path = "bee" }
+"#;
+
+    static EXPECTED_STDERR: &str = r#"      Moving `{{ws1}}/b` to `{{ws2}}/bee`
+    Removing directory `{{ws1}}/b`
+    Removing "b" from `workspace.members`
+      Adding "bee" to `workspace.members`
+    Rebasing "b" in `workspace.dependencies` of {{ws1}}/Cargo.toml
+      Adding "bee" to `workspace.dependencies` of {{ws2}}/Cargo.toml
+    Updating {{ws1}}/Cargo.lock
+warning: could not edit {{ws1}}/Cargo.lock in place: `bee` (dependency of `a`) is not locked. resolving the dependencies instead
+    Updating {{ws2}}/Cargo.lock
+"#;
+}
+
+fn write_package(workspace_root: &Path, dir: &str, manifest: &str) -> io::Result<()> {
+    let dir = workspace_root.join(dir);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("Cargo.toml"), manifest)?;
+    if manifest.starts_with("[package]") {
+        fs::create_dir_all(dir.join("src"))?;
+        fs::write(dir.join("src").join("lib.rs"), "")?;
+    }
+    Ok(())
+}
+
 fn cargo_new(path: &Path) -> io::Result<()> {
     let cargo_exe = env::var("CARGO").unwrap();
     cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
@@ -0,0 +1,234 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn patch_and_unpatch() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-patch-and-unpatch")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let ws = tempdir_path.join("ws");
+
+    create_workspace(&ws)?;
+    create_local_package(&tempdir_path.join("ext").join("shell-escape"), "0.1.6")?;
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &["--offline"])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Patch::from_metadata(
+        &metadata,
+        "shell-escape",
+        &ws.join("..").join("ext").join("shell-escape"),
+    )
+    .offline(true)
+    .dry_run(false)
+    .stderr(NoColor::new(&mut stderr))
+    .exec()?;
+
+    assert_manifest(&ws.join("Cargo.toml"), EXPECTED_PATCHED_ROOT)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_PATCH_STDERR.replace("{{lock}}", ws.join("Cargo.lock").as_ref()),
+    )?;
+
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &["--offline"])?;
+    let mut stdout = vec![];
+
+    cargo_member::PatchList::from_metadata(&metadata)
+        .stdout(&mut stdout)
+        .exec()?;
+
+    assert_eq!(
+        "patch.crates-io.shell-escape = ../ext/shell-escape (used)\n",
+        str::from_utf8(&stdout)?,
+    );
+
+    let mut stderr = vec![];
+
+    cargo_member::Unpatch::new(&ws, "shell-escape")
+        .offline(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&ws.join("Cargo.toml"), ROOT)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_UNPATCH_STDERR.replace("{{lock}}", ws.join("Cargo.lock").as_ref()),
+    )?;
+
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &["--offline"])?;
+    let shell_escape = metadata
+        .packages
+        .iter()
+        .find(|p| p.name == "shell-escape")
+        .expect("should exist");
+    assert!(shell_escape.source.is_some());
+    return Ok(());
+
+    static EXPECTED_PATCHED_ROOT: &str = r#"[workspace]
+members = ["a"]
+resolver = "2"
+
+[patch.crates-io]
+shell-escape = { path = "../ext/shell-escape" }
+"#;
+
+    static EXPECTED_PATCH_STDERR: &str = r#"      Adding "shell-escape" to `patch.crates-io`
+    Updating {{lock}}
+"#;
+
+    static EXPECTED_UNPATCH_STDERR: &str = r#"    Removing "shell-escape" from `patch.crates-io`
+    Updating {{lock}}
+"#;
+}
+
+#[test]
+fn incompatible_version() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-patch-incompatible-version")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let ws = tempdir_path.join("ws");
+    let local = tempdir_path.join("ext").join("shell-escape");
+
+    create_workspace(&ws)?;
+    create_local_package(&local, "0.2.0")?;
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &["--offline"])?;
+
+    let err = cargo_member::Patch::from_metadata(&metadata, "shell-escape", &local)
+        .offline(true)
+        .dry_run(false)
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        format!(
+            "`shell-escape v0.2.0` at `{}` does not match any of the requirements: ^0.1.5",
+            local,
+        ),
+        err.to_string(),
+    );
+    assert_manifest(&ws.join("Cargo.toml"), ROOT)?;
+    Ok(())
+}
+
+#[test]
+fn alternate_registry() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-patch-alternate-registry")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let ws = tempdir_path.join("ws");
+    let local = tempdir_path.join("ext").join("shell-escape");
+
+    fs::create_dir_all(ws.join(".cargo"))?;
+    fs::create_dir_all(ws.join("a").join("src"))?;
+    fs::write(ws.join(".cargo").join("config.toml"), CONFIG)?;
+    fs::write(ws.join("Cargo.toml"), ROOT)?;
+    fs::write(ws.join("a").join("Cargo.toml"), A)?;
+    fs::write(ws.join("a").join("src").join("lib.rs"), "")?;
+    create_local_package(&local, "0.1.6")?;
+    // Cargo reads `.cargo/config.toml` from the current directory, not the manifest's.
+    let metadata = MetadataCommand::new()
+        .manifest_path(ws.join("Cargo.toml"))
+        .current_dir(&ws)
+        .no_deps()
+        .other_options(vec!["--offline".to_owned()])
+        .exec()?;
+
+    let err = cargo_member::Patch::from_metadata(&metadata, "shell-escape", &local)
+        .registry(Some("my-registry"))
+        .dry_run(true)
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        format!(
+            "`shell-escape v0.1.6` at `{}` does not match any of the requirements: ^0.2",
+            local,
+        ),
+        err.to_string(),
+    );
+    assert_manifest(&ws.join("Cargo.toml"), ROOT)?;
+    return Ok(());
+
+    static CONFIG: &str = r#"[registries.my-registry]
+index = "https://my-registry.example.com/index"
+
+[registries.other-registry]
+index = "sparse+https://other-registry.example.com/index/"
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+shell-escape = "0.1.5"
+my-shell-escape = { package = "shell-escape", version = "0.2", registry = "my-registry" }
+other-shell-escape = { package = "shell-escape", version = "0.3", registry = "other-registry" }
+"#;
+}
+
+static ROOT: &str = r#"[workspace]
+members = ["a"]
+resolver = "2"
+"#;
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::create_dir_all(root.join("a").join("src"))?;
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    fs::write(root.join("a").join("Cargo.toml"), A)?;
+    fs::write(root.join("a").join("src").join("lib.rs"), "")?;
+    return Ok(());
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+shell-escape = "0.1.5"
+"#;
+}
+
+fn create_local_package(path: &Path, version: &str) -> io::Result<()> {
+    fs::create_dir_all(path.join("src"))?;
+    fs::write(
+        path.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"shell-escape\"\nversion = \"{}\"\nedition = \"2021\"\n",
+            version,
+        ),
+    )?;
+    fs::write(path.join("src").join("lib.rs"), "")
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
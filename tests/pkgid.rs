@@ -0,0 +1,113 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use std::{fs, io};
+use tempdir::TempDir;
+use url::Url;
+
+#[test]
+fn pkgid() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-pkgid")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let bar_url = Url::from_directory_path(tempdir_path.join("bar")).expect("absolute path");
+    let bar_url = bar_url.as_str().trim_end_matches('/');
+
+    for spec in [
+        "bar".to_owned(),
+        "bar@0.1".to_owned(),
+        "bar:0.1.0".to_owned(),
+        bar_url.to_owned(),
+        format!("{}#0.1.0", bar_url),
+        format!("path+{}#bar@0.1.0", bar_url),
+    ] {
+        exclude(&metadata, &spec).map_err(|e| anyhow::anyhow!("{}: {}", spec, e))?;
+    }
+
+    let err = |spec: &str| exclude(&metadata, spec).unwrap_err().to_string();
+
+    assert_eq!(
+        "There are multiple `foo` packages in your project, and the specification `foo` is \
+         ambiguous.\n\
+         Please re-run this command with one of the following specifications:\n  \
+         foo@0.1.0\n  \
+         foo@0.2.0",
+        err("foo"),
+    );
+    assert_eq!(
+        "package `foo@0.2.0` is not a member of the workspace",
+        err("foo@0.2.0"),
+    );
+    assert_eq!(
+        "package ID specification `baz` did not match any packages",
+        err("baz"),
+    );
+    assert_eq!("invalid package ID specification: `foo@`", err("foo@"));
+    assert_eq!(
+        "package ID specification `registry+https://github.com/rust-lang/crates.io-index#bar` \
+         did not match any packages",
+        err("registry+https://github.com/rust-lang/crates.io-index#bar"),
+    );
+    Ok(())
+}
+
+fn exclude(metadata: &Metadata, spec: &str) -> anyhow::Result<()> {
+    cargo_member::Exclude::from_metadata(metadata, None::<&Path>, [spec])
+        .dry_run(true)
+        .exec()
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    for (dir, manifest) in [("foo", FOO), ("bar", BAR), ("vendor/foo", VENDORED_FOO)] {
+        fs::create_dir_all(root.join(dir).join("src"))?;
+        fs::write(root.join(dir).join("Cargo.toml"), manifest)?;
+        fs::write(root.join(dir).join("src").join("lib.rs"), "")?;
+    }
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["foo", "bar"]
+exclude = ["vendor/foo"]
+resolver = "2"
+"#;
+
+    static FOO: &str = r#"[package]
+name = "foo"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static BAR: &str = r#"[package]
+name = "bar"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+foo = { path = "../foo" }
+old-foo = { package = "foo", path = "../vendor/foo" }
+"#;
+
+    static VENDORED_FOO: &str = r#"[package]
+name = "foo"
+version = "0.2.0"
+edition = "2021"
+"#;
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
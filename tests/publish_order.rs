@@ -0,0 +1,137 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn publish_order() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-publish-order")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let (mut stdout, mut stderr) = (vec![], vec![]);
+
+    cargo_member::PublishOrder::from_metadata(&metadata)
+        .stdout(&mut stdout)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_eq!("a\nb e\nd\n", str::from_utf8(&stdout)?);
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+
+    let mut stdout = vec![];
+
+    cargo_member::PublishOrder::from_metadata(&metadata)
+        .json(true)
+        .stdout(&mut stdout)
+        .exec()?;
+
+    let json = serde_json::from_slice::<serde_json::Value>(&stdout)?;
+    let names = json["levels"]
+        .as_array()
+        .expect("should be an array")
+        .iter()
+        .map(|level| {
+            level
+                .as_array()
+                .expect("should be an array")
+                .iter()
+                .map(|p| p["name"].as_str().expect("should be a string"))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(vec![vec!["a"], vec!["b", "e"], vec!["d"]], names);
+    return Ok(());
+
+    static EXPECTED_STDERR: &str = r#"warning: `e` has a path dependency on `a` without `version`, which blocks publishing
+"#;
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    for (name, manifest) in [("a", A), ("b", B), ("c", C), ("d", D), ("e", E)] {
+        fs::create_dir_all(root.join(name).join("src"))?;
+        fs::write(root.join(name).join("Cargo.toml"), manifest)?;
+        fs::write(root.join(name).join("src").join("lib.rs"), "")?;
+    }
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b", "c", "d", "e"]
+resolver = "2"
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+a = { path = "../a", version = "0.1.0" }
+"#;
+
+    static C: &str = r#"[package]
+name = "c"
+version = "0.1.0"
+edition = "2021"
+publish = false
+
+[dependencies]
+a = { path = "../a" }
+"#;
+
+    static D: &str = r#"[package]
+name = "d"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+b = { path = "../b", version = "0.1.0" }
+e = { path = "../e", version = "0.1.0", optional = true }
+
+[dev-dependencies]
+c = { path = "../c" }
+"#;
+
+    static E: &str = r#"[package]
+name = "e"
+version = "0.1.0"
+edition = "2021"
+
+[build-dependencies]
+a = { path = "../a" }
+"#;
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
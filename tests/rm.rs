@@ -1,6 +1,7 @@
 #![warn(rust_2018_idioms)]
 
 use camino::Utf8Path as Path;
+use cargo_member::cli::{Cargo, Context};
 use cargo_metadata::{Metadata, MetadataCommand};
 use difference::assert_diff;
 use duct::cmd;
@@ -8,6 +9,7 @@ use std::{
     env, fs, io,
     str::{self, Utf8Error},
 };
+use structopt::StructOpt as _;
 use tempdir::TempDir;
 use termcolor::NoColor;
 
@@ -18,7 +20,8 @@ fn rm() -> anyhow::Result<()> {
 
     let expected_stderr = EXPECTED_STDERR
         .replace("{{b}}", tempdir_path.join("b").as_ref())
-        .replace("{{c}}", tempdir_path.join("c").as_ref());
+        .replace("{{c}}", tempdir_path.join("c").as_ref())
+        .replace("{{lock}}", tempdir_path.join("Cargo.lock").as_ref());
 
     fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
     cargo_new(&tempdir_path.join("a"))?;
@@ -53,9 +56,81 @@ exclude = []
     Removing "b" from `workspace.members`
     Removing directory `{{c}}`
     Removing "c" from `workspace.members`
+    Updating {{lock}}
 "#;
 }
 
+#[test]
+fn missing() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-rm-missing")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir_path.join("a"))?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &[])?;
+
+    for permanent in [false, true] {
+        let err = cargo_member::Rm::new(
+            &metadata.workspace_root,
+            [tempdir_path.join("a"), tempdir_path.join("missing")],
+        )
+        .permanent(permanent)
+        .exec()
+        .unwrap_err();
+        assert_eq!(
+            format!(
+                "`{}` does not seem to be a package. enable `--force` to remove",
+                tempdir_path.join("missing"),
+            ),
+            err.to_string(),
+        );
+        assert_manifest(&tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+        assert!(tempdir_path.join("a").join("Cargo.toml").exists());
+    }
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+"#;
+}
+
+#[test]
+fn exclude_without_all() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-rm-exclude-without-all")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let manifest_path = tempdir_path.join("Cargo.toml");
+
+    fs::write(&manifest_path, ORIGINAL)?;
+    cargo_new(&tempdir_path.join("a"))?;
+    cargo_new(&tempdir_path.join("b"))?;
+
+    let err = run(&[
+        "rm",
+        "--manifest-path",
+        manifest_path.as_ref(),
+        "--exclude",
+        "a",
+    ])
+    .unwrap_err();
+    assert_eq!("`--exclude` can only be used with `--all`", err.to_string());
+    assert_manifest(&manifest_path, ORIGINAL)?;
+    assert!(tempdir_path.join("a").exists());
+    assert!(tempdir_path.join("b").exists());
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+"#;
+}
+
+fn run(args: &[&str]) -> anyhow::Result<()> {
+    let Cargo::Member(opt) =
+        Cargo::from_iter_safe(["cargo", "member"].iter().chain(args).chain(&["--offline"]))?;
+    cargo_member::cli::run(opt, Context::new(NoColor::new(vec![]))?)
+}
+
 fn cargo_new(path: &Path) -> io::Result<()> {
     let cargo_exe = env::var("CARGO").unwrap();
     cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
@@ -0,0 +1,150 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_member::Selection;
+use cargo_metadata::{Metadata, MetadataCommand};
+use std::{fs, io, str};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn selection() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-selection")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let select = |selection: Selection| -> anyhow::Result<(Vec<String>, String)> {
+        let mut stderr = vec![];
+        let names = selection
+            .select(&metadata, NoColor::new(&mut stderr))?
+            .into_iter()
+            .map(|p| p.name.clone())
+            .collect();
+        Ok((names, String::from_utf8(stderr)?))
+    };
+
+    assert_eq!((vec![], "".to_owned()), select(Selection::new())?,);
+    assert_eq!(
+        (vec!["storage-a".to_owned()], "".to_owned()),
+        select(Selection::new().specs(["storage-a"]))?,
+    );
+    assert_eq!(
+        (
+            vec!["storage-a".to_owned(), "storage-b".to_owned()],
+            "    Selected storage-a, storage-b\n".to_owned(),
+        ),
+        select(Selection::new().specs(["storage-*"]))?,
+    );
+    assert_eq!(
+        (
+            vec!["app".to_owned(), "storage-b".to_owned()],
+            "    Selected app, storage-b\n".to_owned(),
+        ),
+        select(Selection::new().all(true).exclude(["storage-a", "util"]))?,
+    );
+    assert_eq!(
+        vec!["storage-a", "storage-b"],
+        select(Selection::new().paths(["crates/*"]))?.0,
+    );
+    assert_eq!(
+        vec!["app", "storage-b"],
+        select(Selection::new().dependents_of(["storage-a"]))?.0,
+    );
+    assert_eq!(
+        vec!["storage-a", "storage-b", "util"],
+        select(Selection::new().dependencies_of(["app"]))?.0,
+    );
+    assert_eq!(
+        vec!["storage-b"],
+        select(Selection::new().all(true).has_feature(["fast"]))?.0,
+    );
+    assert_eq!(
+        (vec![], "warning: no members are selected\n".to_owned()),
+        select(Selection::new().specs(["util"]).has_feature(["fast"]))?,
+    );
+
+    let err = select(Selection::new().specs(["nope-*"])).unwrap_err();
+    assert_eq!("`nope-*` did not match any members", err.to_string());
+    let err = select(Selection::new().exclude(["util"])).unwrap_err();
+    assert_eq!("`--exclude` can only be used with `--all`", err.to_string());
+    let err = select(Selection::new().has_feature(["fast"])).unwrap_err();
+    assert_eq!(
+        "`--has-feature` requires members to filter. specify packages or `--all`",
+        err.to_string(),
+    );
+    Ok(())
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    for (dir, manifest) in [
+        ("app", APP),
+        ("util", UTIL),
+        ("crates/storage-a", STORAGE_A),
+        ("crates/storage-b", STORAGE_B),
+    ] {
+        fs::create_dir_all(root.join(dir).join("src"))?;
+        fs::write(root.join(dir).join("Cargo.toml"), manifest)?;
+        fs::write(root.join(dir).join("src").join("lib.rs"), "")?;
+    }
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["app", "util", "crates/*"]
+resolver = "2"
+"#;
+
+    static APP: &str = r#"[package]
+name = "app"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+storage-b = { path = "../crates/storage-b" }
+
+[dev-dependencies]
+util = { path = "../util" }
+"#;
+
+    static UTIL: &str = r#"[package]
+name = "util"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static STORAGE_A: &str = r#"[package]
+name = "storage-a"
+version = "0.1.0"
+edition = "2021"
+
+[build-dependencies]
+util = { path = "../../util" }
+"#;
+
+    static STORAGE_B: &str = r#"[package]
+name = "storage-b"
+version = "0.1.0"
+edition = "2021"
+
+[features]
+fast = []
+
+[dependencies]
+storage-a = { path = "../storage-a" }
+"#;
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
@@ -0,0 +1,137 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use difference::assert_diff;
+use std::{
+    fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn normal() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-sort-normal")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Sort::new(tempdir_path)
+        .multiline_threshold(Some(2))
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = [
+    "d", # owned by someone else
+    # the core
+    "a",
+    "c",
+    "./a",
+    "b",
+]
+exclude = ["z", "y"]
+default-members = [
+    "b",
+    "a",
+]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    # the core
+    "a",
+    "b",
+    "c",
+    "d", # owned by someone else
+]
+exclude = ["y", "z"]
+default-members = ["a", "b"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"     Sorting `workspace.members`
+     Sorting `workspace.exclude`
+     Sorting `workspace.default-members`
+"#;
+}
+
+#[test]
+fn keep_layout() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-sort-keep-layout")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Sort::new(tempdir_path)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = [
+  "b",
+  "a"
+]
+exclude = ["d", "c"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+  "a",
+  "b",
+]
+exclude = ["c", "d"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"     Sorting `workspace.members`
+     Sorting `workspace.exclude`
+"#;
+}
+
+#[test]
+fn check() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-sort-check")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    fs::write(tempdir_path.join("Cargo.toml"), SORTED)?;
+    cargo_member::Sort::new(tempdir_path).check(true).exec()?;
+
+    fs::write(tempdir_path.join("Cargo.toml"), UNSORTED)?;
+    let err = cargo_member::Sort::new(tempdir_path)
+        .check(true)
+        .exec()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "`workspace.members` not sorted");
+    assert_manifest(&tempdir_path.join("Cargo.toml"), UNSORTED)?;
+    return Ok(());
+
+    static SORTED: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+    static UNSORTED: &str = r#"[workspace]
+members = ["b", "a"]
+"#;
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
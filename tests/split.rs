@@ -0,0 +1,288 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    env, fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn split() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-split")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let (ws, new_ws) = (tempdir_path.join("ws"), tempdir_path.join("new-ws"));
+
+    let expected_stderr = EXPECTED_STDERR
+        .replace("{{ws}}", ws.as_ref())
+        .replace("{{new_ws}}", new_ws.as_ref());
+
+    create_workspace(&ws)?;
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Split::from_metadata(&metadata, ["b", "c"], &new_ws)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&ws.join("Cargo.toml"), EXPECTED_WS)?;
+    assert_manifest(&new_ws.join("Cargo.toml"), EXPECTED_NEW_WS)?;
+    assert_stderr(&stderr, &expected_stderr)?;
+    assert!(!ws.join("crates").join("b").exists());
+    assert!(new_ws.join("crates").join("b").join("src").exists());
+    let metadata = cargo_metadata(&new_ws.join("Cargo.toml"), &["--locked"])?;
+    assert_eq!(metadata.workspace_members.len(), 2);
+    cargo_metadata(&ws.join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static EXPECTED_WS: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+
+[workspace.package]
+version = "0.1.0"
+edition = "2021"
+license = "MIT"
+"#;
+
+    static EXPECTED_NEW_WS: &str = r#"[workspace]
+members = ["crates/b", "crates/c"]
+
+[workspace.package]
+version = "0.1.0"
+edition = "2021"
+
+[workspace.dependencies]
+c = { path = "crates/c" }
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Creating workspace at {{new_ws}}
+      Moving `{{ws}}/crates/b` to `{{new_ws}}/crates/b`
+    Removing directory `{{ws}}/crates/b`
+      Moving `{{ws}}/crates/c` to `{{new_ws}}/crates/c`
+    Removing directory `{{ws}}/crates/c`
+     Writing {{new_ws}}/Cargo.lock
+    Removing "crates/b" from `workspace.members`
+    Removing "crates/c" from `workspace.members`
+    Removing "c" from `workspace.dependencies`
+    Updating {{new_ws}}/Cargo.lock
+    Updating {{ws}}/Cargo.lock
+"#;
+}
+
+#[test]
+fn remaining_dependents() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-split-remaining-dependents")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let (ws, new_ws) = (tempdir_path.join("ws"), tempdir_path.join("new-ws"));
+
+    create_workspace(&ws)?;
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &[])?;
+
+    let err = cargo_member::Split::from_metadata(&metadata, ["c"], &new_ws)
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "`b` depends on `c`, which is going to be split",
+    );
+    assert!(!new_ws.exists());
+    Ok(())
+}
+
+#[test]
+fn optional_dependencies() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-split-optional-dependencies")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let (ws, new_ws) = (tempdir_path.join("ws"), tempdir_path.join("new-ws"));
+
+    for (dir, manifest) in [("", ROOT), ("a", A), ("b", B)] {
+        let dir = ws.join(dir);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("Cargo.toml"), manifest)?;
+        if manifest.starts_with("[package]") {
+            fs::create_dir_all(dir.join("src"))?;
+            fs::write(dir.join("src").join("lib.rs"), "")?;
+        }
+    }
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(&cargo_exe, "generate-lockfile", "--offline")
+        .dir(&ws)
+        .stderr_null()
+        .run()?;
+    cmd!(
+        &cargo_exe,
+        "update",
+        "--offline",
+        "-p",
+        "camino",
+        "--precise",
+        "1.1.6"
+    )
+    .dir(&ws)
+    .stderr_null()
+    .run()?;
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &["--offline"])?;
+
+    cargo_member::Split::from_metadata(&metadata, ["b"], &new_ws)
+        .offline(true)
+        .exec()?;
+
+    cargo_metadata(&new_ws.join("Cargo.toml"), &["--offline", "--locked"])?;
+    let lock = fs::read_to_string(new_ws.join("Cargo.lock"))?;
+    assert!(
+        lock.contains("name = \"camino\"\nversion = \"1.1.6\"\n"),
+        "{}",
+        lock
+    );
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b"]
+resolver = "2"
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+camino = { version = "1.0", optional = true }
+"#;
+}
+
+#[test]
+fn remaining_optional_dependents() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-split-remaining-optional-dependents")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let (ws, new_ws) = (tempdir_path.join("ws"), tempdir_path.join("new-ws"));
+
+    for (dir, manifest) in [("", ROOT), ("a", A), ("b", B)] {
+        let dir = ws.join(dir);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("Cargo.toml"), manifest)?;
+        if manifest.starts_with("[package]") {
+            fs::create_dir_all(dir.join("src"))?;
+            fs::write(dir.join("src").join("lib.rs"), "")?;
+        }
+    }
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &["--offline"])?;
+
+    let err = cargo_member::Split::from_metadata(&metadata, ["b"], &new_ws)
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "`a` depends on `b`, which is going to be split",
+    );
+    assert!(!new_ws.exists());
+    assert!(ws.join("b").join("Cargo.toml").exists());
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b"]
+resolver = "2"
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+b = { path = "../b", optional = true }
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2021"
+"#;
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    for (dir, manifest) in [("", ROOT), ("a", A), ("crates/b", B), ("crates/c", C)] {
+        let dir = root.join(dir);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("Cargo.toml"), manifest)?;
+        if manifest.starts_with("[package]") {
+            fs::create_dir_all(dir.join("src"))?;
+            fs::write(dir.join("src").join("lib.rs"), "")?;
+        }
+    }
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "crates/b", "crates/c"]
+exclude = []
+
+[workspace.package]
+version = "0.1.0"
+edition = "2021"
+license = "MIT"
+
+[workspace.dependencies]
+c = { path = "crates/c" }
+"#;
+
+    static A: &str = r#"[package]
+name = "a"
+version.workspace = true
+edition.workspace = true
+license.workspace = true
+"#;
+
+    static B: &str = r#"[package]
+name = "b"
+version.workspace = true
+edition.workspace = true
+
+[dependencies]
+c.workspace = true
+"#;
+
+    static C: &str = r#"[package]
+name = "c"
+version.workspace = true
+edition.workspace = true
+"#;
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
@@ -0,0 +1,135 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    env, fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn pop() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-stash-pop")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    for name in ["a", "b", "heavy", "new"] {
+        cargo_new(&tempdir_path.join(name))?;
+    }
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &[])?;
+
+    cargo_member::StashPush::from_metadata(&metadata)
+        .name(Some("normal"))
+        .exec()?;
+
+    fs::write(tempdir_path.join("Cargo.toml"), MODIFIED)?;
+
+    let mut stdout = vec![];
+    cargo_member::StashList::from_metadata(&metadata)
+        .stdout(&mut stdout)
+        .exec()?;
+    assert_diff!(EXPECTED_LIST, str::from_utf8(&stdout)?, "\n", 0);
+
+    let mut stderr = vec![];
+    cargo_member::StashApply::from_metadata(&metadata)
+        .pop(true)
+        .lock_mode(cargo_member::LockMode::Skip)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+
+    let mut stdout = vec![];
+    cargo_member::StashList::from_metadata(&metadata)
+        .stdout(&mut stdout)
+        .exec()?;
+    assert!(stdout.is_empty());
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = ["heavy"]
+"#;
+
+    static MODIFIED: &str = r#"[workspace]
+members = ["a", "b", "heavy", "new"]
+exclude = []
+default-members = ["a"]
+"#;
+
+    static EXPECTED_LIST: &str = r#"0: normal: members = ["a", "b"], exclude = ["heavy"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a", "b", "new"]
+exclude = ["heavy"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Removing "heavy" from `workspace.members`
+      Adding "heavy" to `workspace.exclude`
+    Removing "a" from `workspace.default-members`
+    Dropping normal: members = ["a", "b"], exclude = ["heavy"]
+"#;
+}
+
+#[test]
+fn unknown() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-stash-unknown")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    cargo_new(&tempdir_path.join("a"))?;
+    fs::write(tempdir_path.join("Cargo.toml"), MANIFEST)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &[])?;
+
+    let err = cargo_member::StashApply::from_metadata(&metadata)
+        .exec()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "no stash entries found");
+
+    cargo_member::StashPush::from_metadata(&metadata).exec()?;
+    let err = cargo_member::StashApply::from_metadata(&metadata)
+        .stash(Some("1"))
+        .exec()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "stash `1` does not exist");
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
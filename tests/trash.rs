@@ -0,0 +1,140 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    env, fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn restore() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-trash-restore")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    let expected_stderr = EXPECTED_STDERR.replace("{{b}}", tempdir_path.join("b").as_ref());
+
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir_path.join("a"))?;
+    cargo_new(&tempdir_path.join("b"))?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &[])?;
+
+    cargo_member::Rm::new(&metadata.workspace_root, [tempdir_path.join("b")])
+        .target_directory(&metadata.target_directory)
+        .lock_mode(cargo_member::LockMode::Skip)
+        .exec()?;
+    assert!(!tempdir_path.join("b").exists());
+
+    let mut stdout = vec![];
+    cargo_member::TrashList::from_metadata(&metadata)
+        .stdout(&mut stdout)
+        .exec()?;
+    let stdout = str::from_utf8(&stdout)?;
+    assert!(stdout.starts_with("0: "), "{}", stdout);
+    assert!(stdout.ends_with(&format!(" {}\n", tempdir_path.join("b"))));
+
+    let mut stderr = vec![];
+
+    cargo_member::TrashRestore::from_metadata(&metadata)
+        .item(Some("b"))
+        .lock_mode(cargo_member::LockMode::Skip)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, &expected_stderr)?;
+    assert!(tempdir_path.join("b").join("Cargo.toml").exists());
+
+    let mut stdout = vec![];
+    cargo_member::TrashList::from_metadata(&metadata)
+        .stdout(&mut stdout)
+        .exec()?;
+    assert!(stdout.is_empty());
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+"#;
+
+    static EXPECTED_STDERR: &str = r#"   Restoring directory `{{b}}`
+      Adding "b" to `workspace.members`
+"#;
+}
+
+#[test]
+fn permanent() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-trash-permanent")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    fs::write(tempdir_path.join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir_path.join("a"))?;
+    cargo_new(&tempdir_path.join("b"))?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &[])?;
+
+    cargo_member::Rm::new(&metadata.workspace_root, [tempdir_path.join("b")])
+        .permanent(true)
+        .lock_mode(cargo_member::LockMode::Skip)
+        .journal(Some(&metadata.target_directory))
+        .exec()?;
+    assert!(!tempdir_path.join("b").exists());
+
+    let err = cargo_member::TrashRestore::from_metadata(&metadata)
+        .exec()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "the trash is empty");
+
+    let err = cargo_member::Undo::from_metadata(&metadata)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "refusing to undo:\n  `{}` was deleted permanently by `rm`",
+            tempdir_path.join("b"),
+        ),
+    );
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
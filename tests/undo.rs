@@ -0,0 +1,127 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    env, fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn rm() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-undo-rm")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    let expected_stderr = EXPECTED_STDERR
+        .replace("{{b}}", tempdir_path.join("b").as_ref())
+        .replace("{{manifest}}", tempdir_path.join("Cargo.toml").as_ref());
+
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir_path.join("a"))?;
+    cargo_new(&tempdir_path.join("b"))?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &[])?;
+
+    cargo_member::Rm::new(&metadata.workspace_root, [tempdir_path.join("b")])
+        .lock_mode(cargo_member::LockMode::Skip)
+        .journal(Some(&metadata.target_directory))
+        .exec()?;
+    assert!(!tempdir_path.join("b").exists());
+
+    let mut stderr = vec![];
+
+    cargo_member::Undo::from_metadata(&metadata)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    assert_stderr(&stderr, &expected_stderr)?;
+    assert!(tempdir_path.join("b").join("Cargo.toml").exists());
+
+    let err = cargo_member::Undo::from_metadata(&metadata)
+        .exec()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "nothing to undo");
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"     Undoing `rm`
+   Restoring directory `{{b}}`
+   Restoring {{manifest}}
+"#;
+}
+
+#[test]
+fn conflict() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-undo-conflict")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    fs::write(tempdir_path.join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir_path.join("a"))?;
+    cargo_new(&tempdir_path.join("b"))?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &[])?;
+
+    cargo_member::Exclude::new(&metadata.workspace_root, [tempdir_path.join("b")])
+        .journal(Some(&metadata.target_directory))
+        .exec()?;
+    fs::write(tempdir_path.join("Cargo.toml"), EDITED)?;
+
+    let err = cargo_member::Undo::from_metadata(&metadata)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "refusing to undo:\n  `{}` has changed since `exclude`",
+            tempdir_path.join("Cargo.toml"),
+        ),
+    );
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EDITED)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+    static EDITED: &str = r#"[workspace]
+members = ["a"]
+exclude = ["b"]
+resolver = "2"
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
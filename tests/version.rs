@@ -0,0 +1,187 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_member::Bump;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn cascade() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-version-cascade")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Version::from_metadata(&metadata, ["a"], Bump::Minor)
+        .cascade(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), EXPECTED_ROOT)?;
+    assert_manifest(&tempdir_path.join("a").join("Cargo.toml"), EXPECTED_A)?;
+    assert_manifest(&tempdir_path.join("b").join("Cargo.toml"), B)?;
+    assert_manifest(&tempdir_path.join("d").join("Cargo.toml"), EXPECTED_D)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{{root}}", tempdir_path.as_ref()),
+    )?;
+
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline", "--locked"])?;
+    let c = metadata
+        .packages
+        .iter()
+        .find(|p| p.name == "c")
+        .expect("should exist");
+    assert_eq!("0.4.0", c.version.to_string());
+    return Ok(());
+
+    static EXPECTED_ROOT: &str = r#"[workspace]
+members = ["a", "b", "c", "d"]
+resolver = "2"
+
+[workspace.package]
+version = "0.4.0" # shared
+
+[workspace.dependencies]
+a = { path = "a", version = "0.2" }
+"#;
+
+    static EXPECTED_A: &str = r#"[package]
+name = "a"
+version = "0.2.0"
+edition = "2021"
+"#;
+
+    static EXPECTED_D: &str = r#"[package]
+name = "d"
+version = "1.1.0"
+edition = "2021"
+
+[dependencies]
+b = { path = "../b", version = "=0.4.0" }
+
+[dev-dependencies]
+a = { path = "../a", version = "0.2.0" }
+"#;
+
+    static EXPECTED_STDERR: &str = r#"     Bumping `a` from 0.1.2 to 0.2.0
+     Bumping `b` from 0.3.0 to 0.4.0
+     Bumping `c` from 0.3.0 to 0.4.0
+     Bumping `d` from 1.0.0 to 1.1.0
+    Updating requirement on "a" from "0.1" to "0.2" in {{root}}/Cargo.toml
+    Updating requirement on "b" from "=0.3.0" to "=0.4.0" in {{root}}/d/Cargo.toml
+    Updating requirement on "a" from "0.1.2" to "0.2.0" in {{root}}/d/Cargo.toml
+    Updating {{root}}/Cargo.lock
+"#;
+}
+
+#[test]
+fn exact() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-version-exact")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+
+    create_workspace(tempdir_path)?;
+    let metadata = cargo_metadata(&tempdir_path.join("Cargo.toml"), &["--offline"])?;
+
+    cargo_member::Version::from_metadata(&metadata, ["d"], "1.0.1-alpha.1".parse()?)
+        .dry_run(false)
+        .exec()?;
+
+    assert_manifest(&tempdir_path.join("Cargo.toml"), ROOT)?;
+    assert_manifest(
+        &tempdir_path.join("d").join("Cargo.toml"),
+        &D.replace("1.0.0", "1.0.1-alpha.1"),
+    )?;
+    Ok(())
+}
+
+static ROOT: &str = r#"[workspace]
+members = ["a", "b", "c", "d"]
+resolver = "2"
+
+[workspace.package]
+version = "0.3.0" # shared
+
+[workspace.dependencies]
+a = { path = "a", version = "0.1" }
+"#;
+
+static B: &str = r#"[package]
+name = "b"
+version.workspace = true
+edition = "2021"
+
+[dependencies]
+a.workspace = true
+"#;
+
+static D: &str = r#"[package]
+name = "d"
+version = "1.0.0"
+edition = "2021"
+
+[dependencies]
+b = { path = "../b", version = "=0.3.0" }
+
+[dev-dependencies]
+a = { path = "../a", version = "0.1.2" }
+"#;
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    for name in ["a", "b", "c", "d"] {
+        fs::create_dir_all(root.join(name).join("src"))?;
+        fs::write(root.join(name).join("src").join("lib.rs"), "")?;
+    }
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    fs::write(root.join("a").join("Cargo.toml"), A)?;
+    fs::write(root.join("b").join("Cargo.toml"), B)?;
+    fs::write(root.join("c").join("Cargo.toml"), C)?;
+    fs::write(root.join("d").join("Cargo.toml"), D)?;
+    return Ok(());
+
+    static A: &str = r#"[package]
+name = "a"
+version = "0.1.2"
+edition = "2021"
+"#;
+
+    static C: &str = r#"[package]
+name = "c"
+version.workspace = true
+edition = "2021"
+"#;
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
@@ -0,0 +1,121 @@
+#![warn(rust_2018_idioms)]
+
+use camino::Utf8Path as Path;
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn which() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-which")?;
+    let tempdir_path = Path::from_path(tempdir.path()).expect("invalid utf8 path");
+    let ws = tempdir_path.join("ws");
+
+    create_workspace(&ws)?;
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &["--offline"])?;
+
+    let paths = [
+        ws.join("a").join("src").join("lib.rs"),
+        ws.join("x").join("src").join("new.rs"),
+        ws.join("y").join("Cargo.toml"),
+        ws.join("nested").join("inner").join("z.rs"),
+        ws.join("README.md"),
+        tempdir_path.join("outside.rs"),
+    ];
+    let (mut stdout, mut stderr) = (vec![], vec![]);
+
+    cargo_member::Which::from_metadata(&metadata, &paths)
+        .stdout(&mut stdout)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_diff!(EXPECTED_STDOUT, str::from_utf8(&stdout)?, "\n", 0);
+    assert_stderr(
+        &stderr,
+        &format!(
+            "warning: `{}` is outside of the workspace\n",
+            tempdir_path.join("outside.rs"),
+        ),
+    )?;
+
+    let mut stdout = vec![];
+
+    cargo_member::Which::from_metadata(&metadata, &paths)
+        .json(true)
+        .stdout(&mut stdout)
+        .exec()?;
+
+    let json = serde_json::from_slice::<serde_json::Value>(&stdout)?;
+    let statuses = json
+        .as_array()
+        .expect("should be an array")
+        .iter()
+        .map(|p| p["owner"]["status"].as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            Some("member"),
+            Some("excluded"),
+            Some("inactive"),
+            Some("inactive"),
+            Some("root"),
+            None,
+        ],
+        statuses,
+    );
+    return Ok(());
+
+    static EXPECTED_STDOUT: &str = r#"a/src/lib.rs: a
+x/src/new.rs: x
+y/Cargo.toml: y
+nested/inner/z.rs: nested/Cargo.toml
+README.md: Cargo.toml
+"#;
+}
+
+fn create_workspace(root: &Path) -> io::Result<()> {
+    fs::create_dir_all(root.join("nested"))?;
+    fs::write(root.join("Cargo.toml"), ROOT)?;
+    fs::write(root.join("nested").join("Cargo.toml"), "[workspace]\n")?;
+    for name in ["a", "x", "y"] {
+        fs::create_dir_all(root.join(name).join("src"))?;
+        fs::write(
+            root.join(name).join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+                name,
+            ),
+        )?;
+        fs::write(root.join(name).join("src").join("lib.rs"), "")?;
+    }
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a"]
+exclude = ["x"]
+resolver = "2"
+"#;
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}